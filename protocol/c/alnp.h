@@ -26,16 +26,29 @@ typedef struct {
   alnp_bytes_t signature;
 } alnp_signed_reply_t;
 
+// Channel formats; the wire packs each slot big-endian at the given width.
 typedef enum {
-  ALNP_CHANNEL_U8 = 0,
-  ALNP_CHANNEL_U16 = 1
+  ALNP_CHANNEL_U8 = 0,    // 1 byte
+  ALNP_CHANNEL_U16 = 1,   // 2 bytes
+  ALNP_CHANNEL_U24 = 2,   // 3 bytes
+  ALNP_CHANNEL_F32 = 3,   // 4 bytes, IEEE 754
+  ALNP_CHANNEL_RGB8 = 4,  // 3 bytes: R, G, B
+  ALNP_CHANNEL_RGBW8 = 5  // 4 bytes: R, G, B, W
 } alnp_channel_format_t;
 
+// Streaming frame header and payload. `epoch` is the id of the sending stream
+// instance, increasing with every instance created on the session, and, with
+// `sequence`, determines the AEAD nonce. Frames are
+// encoded as keyframes and the encoder fills in `mac`.
 typedef struct {
-  const uint16_t* channels;
-  uint32_t channels_len;
-  alnp_channel_format_t format;
+  uint8_t stream_id;      // 0 unless the sender runs several streams
+  uint64_t epoch;
+  uint64_t sequence;      // starts at 1 in every epoch
+  uint64_t timestamp_us;
   uint8_t priority;
+  alnp_bytes_t channels;  // packed slots, channels.len a multiple of the slot width
+  alnp_channel_format_t format;
+  bool encrypt;           // seal channels into `ciphertext`
 } alnp_frame_t;
 
 // Build a CBOR-encoded discovery request buffer into the provided output.
@@ -47,8 +60,9 @@ int alnp_verify_discovery_reply(const alnp_signed_reply_t* reply, const uint8_t*
 // Encode and send a control envelope (caller provides transport).
 int alnp_encode_control(const uint8_t* session_id, const uint8_t* payload, uint32_t payload_len, uint64_t seq, alnp_bytes_t* out_buf);
 
-// Encode a streaming frame for transmission.
-int alnp_encode_stream_frame(const uint8_t* session_id, const alnp_frame_t* frame, alnp_bytes_t* out_buf);
+// Encode a streaming frame for transmission, sealed with the key derived from
+// the session `stream_key` (32 bytes) for the frame's stream_id and epoch.
+int alnp_encode_stream_frame(const uint8_t* session_id, const uint8_t* stream_key, const alnp_frame_t* frame, alnp_bytes_t* out_buf);
 
 #ifdef __cplusplus
 }
@@ -5,11 +5,25 @@ These helpers mirror the Rust types and make it straightforward to build CBOR-re
 maps for discovery, handshake, control, and streaming operations.
 """
 
+import struct
 from dataclasses import dataclass, asdict
-from typing import Any, Dict, List, Optional
+from typing import Any, Dict, List, Optional, Sequence, Union
 
 ALPINE_VERSION = "1.0"
 
+FRAME_KIND_KEYFRAME = "keyframe"
+FRAME_KIND_DELTA = "delta"
+
+# Big-endian struct code per channel slot; `rgb8`/`rgbw8` slots pack one byte
+# per component and take a flat component list.
+_SLOT_CODES = {
+    "u8": "B",
+    "u16": "H",
+    "f32": "f",
+    "rgb8": "B",
+    "rgbw8": "B",
+}
+
 from .profile import CompiledStreamProfile, StreamProfile
 
 
@@ -21,6 +35,7 @@ class CapabilitySet:
     streaming_supported: bool
     encryption_supported: bool
     vendor_extensions: Optional[Dict[str, Any]] = None
+    max_frame_rate_hz: Optional[int] = None
 
 
 @dataclass
@@ -90,19 +105,66 @@ class ControlEnvelope:
         return asdict(self)
 
 
+def pack_channels(channel_format: str, values: Sequence[Union[int, float]]) -> bytes:
+    """
+    Pack channel values into the byte string carried on the wire for
+    ``channel_format``: big-endian, 1 byte per ``u8`` slot, 2 per ``u16``,
+    3 per ``u24`` and 4 per ``f32``. ``rgb8``/``rgbw8`` take a flat list of
+    component bytes (R, G, B[, W] per slot).
+    """
+    if channel_format == "u24":
+        return b"".join(int(value).to_bytes(3, "big") for value in values)
+    code = _SLOT_CODES.get(channel_format)
+    if code is None:
+        raise ValueError(f"unknown channel format {channel_format!r}")
+    return struct.pack(f">{len(values)}{code}", *values)
+
+
+@dataclass
+class ChannelRange:
+    """Changed slots of a delta frame, starting at slot ``offset``."""
+
+    offset: int
+    values: bytes
+
+
 @dataclass
 class FrameEnvelope:
+    """
+    Streaming frame. ``epoch`` and ``sequence`` feed the AEAD nonce, and
+    ``mac`` is the ChaCha20-Poly1305 tag under the sender's epoch key; see
+    ``SessionKeys::for_stream_epoch`` in the Rust crate.
+    """
+
     type: str
     session_id: str
+    epoch: int
+    sequence: int
     timestamp_us: int
     priority: int
     channel_format: str
-    channels: List[int]
-    groups: Optional[Dict[str, List[int]]] = None
+    # Packed per `channel_format`; see `pack_channels`.
+    channels: bytes
+    mac: bytes
+    stream_id: int = 0
+    kind: str = FRAME_KIND_KEYFRAME
+    groups: Optional[Dict[str, bytes]] = None
     metadata: Optional[Dict[str, Any]] = None
+    # [keyframe_interval, delta_depth, deadline_offset_ms,
+    #  frames_since_keyframe, flags, event, recovery]
+    adaptation: Optional[List[int]] = None
+    base_sequence: Optional[int] = None
+    delta: Optional[List[ChannelRange]] = None
+    ciphertext: Optional[bytes] = None
 
     def to_map(self) -> Dict[str, Any]:
-        return asdict(self)
+        payload = asdict(self)
+        if self.stream_id == 0:
+            del payload["stream_id"]
+        for key in ("adaptation", "base_sequence", "delta", "ciphertext"):
+            if payload[key] is None:
+                del payload[key]
+        return payload
 
 
 def _to_cbor(map_obj: Dict[str, Any]) -> bytes:
@@ -148,38 +210,54 @@ def build_control_envelope(
 
 def build_frame_envelope(
     session_id: str,
+    epoch: int,
+    sequence: int,
     timestamp_us: int,
     priority: int,
     channel_format: str,
-    channels: List[int],
-    groups: Optional[Dict[str, List[int]]] = None,
+    channels: bytes,
+    mac: bytes,
+    stream_id: int = 0,
+    groups: Optional[Dict[str, bytes]] = None,
     metadata: Optional[Dict[str, Any]] = None,
+    adaptation: Optional[List[int]] = None,
+    ciphertext: Optional[bytes] = None,
 ) -> FrameEnvelope:
     return FrameEnvelope(
         type="alpine_frame",
         session_id=session_id,
+        epoch=epoch,
+        sequence=sequence,
         timestamp_us=timestamp_us,
         priority=priority,
         channel_format=channel_format,
         channels=channels,
+        mac=mac,
+        stream_id=stream_id,
         groups=groups,
         metadata=metadata,
+        adaptation=adaptation,
+        ciphertext=ciphertext,
     )
 
 
 __all__ = [
     "ALPINE_VERSION",
+    "FRAME_KIND_KEYFRAME",
+    "FRAME_KIND_DELTA",
     "CapabilitySet",
     "DeviceIdentity",
     "DiscoveryRequest",
     "DiscoveryReply",
     "build_discovery_request",
     "ControlEnvelope",
+    "ChannelRange",
     "FrameEnvelope",
     "build_control_envelope",
     "build_frame_envelope",
     "encode_control",
     "encode_frame",
+    "pack_channels",
     "StreamProfile",
     "CompiledStreamProfile",
 ]
@@ -2,12 +2,12 @@ use std::net::UdpSocket as StdUdpSocket;
 use std::time::Duration;
 
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
-use serde_cbor;
 use tokio::runtime::Runtime;
 
 use alpine::e2e_common::run_udp_handshake;
-use alpine::messages::{ChannelFormat, FrameEnvelope, MessageType};
-use alpine::stream::{AlnpStream, FrameTransport};
+use alpine::messages::MessageType;
+use alpine::profile::StreamProfile;
+use alpine::stream::{AlnpStream, AlnpStreamReceiver, FrameTransport, StreamError};
 
 #[path = "common/mod.rs"]
 mod common;
@@ -40,7 +40,7 @@ impl FrameTransport for UdpFrameTransport {
 
 fn bench_alpine_streaming(c: &mut Criterion) {
     let rt = Runtime::new().expect("tokio runtime");
-    let (session, _node) = rt.block_on(run_udp_handshake()).expect("handshake failed");
+    let (session, node) = rt.block_on(run_udp_handshake()).expect("handshake failed");
 
     let mut group = c.benchmark_group("alpine_streaming_latency");
     for &channels in CHANNEL_COUNTS.iter() {
@@ -51,7 +51,9 @@ fn bench_alpine_streaming(c: &mut Criterion) {
             .unwrap();
         let receiver_addr = receiver_socket.local_addr().unwrap();
         let transport = UdpFrameTransport::new(sender_socket, receiver_addr);
-        let stream = AlnpStream::new(session.clone(), transport);
+        let profile = StreamProfile::auto().compile().expect("profile compile");
+        let stream = AlnpStream::new(session.clone(), transport, profile.clone());
+        let receiver = AlnpStreamReceiver::new(node.clone(), profile);
 
         let payload = channel_payload(channels);
         let mut recv_buf = vec![0u8; UDP_BUFFER_SIZE];
@@ -62,19 +64,18 @@ fn bench_alpine_streaming(c: &mut Criterion) {
             |b, payload| {
                 b.iter(|| {
                     stream
-                        .send(
-                            ChannelFormat::U8,
-                            payload.clone(),
-                            FRAME_PRIORITY,
-                            None,
-                            None,
-                        )
+                        .send(payload.clone(), FRAME_PRIORITY, None, None)
                         .expect("stream send failed");
-                    let (len, _) = receiver_socket
-                        .recv_from(&mut recv_buf)
-                        .expect("recv failed");
-                    let frame: FrameEnvelope =
-                        serde_cbor::from_slice(&recv_buf[..len]).expect("decode failed");
+                    // FEC parity datagrams follow some frames; skip them.
+                    let frame = loop {
+                        let (len, _) = receiver_socket
+                            .recv_from(&mut recv_buf)
+                            .expect("recv failed");
+                        match receiver.receive(&recv_buf[..len]) {
+                            Err(StreamError::ParityUnused) => continue,
+                            result => break result.expect("decode failed"),
+                        }
+                    };
                     assert_eq!(frame.message_type, MessageType::AlpineFrame);
                     assert_eq!(frame.channels.len(), payload.len());
                     black_box(frame);
@@ -14,7 +14,7 @@ use common::{
 const ARTNET_ID: &[u8] = b"Art-Net\0";
 const OPCODE_DMX: u16 = 0x5000;
 
-fn build_artnet_packet(channels: &[u16]) -> Vec<u8> {
+fn build_artnet_packet(channels: &[u8]) -> Vec<u8> {
     let mut packet = Vec::with_capacity(20 + channels.len());
     packet.extend_from_slice(ARTNET_ID);
     packet.extend_from_slice(&OPCODE_DMX.to_le_bytes());
@@ -25,9 +25,7 @@ fn build_artnet_packet(channels: &[u16]) -> Vec<u8> {
     let length = (channels.len() + 1) as u16;
     packet.extend_from_slice(&length.to_be_bytes());
     packet.push(0x00);
-    for &value in channels {
-        packet.push((value % 256) as u8);
-    }
+    packet.extend_from_slice(channels);
     packet
 }
 
@@ -1,4 +1,5 @@
 pub const CHANNEL_COUNTS: [usize; 2] = [128, 512];
+#[allow(dead_code)]
 pub const UDP_BUFFER_SIZE: usize = 4096;
 #[allow(dead_code)]
 pub const FRAME_PRIORITY: u8 = 5;
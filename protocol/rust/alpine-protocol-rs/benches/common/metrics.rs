@@ -1,3 +1,3 @@
-pub fn channel_payload(count: usize) -> Vec<u16> {
-    (0..count).map(|i| (i % 256) as u16).collect()
+pub fn channel_payload(count: usize) -> Vec<u8> {
+    (0..count).map(|i| (i % 256) as u8).collect()
 }
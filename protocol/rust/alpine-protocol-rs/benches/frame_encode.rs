@@ -0,0 +1,69 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use tokio::runtime::Runtime;
+
+use alpine::e2e_common::run_udp_handshake;
+use alpine::messages::{ChannelBuffer, ChannelFormat};
+use alpine::profile::StreamProfile;
+use alpine::stream::{AlnpStream, FrameBuf, FrameTransport};
+
+#[path = "common/mod.rs"]
+mod common;
+
+use common::{
+    config::{CHANNEL_COUNTS, FRAME_PRIORITY},
+    metrics::channel_payload,
+};
+
+/// Discards frames so the benchmark measures encoding alone.
+struct NullTransport;
+
+impl FrameTransport for NullTransport {
+    fn send_frame(&self, bytes: &[u8]) -> Result<(), String> {
+        black_box(bytes);
+        Ok(())
+    }
+}
+
+fn bench_frame_encode(c: &mut Criterion) {
+    let rt = Runtime::new().expect("tokio runtime");
+    let (session, _node) = rt.block_on(run_udp_handshake()).expect("handshake failed");
+    let profile = StreamProfile::realtime()
+        .compile()
+        .expect("profile compile");
+
+    let mut group = c.benchmark_group("alpine_frame_encode");
+    for &channels in CHANNEL_COUNTS.iter() {
+        let stream = AlnpStream::new(session.clone(), NullTransport, profile.clone());
+        let mut payload = channel_payload(channels);
+        let mut tick = 0usize;
+        group.bench_function(BenchmarkId::new("send", channels), |b| {
+            b.iter(|| {
+                tick = tick.wrapping_add(1);
+                payload[tick % channels] = tick as u8;
+                stream
+                    .send(payload.clone(), FRAME_PRIORITY, None, None)
+                    .expect("stream send failed");
+            })
+        });
+
+        let stream = AlnpStream::new(session.clone(), NullTransport, profile.clone());
+        let mut buf = FrameBuf::new(ChannelFormat::U8);
+        *buf.channels_mut() = ChannelBuffer::U8(channel_payload(channels));
+        let mut tick = 0usize;
+        group.bench_function(BenchmarkId::new("send_with", channels), |b| {
+            b.iter(|| {
+                tick = tick.wrapping_add(1);
+                if let ChannelBuffer::U8(values) = buf.channels_mut() {
+                    values[tick % channels] = tick as u8;
+                }
+                stream
+                    .send_with(&mut buf, FRAME_PRIORITY)
+                    .expect("stream send failed");
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_frame_encode);
+criterion_main!(benches);
@@ -13,15 +13,13 @@ use common::{
 
 const SACN_IDENTIFIER: &[u8] = b"ASC-E1.17\0\0\0";
 
-fn build_sacn_packet(channels: &[u16]) -> Vec<u8> {
+fn build_sacn_packet(channels: &[u8]) -> Vec<u8> {
     let mut packet = Vec::with_capacity(32 + channels.len());
     packet.extend_from_slice(SACN_IDENTIFIER);
     let count = channels.len() as u16;
     packet.extend_from_slice(&count.to_be_bytes());
     packet.push(0x00);
-    for &value in channels {
-        packet.push((value % 256) as u8);
-    }
+    packet.extend_from_slice(channels);
     packet
 }
 
@@ -25,6 +25,30 @@ pub struct SessionKeys {
     pub stream_key: [u8; 32],
 }
 
+impl SessionKeys {
+    /// Derives the keys one stream instance seals its messages with.
+    ///
+    /// Streaming nonces only hold the stream ID and a counter that restarts
+    /// with every sender or receiver, so each instance takes a new `epoch`
+    /// from its session and expands `stream_key` per `(stream_id, epoch)`. A stream recreated
+    /// on the same session therefore never reuses a key and nonce pair. The
+    /// derived keys carry no `shared_secret`.
+    pub fn for_stream_epoch(&self, stream_id: u8, epoch: u64) -> Result<SessionKeys, CryptoError> {
+        let hkdf = Hkdf::<Sha256>::new(None, &self.stream_key);
+        let mut stream_key = [0u8; 32];
+        hkdf.expand_multi_info(
+            &[b"alpine-stream-epoch", &[stream_id], &epoch.to_be_bytes()],
+            &mut stream_key,
+        )
+        .map_err(|e| CryptoError::Hkdf(format!("{:?}", e)))?;
+        Ok(SessionKeys {
+            shared_secret: Vec::new(),
+            control_key: self.control_key,
+            stream_key,
+        })
+    }
+}
+
 /// Behavior required to complete the handshake key agreement.
 pub trait KeyExchange {
     fn algorithm(&self) -> KeyExchangeAlgorithm;
@@ -123,6 +147,52 @@ pub fn compute_mac(
     Ok(tag.to_vec())
 }
 
+/// Seal `buffer` in place under the derived stream key and return the detached tag.
+///
+/// Passing an empty buffer yields an authentication-only tag over `aad`.
+pub fn seal_stream(
+    keys: &SessionKeys,
+    nonce: &[u8; 12],
+    aad: &[u8],
+    buffer: &mut [u8],
+) -> Result<Vec<u8>, CryptoError> {
+    seal_stream_tag(keys, nonce, aad, buffer).map(|tag| tag.to_vec())
+}
+
+/// [`seal_stream`] returning the tag by value, for callers that avoid allocating.
+pub(crate) fn seal_stream_tag(
+    keys: &SessionKeys,
+    nonce: &[u8; 12],
+    aad: &[u8],
+    buffer: &mut [u8],
+) -> Result<[u8; 16], CryptoError> {
+    let key = Key::from_slice(&keys.stream_key);
+    let cipher = ChaCha20Poly1305::new(key);
+    let tag = cipher
+        .encrypt_in_place_detached(nonce.into(), aad, buffer)
+        .map_err(|e| CryptoError::Aead(e.to_string()))?;
+    Ok(tag.into())
+}
+
+/// Open `buffer` in place under the derived stream key, verifying the detached tag.
+pub fn open_stream(
+    keys: &SessionKeys,
+    nonce: &[u8; 12],
+    aad: &[u8],
+    buffer: &mut [u8],
+    tag: &[u8],
+) -> Result<(), CryptoError> {
+    const CHACHA_TAG_SIZE: usize = 16;
+    if tag.len() != CHACHA_TAG_SIZE {
+        return Err(CryptoError::Aead("invalid tag length".into()));
+    }
+    let key = Key::from_slice(&keys.stream_key);
+    let cipher = ChaCha20Poly1305::new(key);
+    cipher
+        .decrypt_in_place_detached(nonce.into(), aad, buffer, tag.into())
+        .map_err(|e| CryptoError::Aead(e.to_string()))
+}
+
 /// Validate an authentication tag for a control payload.
 pub fn verify_mac(keys: &SessionKeys, seq: u64, payload: &[u8], aad: &[u8], mac: &[u8]) -> bool {
     const CHACHA_TAG_SIZE: usize = 16;
@@ -18,11 +18,15 @@ pub mod stream;
 pub use control::{ControlClient, ControlCrypto, ControlResponder};
 pub use device::DeviceServer;
 pub use messages::{
-    Acknowledge, CapabilitySet, ChannelFormat, ControlEnvelope, ControlOp, DeviceIdentity,
-    DiscoveryReply, DiscoveryRequest, FrameEnvelope, MessageType, SessionEstablished,
+    Acknowledge, CapabilitySet, ChannelBuffer, ChannelFormat, ControlEnvelope, ControlOp,
+    DeviceIdentity, DiscoveryReply, DiscoveryRequest, FrameEnvelope, MessageType,
+    SessionEstablished,
 };
 pub use profile::{CompiledStreamProfile, StreamProfile};
 pub use session::{AlnpRole, AlnpSession, JitterStrategy};
-pub use stream::{AlnpStream, FrameTransport};
+pub use stream::{
+    AlnpStream, AlnpStreamReceiver, AsyncAlnpStream, AsyncFrameTransport, ChannelGroups,
+    FrameTransport, StreamDemux,
+};
 
 mod c_api;
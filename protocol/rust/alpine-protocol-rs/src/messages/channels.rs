@@ -0,0 +1,636 @@
+//! Typed channel values for `FrameEnvelope`.
+//!
+//! A [`ChannelBuffer`] keeps a frame's channels in the native representation
+//! of its [`ChannelFormat`] instead of forcing every value into a `u16`:
+//! 8/16/24-bit levels, normalized `f32` intensities, or packed RGB/RGBW
+//! pixels. Offsets, delta spans and group members all count slots, so one RGB
+//! pixel is one slot carrying three channels.
+//!
+//! [`ChannelBuffer::convert`] moves values between formats through normalized
+//! levels and only succeeds when converting back reproduces the input exactly,
+//! so a converted buffer never silently loses precision.
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::ChannelFormat;
+
+/// Largest value a `U24` slot can carry.
+pub const U24_MAX: u32 = 0x00ff_ffff;
+
+/// Channel values of one frame in the representation of their format.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChannelBuffer {
+    U8(Vec<u8>),
+    U16(Vec<u16>),
+    /// 24-bit levels in the low bits; values above [`U24_MAX`] fail to encode.
+    U24(Vec<u32>),
+    /// Intensities normalized to `0.0..=1.0`.
+    F32(Vec<f32>),
+    /// One pixel per slot, as red, green, blue.
+    Rgb8(Vec<[u8; 3]>),
+    /// One pixel per slot, as red, green, blue, white.
+    Rgbw8(Vec<[u8; 4]>),
+}
+
+/// Errors from [`ChannelBuffer::convert`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ChannelConversionError {
+    #[error("{1} channels do not fill whole {0:?} slots")]
+    PartialSlot(ChannelFormat, usize),
+    #[error("slot {1} cannot be represented exactly as {0:?}")]
+    Lossy(ChannelFormat, usize),
+}
+
+/// Runs `$body` with `$values` bound to the slot vector of `$buffer`,
+/// whatever its format.
+macro_rules! each_format {
+    ($buffer:expr, $values:ident => $body:expr) => {
+        match $buffer {
+            ChannelBuffer::U8($values) => $body,
+            ChannelBuffer::U16($values) => $body,
+            ChannelBuffer::U24($values) => $body,
+            ChannelBuffer::F32($values) => $body,
+            ChannelBuffer::Rgb8($values) => $body,
+            ChannelBuffer::Rgbw8($values) => $body,
+        }
+    };
+}
+
+/// Runs `$body` with the slot vectors of two buffers that share a format, or
+/// evaluates `$mismatch` when their formats differ.
+macro_rules! same_format {
+    ($a:expr, $b:expr, ($x:ident, $y:ident) => $body:expr, _ => $mismatch:expr) => {
+        match ($a, $b) {
+            (ChannelBuffer::U8($x), ChannelBuffer::U8($y)) => $body,
+            (ChannelBuffer::U16($x), ChannelBuffer::U16($y)) => $body,
+            (ChannelBuffer::U24($x), ChannelBuffer::U24($y)) => $body,
+            (ChannelBuffer::F32($x), ChannelBuffer::F32($y)) => $body,
+            (ChannelBuffer::Rgb8($x), ChannelBuffer::Rgb8($y)) => $body,
+            (ChannelBuffer::Rgbw8($x), ChannelBuffer::Rgbw8($y)) => $body,
+            _ => $mismatch,
+        }
+    };
+}
+
+pub(crate) use same_format;
+
+impl Default for ChannelBuffer {
+    fn default() -> Self {
+        ChannelBuffer::U8(Vec::new())
+    }
+}
+
+impl ChannelBuffer {
+    /// Creates an empty buffer for `format`.
+    pub fn new(format: ChannelFormat) -> Self {
+        Self::zeroed(format, 0)
+    }
+
+    /// Creates a buffer of `len` zeroed slots in `format`.
+    pub fn zeroed(format: ChannelFormat, len: usize) -> Self {
+        match format {
+            ChannelFormat::U8 => ChannelBuffer::U8(vec![0; len]),
+            ChannelFormat::U16 => ChannelBuffer::U16(vec![0; len]),
+            ChannelFormat::U24 => ChannelBuffer::U24(vec![0; len]),
+            ChannelFormat::F32 => ChannelBuffer::F32(vec![0.0; len]),
+            ChannelFormat::Rgb8 => ChannelBuffer::Rgb8(vec![[0; 3]; len]),
+            ChannelFormat::Rgbw8 => ChannelBuffer::Rgbw8(vec![[0; 4]; len]),
+        }
+    }
+
+    pub fn format(&self) -> ChannelFormat {
+        match self {
+            ChannelBuffer::U8(_) => ChannelFormat::U8,
+            ChannelBuffer::U16(_) => ChannelFormat::U16,
+            ChannelBuffer::U24(_) => ChannelFormat::U24,
+            ChannelBuffer::F32(_) => ChannelFormat::F32,
+            ChannelBuffer::Rgb8(_) => ChannelFormat::Rgb8,
+            ChannelBuffer::Rgbw8(_) => ChannelFormat::Rgbw8,
+        }
+    }
+
+    /// Number of slots.
+    pub fn len(&self) -> usize {
+        each_format!(self, values => values.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of channels: slots times [`ChannelFormat::components`]. This is
+    /// what a receiver's `max_channels` limits.
+    pub fn channel_count(&self) -> usize {
+        self.len() * self.format().components()
+    }
+
+    /// Converts the values to `format` without losing information.
+    ///
+    /// Levels are scaled between bit depths (a `U8` 255 becomes `U16` 65535),
+    /// `F32` maps `0.0..=1.0` onto the full integer range, and pixel formats
+    /// convert to and from flat channels component by component, with an
+    /// absent white channel reading as zero. Fails with
+    /// [`ChannelConversionError::Lossy`] at the first slot that would not
+    /// convert back to the same value, and with
+    /// [`ChannelConversionError::PartialSlot`] when the channels do not fill
+    /// whole pixels.
+    pub fn convert(&self, format: ChannelFormat) -> Result<ChannelBuffer, ChannelConversionError> {
+        match (self, &format) {
+            (_, format) if self.format() == *format => return Ok(self.clone()),
+            (ChannelBuffer::Rgb8(pixels), ChannelFormat::Rgbw8) => {
+                return Ok(ChannelBuffer::Rgbw8(
+                    pixels.iter().map(|&[r, g, b]| [r, g, b, 0]).collect(),
+                ))
+            }
+            (ChannelBuffer::Rgbw8(pixels), ChannelFormat::Rgb8) => {
+                return match pixels.iter().position(|pixel| pixel[3] != 0) {
+                    Some(index) => Err(ChannelConversionError::Lossy(format, index)),
+                    None => Ok(ChannelBuffer::Rgb8(
+                        pixels.iter().map(|&[r, g, b, _]| [r, g, b]).collect(),
+                    )),
+                };
+            }
+            _ => {}
+        }
+        let converted = Self::from_levels(&format, &self.levels())?;
+        let round_trip = Self::from_levels(&self.format(), &converted.levels())
+            .map_err(|_| ChannelConversionError::Lossy(format, 0))?;
+        match self.first_difference(&round_trip) {
+            Some(index) => Err(ChannelConversionError::Lossy(format, index)),
+            None => Ok(converted),
+        }
+    }
+
+    /// Normalized level of every channel, components of a pixel in order.
+    fn levels(&self) -> Vec<f64> {
+        let mut levels = Vec::with_capacity(self.channel_count());
+        each_format!(self, values => {
+            for value in values {
+                value.push_levels(&mut levels);
+            }
+        });
+        levels
+    }
+
+    fn from_levels(
+        format: &ChannelFormat,
+        levels: &[f64],
+    ) -> Result<ChannelBuffer, ChannelConversionError> {
+        let components = format.components();
+        if !levels.len().is_multiple_of(components) {
+            return Err(ChannelConversionError::PartialSlot(*format, levels.len()));
+        }
+        fn collect<T: ChannelSlot>(levels: &[f64]) -> Vec<T> {
+            levels
+                .chunks_exact(T::COMPONENTS)
+                .map(T::from_levels)
+                .collect()
+        }
+        Ok(match format {
+            ChannelFormat::U8 => ChannelBuffer::U8(collect(levels)),
+            ChannelFormat::U16 => ChannelBuffer::U16(collect(levels)),
+            ChannelFormat::U24 => ChannelBuffer::U24(collect(levels)),
+            ChannelFormat::F32 => ChannelBuffer::F32(collect(levels)),
+            ChannelFormat::Rgb8 => ChannelBuffer::Rgb8(collect(levels)),
+            ChannelFormat::Rgbw8 => ChannelBuffer::Rgbw8(collect(levels)),
+        })
+    }
+
+    /// Index of the first slot that differs from `other`.
+    fn first_difference(&self, other: &ChannelBuffer) -> Option<usize> {
+        same_format!(self, other, (a, b) => {
+            a.iter()
+                .zip(b.iter())
+                .position(|(x, y)| x != y)
+                .or_else(|| (a.len() != b.len()).then_some(a.len().min(b.len())))
+        }, _ => Some(0))
+    }
+
+    /// Appends the values packed for the wire to `out`, failing on the first
+    /// value that does not fit its format.
+    pub(crate) fn pack_into(&self, out: &mut Vec<u8>) -> Result<(), String> {
+        fn pack<T: ChannelSlot>(values: &[T], out: &mut Vec<u8>) -> Result<(), String> {
+            out.reserve(values.len() * T::WIDTH);
+            for value in values {
+                if !value.fits() {
+                    return Err(format!("channel value {:?} exceeds 24 bits", value));
+                }
+                value.write(out);
+            }
+            Ok(())
+        }
+        each_format!(self, values => pack(values, out))
+    }
+
+    /// Reads a byte string produced by [`ChannelBuffer::pack_into`].
+    pub(crate) fn unpack(format: &ChannelFormat, bytes: &[u8]) -> Result<Self, String> {
+        if !bytes.len().is_multiple_of(format.slot_width()) {
+            return Err(format!(
+                "{:?} channel payload of {} bytes is not a whole number of slots",
+                format,
+                bytes.len()
+            ));
+        }
+        fn read<T: ChannelSlot>(bytes: &[u8]) -> Vec<T> {
+            bytes.chunks_exact(T::WIDTH).map(T::read).collect()
+        }
+        Ok(match format {
+            ChannelFormat::U8 => ChannelBuffer::U8(bytes.to_vec()),
+            ChannelFormat::U16 => ChannelBuffer::U16(read(bytes)),
+            ChannelFormat::U24 => ChannelBuffer::U24(read(bytes)),
+            ChannelFormat::F32 => ChannelBuffer::F32(read(bytes)),
+            ChannelFormat::Rgb8 => ChannelBuffer::Rgb8(read(bytes)),
+            ChannelFormat::Rgbw8 => ChannelBuffer::Rgbw8(read(bytes)),
+        })
+    }
+
+    /// Replaces the contents with `other`, reusing the allocation when both
+    /// share a format.
+    pub(crate) fn copy_from(&mut self, other: &ChannelBuffer) {
+        let copied = same_format!(&mut *self, other, (values, source) => {
+            values.clear();
+            values.extend_from_slice(source);
+            true
+        }, _ => false);
+        if !copied {
+            *self = other.clone();
+        }
+    }
+
+    /// Overwrites the slots starting at `offset` with `values`; returns
+    /// `false`, leaving `self` unchanged, when they do not fit or the formats
+    /// differ.
+    pub(crate) fn splice(&mut self, offset: usize, values: &ChannelBuffer) -> bool {
+        same_format!(&mut *self, values, (slots, source) => {
+            match slots.get_mut(offset..offset.saturating_add(source.len())) {
+                Some(target) => {
+                    target.copy_from_slice(source);
+                    true
+                }
+                None => false,
+            }
+        }, _ => false)
+    }
+
+    /// Copies slot `from` of `source` into slot `index`; returns `false` when
+    /// `index` is out of bounds or the formats differ.
+    pub(crate) fn copy_slot(&mut self, index: usize, source: &ChannelBuffer, from: usize) -> bool {
+        same_format!(&mut *self, source, (slots, source) => {
+            match (slots.get_mut(index), source.get(from)) {
+                (Some(slot), Some(value)) => {
+                    *slot = *value;
+                    true
+                }
+                _ => false,
+            }
+        }, _ => false)
+    }
+
+    /// Copies slots `range` into a new buffer of the same format, e.g. the
+    /// `values` of a [`ChannelRange`](super::ChannelRange).
+    ///
+    /// # Panics
+    /// Panics when `range` is out of bounds.
+    pub fn slice(&self, range: std::ops::Range<usize>) -> ChannelBuffer {
+        match self {
+            ChannelBuffer::U8(values) => ChannelBuffer::U8(values[range].to_vec()),
+            ChannelBuffer::U16(values) => ChannelBuffer::U16(values[range].to_vec()),
+            ChannelBuffer::U24(values) => ChannelBuffer::U24(values[range].to_vec()),
+            ChannelBuffer::F32(values) => ChannelBuffer::F32(values[range].to_vec()),
+            ChannelBuffer::Rgb8(values) => ChannelBuffer::Rgb8(values[range].to_vec()),
+            ChannelBuffer::Rgbw8(values) => ChannelBuffer::Rgbw8(values[range].to_vec()),
+        }
+    }
+
+    /// Interpolates every slot `fraction` of the way towards `to`, component
+    /// by component; `None` unless both buffers share format and length.
+    pub(crate) fn lerp(&self, to: &ChannelBuffer, fraction: f64) -> Option<ChannelBuffer> {
+        fn lerp_slots<T: ChannelSlot>(from: &[T], to: &[T], fraction: f64) -> Option<Vec<T>> {
+            (from.len() == to.len()).then(|| {
+                from.iter()
+                    .zip(to)
+                    .map(|(from, to)| from.lerp(*to, fraction))
+                    .collect()
+            })
+        }
+        match (self, to) {
+            (ChannelBuffer::U8(a), ChannelBuffer::U8(b)) => {
+                lerp_slots(a, b, fraction).map(ChannelBuffer::U8)
+            }
+            (ChannelBuffer::U16(a), ChannelBuffer::U16(b)) => {
+                lerp_slots(a, b, fraction).map(ChannelBuffer::U16)
+            }
+            (ChannelBuffer::U24(a), ChannelBuffer::U24(b)) => {
+                lerp_slots(a, b, fraction).map(ChannelBuffer::U24)
+            }
+            (ChannelBuffer::F32(a), ChannelBuffer::F32(b)) => {
+                lerp_slots(a, b, fraction).map(ChannelBuffer::F32)
+            }
+            (ChannelBuffer::Rgb8(a), ChannelBuffer::Rgb8(b)) => {
+                lerp_slots(a, b, fraction).map(ChannelBuffer::Rgb8)
+            }
+            (ChannelBuffer::Rgbw8(a), ChannelBuffer::Rgbw8(b)) => {
+                lerp_slots(a, b, fraction).map(ChannelBuffer::Rgbw8)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl From<Vec<u8>> for ChannelBuffer {
+    fn from(values: Vec<u8>) -> Self {
+        ChannelBuffer::U8(values)
+    }
+}
+
+impl From<Vec<u16>> for ChannelBuffer {
+    fn from(values: Vec<u16>) -> Self {
+        ChannelBuffer::U16(values)
+    }
+}
+
+impl From<Vec<f32>> for ChannelBuffer {
+    fn from(values: Vec<f32>) -> Self {
+        ChannelBuffer::F32(values)
+    }
+}
+
+impl From<Vec<[u8; 3]>> for ChannelBuffer {
+    fn from(values: Vec<[u8; 3]>) -> Self {
+        ChannelBuffer::Rgb8(values)
+    }
+}
+
+impl From<Vec<[u8; 4]>> for ChannelBuffer {
+    fn from(values: Vec<[u8; 4]>) -> Self {
+        ChannelBuffer::Rgbw8(values)
+    }
+}
+
+/// Per-format slot operations behind [`ChannelBuffer`].
+trait ChannelSlot: Copy + PartialEq + std::fmt::Debug {
+    /// Bytes per slot on the wire.
+    const WIDTH: usize;
+    /// Channels per slot.
+    const COMPONENTS: usize = 1;
+
+    /// Whether the value can be packed.
+    fn fits(&self) -> bool {
+        true
+    }
+
+    /// Appends the big-endian wire bytes.
+    fn write(&self, out: &mut Vec<u8>);
+
+    /// Reads one slot from exactly [`ChannelSlot::WIDTH`] bytes.
+    fn read(bytes: &[u8]) -> Self;
+
+    /// Appends the normalized level of each component.
+    fn push_levels(&self, levels: &mut Vec<f64>);
+
+    /// Builds a slot from [`ChannelSlot::COMPONENTS`] normalized levels,
+    /// rounding integer formats to the nearest step.
+    fn from_levels(levels: &[f64]) -> Self;
+
+    fn lerp(&self, to: Self, fraction: f64) -> Self;
+}
+
+/// Integer step nearest to `level` for a format whose full scale is `max`.
+fn quantize(level: f64, max: f64) -> f64 {
+    (level.clamp(0.0, 1.0) * max).round()
+}
+
+fn lerp_level(from: f64, to: f64, fraction: f64) -> f64 {
+    from + (to - from) * fraction
+}
+
+impl ChannelSlot for u8 {
+    const WIDTH: usize = 1;
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.push(*self);
+    }
+
+    fn read(bytes: &[u8]) -> Self {
+        bytes[0]
+    }
+
+    fn push_levels(&self, levels: &mut Vec<f64>) {
+        levels.push(*self as f64 / u8::MAX as f64);
+    }
+
+    fn from_levels(levels: &[f64]) -> Self {
+        quantize(levels[0], u8::MAX as f64) as u8
+    }
+
+    fn lerp(&self, to: Self, fraction: f64) -> Self {
+        lerp_level(*self as f64, to as f64, fraction).round() as u8
+    }
+}
+
+impl ChannelSlot for u16 {
+    const WIDTH: usize = 2;
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_be_bytes());
+    }
+
+    fn read(bytes: &[u8]) -> Self {
+        u16::from_be_bytes([bytes[0], bytes[1]])
+    }
+
+    fn push_levels(&self, levels: &mut Vec<f64>) {
+        levels.push(*self as f64 / u16::MAX as f64);
+    }
+
+    fn from_levels(levels: &[f64]) -> Self {
+        quantize(levels[0], u16::MAX as f64) as u16
+    }
+
+    fn lerp(&self, to: Self, fraction: f64) -> Self {
+        lerp_level(*self as f64, to as f64, fraction).round() as u16
+    }
+}
+
+/// `U24` levels.
+impl ChannelSlot for u32 {
+    const WIDTH: usize = 3;
+
+    fn fits(&self) -> bool {
+        *self <= U24_MAX
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_be_bytes()[1..]);
+    }
+
+    fn read(bytes: &[u8]) -> Self {
+        u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]])
+    }
+
+    fn push_levels(&self, levels: &mut Vec<f64>) {
+        levels.push(*self as f64 / U24_MAX as f64);
+    }
+
+    fn from_levels(levels: &[f64]) -> Self {
+        quantize(levels[0], U24_MAX as f64) as u32
+    }
+
+    fn lerp(&self, to: Self, fraction: f64) -> Self {
+        lerp_level(*self as f64, to as f64, fraction).round() as u32
+    }
+}
+
+impl ChannelSlot for f32 {
+    const WIDTH: usize = 4;
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_be_bytes());
+    }
+
+    fn read(bytes: &[u8]) -> Self {
+        f32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+
+    fn push_levels(&self, levels: &mut Vec<f64>) {
+        levels.push(*self as f64);
+    }
+
+    fn from_levels(levels: &[f64]) -> Self {
+        levels[0] as f32
+    }
+
+    fn lerp(&self, to: Self, fraction: f64) -> Self {
+        lerp_level(*self as f64, to as f64, fraction) as f32
+    }
+}
+
+impl<const N: usize> ChannelSlot for [u8; N] {
+    const WIDTH: usize = N;
+    const COMPONENTS: usize = N;
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self);
+    }
+
+    fn read(bytes: &[u8]) -> Self {
+        let mut pixel = [0; N];
+        pixel.copy_from_slice(bytes);
+        pixel
+    }
+
+    fn push_levels(&self, levels: &mut Vec<f64>) {
+        for component in self {
+            component.push_levels(levels);
+        }
+    }
+
+    fn from_levels(levels: &[f64]) -> Self {
+        std::array::from_fn(|index| u8::from_levels(&levels[index..]))
+    }
+
+    fn lerp(&self, to: Self, fraction: f64) -> Self {
+        std::array::from_fn(|index| self[index].lerp(to[index], fraction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_format_round_trips_through_the_wire() {
+        let buffers = [
+            ChannelBuffer::U8(vec![0, 1, 255]),
+            ChannelBuffer::U16(vec![0, 258, u16::MAX]),
+            ChannelBuffer::U24(vec![0, 0x01_0203, U24_MAX]),
+            ChannelBuffer::F32(vec![0.0, 0.25, 1.0]),
+            ChannelBuffer::Rgb8(vec![[1, 2, 3], [255, 0, 128]]),
+            ChannelBuffer::Rgbw8(vec![[1, 2, 3, 4]]),
+        ];
+        for buffer in buffers {
+            let mut packed = Vec::new();
+            buffer.pack_into(&mut packed).unwrap();
+            assert_eq!(packed.len(), buffer.len() * buffer.format().slot_width());
+            assert_eq!(
+                ChannelBuffer::unpack(&buffer.format(), &packed).unwrap(),
+                buffer
+            );
+        }
+
+        let mut packed = Vec::new();
+        ChannelBuffer::U24(vec![0x01_0203])
+            .pack_into(&mut packed)
+            .unwrap();
+        assert_eq!(packed, vec![1, 2, 3]);
+        assert!(ChannelBuffer::U24(vec![U24_MAX + 1])
+            .pack_into(&mut Vec::new())
+            .is_err());
+        assert!(ChannelBuffer::unpack(&ChannelFormat::Rgb8, &[1, 2, 3, 4]).is_err());
+    }
+
+    #[test]
+    fn widening_conversions_are_exact_and_reversible() {
+        let levels = ChannelBuffer::U8(vec![0, 1, 128, 255]);
+        let wide = levels.convert(ChannelFormat::U16).unwrap();
+        assert_eq!(wide, ChannelBuffer::U16(vec![0, 257, 32_896, u16::MAX]));
+        assert_eq!(wide.convert(ChannelFormat::U8).unwrap(), levels);
+        assert_eq!(
+            levels.convert(ChannelFormat::U24).unwrap(),
+            ChannelBuffer::U24(vec![0, 0x01_0101, 0x80_8080, U24_MAX])
+        );
+        let float = wide.convert(ChannelFormat::F32).unwrap();
+        assert_eq!(float.convert(ChannelFormat::U16).unwrap(), wide);
+    }
+
+    #[test]
+    fn narrowing_fails_unless_exact() {
+        assert_eq!(
+            ChannelBuffer::U16(vec![257, 300]).convert(ChannelFormat::U8),
+            Err(ChannelConversionError::Lossy(ChannelFormat::U8, 1))
+        );
+        assert_eq!(
+            ChannelBuffer::F32(vec![1.0, 0.5]).convert(ChannelFormat::U8),
+            Err(ChannelConversionError::Lossy(ChannelFormat::U8, 1))
+        );
+        assert!(ChannelBuffer::F32(vec![1.5])
+            .convert(ChannelFormat::U16)
+            .is_err());
+    }
+
+    #[test]
+    fn pixels_convert_to_and_from_flat_channels() {
+        let flat = ChannelBuffer::U8(vec![1, 2, 3, 4, 5, 6]);
+        let rgb = flat.convert(ChannelFormat::Rgb8).unwrap();
+        assert_eq!(rgb, ChannelBuffer::Rgb8(vec![[1, 2, 3], [4, 5, 6]]));
+        assert_eq!(rgb.convert(ChannelFormat::U8).unwrap(), flat);
+
+        let rgbw = rgb.convert(ChannelFormat::Rgbw8).unwrap();
+        assert_eq!(rgbw, ChannelBuffer::Rgbw8(vec![[1, 2, 3, 0], [4, 5, 6, 0]]));
+        assert_eq!(rgbw.convert(ChannelFormat::Rgb8).unwrap(), rgb);
+        assert_eq!(
+            ChannelBuffer::Rgbw8(vec![[1, 2, 3, 0], [4, 5, 6, 9]]).convert(ChannelFormat::Rgb8),
+            Err(ChannelConversionError::Lossy(ChannelFormat::Rgb8, 1))
+        );
+        assert_eq!(
+            ChannelBuffer::U8(vec![1, 2, 3, 4]).convert(ChannelFormat::Rgb8),
+            Err(ChannelConversionError::PartialSlot(ChannelFormat::Rgb8, 4))
+        );
+    }
+
+    #[test]
+    fn lerp_interpolates_each_component() {
+        let from = ChannelBuffer::Rgb8(vec![[0, 100, 200]]);
+        let to = ChannelBuffer::Rgb8(vec![[100, 100, 0]]);
+        assert_eq!(
+            from.lerp(&to, 0.5),
+            Some(ChannelBuffer::Rgb8(vec![[50, 100, 100]]))
+        );
+        assert_eq!(
+            ChannelBuffer::F32(vec![0.0]).lerp(&ChannelBuffer::F32(vec![1.0]), 0.25),
+            Some(ChannelBuffer::F32(vec![0.25]))
+        );
+        assert_eq!(from.lerp(&ChannelBuffer::U8(vec![0, 0, 0]), 0.5), None);
+    }
+}
@@ -18,6 +18,10 @@ pub enum MessageType {
     AlpineControlAck,
     AlpineFrame,
     Keepalive,
+    AlpineReceiverReport,
+    AlpineKeyframeRequest,
+    AlpineFrameFragment,
+    AlpineFrameParity,
 }
 
 /// Discovery request broadcast by controllers.
@@ -100,6 +104,9 @@ pub struct CapabilitySet {
     pub grouping_supported: bool,
     pub streaming_supported: bool,
     pub encryption_supported: bool,
+    /// Highest frame rate the device accepts, in Hz; `None` means unbounded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_frame_rate_hz: Option<u16>,
     pub vendor_extensions: Option<HashMap<String, serde_json::Value>>,
 }
 
@@ -111,17 +118,49 @@ impl Default for CapabilitySet {
             grouping_supported: false,
             streaming_supported: true,
             encryption_supported: true,
+            max_frame_rate_hz: None,
             vendor_extensions: None,
         }
     }
 }
 
 /// Supported channel encodings for frames.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+///
+/// Frames carry their values as the matching [`ChannelBuffer`] variant.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum ChannelFormat {
     U8,
     U16,
+    /// 24-bit levels for fine control.
+    U24,
+    /// Normalized intensities as IEEE 754 single precision.
+    F32,
+    /// Packed 8-bit RGB pixels, three channels per slot.
+    Rgb8,
+    /// Packed 8-bit RGBW pixels, four channels per slot.
+    Rgbw8,
+}
+
+impl ChannelFormat {
+    /// Bytes per slot on the wire.
+    pub fn slot_width(&self) -> usize {
+        match self {
+            ChannelFormat::U8 => 1,
+            ChannelFormat::U16 => 2,
+            ChannelFormat::U24 | ChannelFormat::Rgb8 => 3,
+            ChannelFormat::F32 | ChannelFormat::Rgbw8 => 4,
+        }
+    }
+
+    /// Channels per slot: 3 for RGB pixels, 4 for RGBW, 1 otherwise.
+    pub fn components(&self) -> usize {
+        match self {
+            ChannelFormat::Rgb8 => 3,
+            ChannelFormat::Rgbw8 => 4,
+            _ => 1,
+        }
+    }
 }
 
 /// Handshake session_init payload.
@@ -215,21 +254,230 @@ pub enum ControlOp {
     SetConfig,
     SetMode,
     TimeSync,
+    /// Declares named channel groups; the payload is a `ChannelGroups` table.
+    DefineGroups,
     Vendor,
 }
 
-/// Real-time frame envelope.
+/// Encoding of a frame's channel payload.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FrameKind {
+    /// Full channel vector in `channels`; resets the delta chain.
+    #[default]
+    Keyframe,
+    /// Changed ranges in `delta`, applied on top of `base_sequence`.
+    Delta,
+}
+
+/// Contiguous run of channel values starting at slot `offset`.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ChannelRange {
+    pub offset: u32,
+    pub values: ChannelBuffer,
+}
+
+/// Real-time frame envelope.
+///
+/// On the wire `channels`, the `values` of each delta range and every group
+/// value are packed into CBOR byte strings according to `channel_format`, at
+/// [`ChannelFormat::slot_width`] big-endian bytes per slot. The wire
+/// `channel_format` is the format of `channels`; encoding fails when a delta
+/// range or group value holds a different format or a `U24` value exceeds 24
+/// bits.
+#[derive(Debug, Clone, PartialEq)]
 pub struct FrameEnvelope {
-    #[serde(rename = "type")]
     pub message_type: MessageType,
     pub session_id: Uuid,
+    /// Logical stream within the session; 0 unless the sender runs several.
+    pub stream_id: u8,
+    /// Identifier of the sending stream instance, increasing with every
+    /// instance created on the session; selects the key the frame is sealed
+    /// with (see `SessionKeys::for_stream_epoch`).
+    pub epoch: u64,
+    /// Monotonic per-stream counter stamped by the sender (starts at 1).
+    pub sequence: u64,
     pub timestamp_us: u64,
     pub priority: u8,
-    pub channel_format: ChannelFormat,
-    pub channels: Vec<u16>,
-    pub groups: Option<HashMap<String, Vec<u16>>>,
+    /// Channel values; their variant is the `channel_format` sent on the wire.
+    pub channels: ChannelBuffer,
+    /// Group assignments, in the same format as `channels`.
+    pub groups: Option<HashMap<String, ChannelBuffer>>,
+    /// Application-owned metadata; the stream never adds keys of its own.
     pub metadata: Option<HashMap<String, serde_json::Value>>,
+    /// Sender adaptation and recovery state at the time of the frame.
+    pub adaptation: Option<AdaptationHeader>,
+    /// Keyframe or delta encoding of the channel payload.
+    pub kind: FrameKind,
+    /// Sequence of the reconstructed frame a delta applies to.
+    pub base_sequence: Option<u64>,
+    /// Changed channel ranges carried by delta frames.
+    pub delta: Option<Vec<ChannelRange>>,
+    /// Encrypted `channels`/`delta`/`groups`/`metadata` when confidentiality is negotiated.
+    pub ciphertext: Option<Vec<u8>>,
+    /// ChaCha20-Poly1305 tag keyed by the sender's epoch key.
+    pub mac: Vec<u8>,
+}
+
+/// Why the stream entered degraded-safe mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DegradedReason {
+    ExceededProfileBounds,
+    UnrecoverableBurst,
+}
+
+/// Observable adaptation transitions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdaptationEvent {
+    KeyframeCadenceIncreased,
+    DeltaDepthReduced,
+    DeltaDisabled,
+    DeadlineAdjusted,
+    EnteredDegradedSafe(DegradedReason),
+    ExitedDegradedSafe,
+}
+
+impl AdaptationEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AdaptationEvent::KeyframeCadenceIncreased => "keyframe_cadence_increased",
+            AdaptationEvent::DeltaDepthReduced => "delta_depth_reduced",
+            AdaptationEvent::DeltaDisabled => "delta_disabled",
+            AdaptationEvent::DeadlineAdjusted => "deadline_adjusted",
+            AdaptationEvent::EnteredDegradedSafe(_) => "entered_degraded_safe",
+            AdaptationEvent::ExitedDegradedSafe => "exited_degraded_safe",
+        }
+    }
+}
+
+/// Represents why recovery was triggered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecoveryReason {
+    /// Sustained loss ratio across many frames.
+    SustainedLoss,
+    /// Burst loss gap (skipped sequences) exceeded the safe window.
+    BurstLoss,
+}
+
+impl RecoveryReason {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            RecoveryReason::SustainedLoss => "sustained_loss",
+            RecoveryReason::BurstLoss => "burst_loss",
+        }
+    }
+}
+
+/// Sender adaptation and recovery state stamped on every streaming frame.
+///
+/// Kept out of the application-owned `metadata` map and encoded as a fixed
+/// CBOR array of small integers. The header is authenticated with the frame
+/// but never encrypted, so receivers can schedule playout from it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "AdaptationHeaderWire", try_from = "AdaptationHeaderWire")]
+pub struct AdaptationHeader {
+    pub keyframe_interval: u8,
+    pub delta_depth: u8,
+    /// Adaptive shift applied to the profile delivery deadline.
+    pub deadline_offset_ms: i16,
+    pub frames_since_keyframe: u8,
+    pub degraded_safe: bool,
+    /// The frame was forced to be a keyframe (cadence, recovery, or request).
+    pub force_keyframe: bool,
+    /// Most recent adaptation decision, `None` while steady.
+    pub event: Option<AdaptationEvent>,
+    /// Active recovery, if any.
+    pub recovery: Option<RecoveryReason>,
+}
+
+/// `[keyframe_interval, delta_depth, deadline_offset_ms, frames_since_keyframe,
+/// flags, event, recovery]`, with 0 meaning "none" for the last two.
+type AdaptationHeaderWire = (u8, u8, i16, u8, u8, u8, u8);
+
+const HEADER_DEGRADED_SAFE: u8 = 0x01;
+const HEADER_FORCE_KEYFRAME: u8 = 0x02;
+
+impl From<AdaptationHeader> for AdaptationHeaderWire {
+    fn from(header: AdaptationHeader) -> Self {
+        let mut flags = 0;
+        if header.degraded_safe {
+            flags |= HEADER_DEGRADED_SAFE;
+        }
+        if header.force_keyframe {
+            flags |= HEADER_FORCE_KEYFRAME;
+        }
+        let event = match header.event {
+            None => 0,
+            Some(AdaptationEvent::KeyframeCadenceIncreased) => 1,
+            Some(AdaptationEvent::DeltaDepthReduced) => 2,
+            Some(AdaptationEvent::DeltaDisabled) => 3,
+            Some(AdaptationEvent::DeadlineAdjusted) => 4,
+            Some(AdaptationEvent::EnteredDegradedSafe(DegradedReason::ExceededProfileBounds)) => 5,
+            Some(AdaptationEvent::EnteredDegradedSafe(DegradedReason::UnrecoverableBurst)) => 6,
+            Some(AdaptationEvent::ExitedDegradedSafe) => 7,
+        };
+        let recovery = match header.recovery {
+            None => 0,
+            Some(RecoveryReason::SustainedLoss) => 1,
+            Some(RecoveryReason::BurstLoss) => 2,
+        };
+        (
+            header.keyframe_interval,
+            header.delta_depth,
+            header.deadline_offset_ms,
+            header.frames_since_keyframe,
+            flags,
+            event,
+            recovery,
+        )
+    }
+}
+
+impl TryFrom<AdaptationHeaderWire> for AdaptationHeader {
+    type Error = String;
+
+    fn try_from(wire: AdaptationHeaderWire) -> Result<Self, Self::Error> {
+        let (
+            keyframe_interval,
+            delta_depth,
+            deadline_offset_ms,
+            frames_since_keyframe,
+            flags,
+            event,
+            recovery,
+        ) = wire;
+        let event = match event {
+            0 => None,
+            1 => Some(AdaptationEvent::KeyframeCadenceIncreased),
+            2 => Some(AdaptationEvent::DeltaDepthReduced),
+            3 => Some(AdaptationEvent::DeltaDisabled),
+            4 => Some(AdaptationEvent::DeadlineAdjusted),
+            5 => Some(AdaptationEvent::EnteredDegradedSafe(
+                DegradedReason::ExceededProfileBounds,
+            )),
+            6 => Some(AdaptationEvent::EnteredDegradedSafe(
+                DegradedReason::UnrecoverableBurst,
+            )),
+            7 => Some(AdaptationEvent::ExitedDegradedSafe),
+            code => return Err(format!("unknown adaptation event {}", code)),
+        };
+        let recovery = match recovery {
+            0 => None,
+            1 => Some(RecoveryReason::SustainedLoss),
+            2 => Some(RecoveryReason::BurstLoss),
+            code => return Err(format!("unknown recovery reason {}", code)),
+        };
+        Ok(Self {
+            keyframe_interval,
+            delta_depth,
+            deadline_offset_ms,
+            frames_since_keyframe,
+            degraded_safe: flags & HEADER_DEGRADED_SAFE != 0,
+            force_keyframe: flags & HEADER_FORCE_KEYFRAME != 0,
+            event,
+            recovery,
+        })
+    }
 }
 
 /// Control-plane keepalive frame to detect dead sessions.
@@ -241,6 +489,110 @@ pub struct Keepalive {
     pub tick_ms: u64,
 }
 
+/// Periodic node-to-controller summary of what the receiver actually observed.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReceiverReport {
+    #[serde(rename = "type")]
+    pub message_type: MessageType,
+    pub session_id: Uuid,
+    /// Logical stream within the session; 0 unless the sender runs several.
+    #[serde(default, skip_serializing_if = "is_default_stream")]
+    pub stream_id: u8,
+    /// Random identifier of the reporting receiver instance.
+    pub epoch: u64,
+    /// Monotonic report counter per receiver `epoch` (starts at 1).
+    pub report_sequence: u64,
+    pub timestamp_us: u64,
+    pub loss_ratio: f64,
+    pub late_frame_rate: f64,
+    pub jitter_ms: Option<f64>,
+    pub max_loss_gap: u64,
+    /// Highest frame `sequence` received so far (0 before the first frame).
+    pub last_sequence: u64,
+    /// Sender `timestamp_us` of the newest frame received (0 before the first frame).
+    #[serde(default)]
+    pub echo_timestamp_us: u64,
+    /// Microseconds between that frame's arrival and this report.
+    #[serde(default)]
+    pub echo_delay_us: u64,
+    /// Loss ratio of each redundant path, indexed by path; empty for
+    /// single-path receivers.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub path_loss_ratios: Vec<f64>,
+    #[serde(default)]
+    pub mac: Vec<u8>,
+}
+
+/// Node-to-controller request for a fresh keyframe when reconstruction fails.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct KeyframeRequest {
+    #[serde(rename = "type")]
+    pub message_type: MessageType,
+    pub session_id: Uuid,
+    /// Logical stream within the session; 0 unless the sender runs several.
+    #[serde(default, skip_serializing_if = "is_default_stream")]
+    pub stream_id: u8,
+    /// Random identifier of the requesting receiver instance.
+    pub epoch: u64,
+    /// Monotonic request counter per receiver `epoch` (starts at 1).
+    pub request_sequence: u64,
+    pub timestamp_us: u64,
+    /// Sequence of the frame that could not be reconstructed.
+    pub failed_sequence: u64,
+    /// Base frame the failed delta referenced, when known.
+    pub missing_base: Option<u64>,
+    #[serde(default)]
+    pub mac: Vec<u8>,
+}
+
+/// One MTU-sized piece of an encoded frame datagram that was too large to send whole.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FrameFragment {
+    #[serde(rename = "type")]
+    pub message_type: MessageType,
+    pub session_id: Uuid,
+    /// Logical stream within the session; 0 unless the sender runs several.
+    #[serde(default, skip_serializing_if = "is_default_stream")]
+    pub stream_id: u8,
+    /// Epoch of the sender that fragmented the frame.
+    pub epoch: u64,
+    /// `sequence` of the frame this fragment belongs to.
+    pub sequence: u64,
+    /// Position of this fragment, starting at 0.
+    pub fragment_index: u16,
+    /// Total number of fragments the frame was split into.
+    pub fragment_count: u16,
+    /// Slice of the encoded `FrameEnvelope` datagram.
+    #[serde(with = "byte_string")]
+    pub payload: Vec<u8>,
+    #[serde(default)]
+    pub mac: Vec<u8>,
+}
+
+/// XOR parity over a group of consecutive frame datagrams, used to rebuild one lost frame.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FrameParity {
+    #[serde(rename = "type")]
+    pub message_type: MessageType,
+    pub session_id: Uuid,
+    /// Logical stream within the session; 0 unless the sender runs several.
+    #[serde(default, skip_serializing_if = "is_default_stream")]
+    pub stream_id: u8,
+    /// Epoch of the sender of the covered frames.
+    pub epoch: u64,
+    /// First frame `sequence` covered by this parity.
+    pub first_sequence: u64,
+    /// Number of consecutive frames covered.
+    pub group_size: u8,
+    /// XOR of the covered datagram lengths.
+    pub length_xor: u32,
+    /// XOR of the covered frame datagrams, zero-padded to the longest.
+    #[serde(with = "byte_string")]
+    pub payload: Vec<u8>,
+    #[serde(default)]
+    pub mac: Vec<u8>,
+}
+
 /// Standard error codes from docs/errors.md.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
@@ -262,3 +614,106 @@ pub enum ErrorCode {
     StreamTooLarge,
     StreamUnsupportedChannelMode,
 }
+
+mod channels;
+
+pub use channels::{ChannelBuffer, ChannelConversionError, U24_MAX};
+
+pub(crate) use channels::same_format;
+
+pub(crate) mod packed;
+
+/// Stream 0 is left off the wire, so single-stream sessions encode as before.
+pub(crate) fn is_default_stream(stream_id: &u8) -> bool {
+    *stream_id == 0
+}
+
+/// Serializes binary blobs as CBOR byte strings instead of integer arrays.
+mod byte_string {
+    use serde::de::{Deserializer, Error, SeqAccess, Visitor};
+    use serde::Serializer;
+    use std::fmt;
+
+    pub fn serialize<S: Serializer>(value: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(value)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        deserializer.deserialize_bytes(BytesVisitor)
+    }
+
+    pub(super) struct BytesVisitor;
+
+    impl<'de> Visitor<'de> for BytesVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a byte string")
+        }
+
+        fn visit_bytes<E: Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+            Ok(v.to_vec())
+        }
+
+        fn visit_byte_buf<E: Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+            Ok(v)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut out = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+            while let Some(byte) = seq.next_element()? {
+                out.push(byte);
+            }
+            Ok(out)
+        }
+    }
+}
+
+/// Serializes optional binary blobs as CBOR byte strings instead of integer arrays.
+mod opt_byte_string {
+    use super::byte_string::BytesVisitor;
+    use serde::de::{Deserializer, Error, Visitor};
+    use serde::Serializer;
+    use std::fmt;
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<&[u8]>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(bytes) => serializer.serialize_bytes(bytes),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<u8>>, D::Error> {
+        deserializer.deserialize_option(OptionVisitor)
+    }
+
+    struct OptionVisitor;
+
+    impl<'de> Visitor<'de> for OptionVisitor {
+        type Value = Option<Vec<u8>>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("an optional byte string")
+        }
+
+        fn visit_none<E: Error>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+
+        fn visit_unit<E: Error>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+
+        fn visit_some<D: Deserializer<'de>>(
+            self,
+            deserializer: D,
+        ) -> Result<Self::Value, D::Error> {
+            deserializer.deserialize_bytes(BytesVisitor).map(Some)
+        }
+    }
+}
@@ -0,0 +1,415 @@
+//! Packed binary channel payloads for `FrameEnvelope`.
+//!
+//! Channel values travel as one CBOR byte string per vector instead of an
+//! array of integers, at [`ChannelFormat::slot_width`] big-endian bytes per
+//! slot. Delta ranges and group values pack the same way. The in-memory API
+//! keeps a typed [`ChannelBuffer`], so packing only happens while encoding
+//! and decoding, where the frame's `channel_format` is known.
+use std::collections::HashMap;
+use std::ops::Range;
+
+use serde::de::{Deserializer, Error as _};
+use serde::ser::{Error as _, SerializeMap, SerializeSeq, Serializer};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::byte_string::BytesVisitor;
+use super::{
+    is_default_stream, opt_byte_string, AdaptationHeader, ChannelBuffer, ChannelFormat,
+    ChannelRange, FrameEnvelope, FrameKind, MessageType,
+};
+
+/// Packs `values` as `format` into `out`, failing when the buffer holds a
+/// different format or a value does not fit.
+pub(crate) fn pack_into(
+    format: &ChannelFormat,
+    values: &ChannelBuffer,
+    out: &mut Vec<u8>,
+) -> Result<(), String> {
+    if values.format() != *format {
+        return Err(format!(
+            "{:?} channels in a {:?} frame",
+            values.format(),
+            format
+        ));
+    }
+    values.pack_into(out)
+}
+
+/// Serializes a channel vector as a packed byte string.
+#[derive(Clone, Copy)]
+pub(crate) enum PackedChannels<'a> {
+    /// Values packed while serializing.
+    Values {
+        format: ChannelFormat,
+        values: &'a ChannelBuffer,
+    },
+    /// Bytes already packed by the caller.
+    Packed(&'a [u8]),
+}
+
+impl Serialize for PackedChannels<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            PackedChannels::Values { format, values } => {
+                let mut packed = Vec::new();
+                pack_into(&format, values, &mut packed).map_err(S::Error::custom)?;
+                serializer.serialize_bytes(&packed)
+            }
+            PackedChannels::Packed(bytes) => serializer.serialize_bytes(bytes),
+        }
+    }
+}
+
+/// Serializes delta ranges with packed `values`.
+#[derive(Clone, Copy)]
+pub(crate) enum PackedRanges<'a> {
+    /// Owned ranges, packed while serializing.
+    Ranges {
+        format: ChannelFormat,
+        ranges: &'a [ChannelRange],
+    },
+    /// Channel index spans into an already packed channel vector.
+    Spans {
+        packed: &'a [u8],
+        width: usize,
+        spans: &'a [Range<usize>],
+    },
+}
+
+#[derive(Serialize)]
+struct PackedRange<'a> {
+    offset: u32,
+    values: PackedChannels<'a>,
+}
+
+impl Serialize for PackedRanges<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            PackedRanges::Ranges { format, ranges } => {
+                let mut seq = serializer.serialize_seq(Some(ranges.len()))?;
+                for range in ranges {
+                    seq.serialize_element(&PackedRange {
+                        offset: range.offset,
+                        values: PackedChannels::Values {
+                            format,
+                            values: &range.values,
+                        },
+                    })?;
+                }
+                seq.end()
+            }
+            PackedRanges::Spans {
+                packed,
+                width,
+                spans,
+            } => {
+                let mut seq = serializer.serialize_seq(Some(spans.len()))?;
+                for span in spans {
+                    seq.serialize_element(&PackedRange {
+                        offset: span.start as u32,
+                        values: PackedChannels::Packed(
+                            &packed[span.start * width..span.end * width],
+                        ),
+                    })?;
+                }
+                seq.end()
+            }
+        }
+    }
+}
+
+/// Serializes group assignments as a map of packed byte strings, in sorted
+/// order so the frame tag covers a canonical encoding.
+#[derive(Clone, Copy)]
+pub(crate) struct PackedGroups<'a> {
+    pub(crate) format: ChannelFormat,
+    pub(crate) groups: &'a HashMap<String, ChannelBuffer>,
+}
+
+impl Serialize for PackedGroups<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut groups: Vec<_> = self.groups.iter().collect();
+        groups.sort_unstable_by_key(|(id, _)| *id);
+        let mut map = serializer.serialize_map(Some(groups.len()))?;
+        for (id, values) in groups {
+            map.serialize_entry(
+                id,
+                &PackedChannels::Values {
+                    format: self.format,
+                    values,
+                },
+            )?;
+        }
+        map.end()
+    }
+}
+
+/// Channel byte string read before the frame's format is known.
+pub(crate) struct RawChannels(Vec<u8>);
+
+impl<'de> Deserialize<'de> for RawChannels {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer
+            .deserialize_bytes(BytesVisitor)
+            .map(RawChannels)
+    }
+}
+
+impl RawChannels {
+    pub(crate) fn unpack(&self, format: &ChannelFormat) -> Result<ChannelBuffer, String> {
+        ChannelBuffer::unpack(format, &self.0)
+    }
+}
+
+/// Delta range whose `values` are still packed.
+#[derive(Deserialize)]
+pub(crate) struct RawRange {
+    offset: u32,
+    values: RawChannels,
+}
+
+/// Unpacks delta ranges read alongside `format`.
+pub(crate) fn unpack_ranges(
+    format: &ChannelFormat,
+    ranges: Vec<RawRange>,
+) -> Result<Vec<ChannelRange>, String> {
+    ranges
+        .into_iter()
+        .map(|range| {
+            Ok(ChannelRange {
+                offset: range.offset,
+                values: range.values.unpack(format)?,
+            })
+        })
+        .collect()
+}
+
+/// Unpacks group assignments read alongside `format`.
+pub(crate) fn unpack_groups(
+    format: &ChannelFormat,
+    groups: HashMap<String, RawChannels>,
+) -> Result<HashMap<String, ChannelBuffer>, String> {
+    groups
+        .into_iter()
+        .map(|(id, values)| Ok((id, values.unpack(format)?)))
+        .collect()
+}
+
+/// Borrowed wire view of a `FrameEnvelope`, serialized exactly like one.
+#[derive(Clone, Copy, Serialize)]
+pub(crate) struct FrameRef<'a> {
+    #[serde(rename = "type")]
+    pub(crate) message_type: &'a MessageType,
+    pub(crate) session_id: &'a Uuid,
+    #[serde(skip_serializing_if = "is_default_stream")]
+    pub(crate) stream_id: u8,
+    pub(crate) epoch: u64,
+    pub(crate) sequence: u64,
+    pub(crate) timestamp_us: u64,
+    pub(crate) priority: u8,
+    pub(crate) channel_format: ChannelFormat,
+    pub(crate) channels: PackedChannels<'a>,
+    pub(crate) groups: Option<PackedGroups<'a>>,
+    pub(crate) metadata: Option<&'a HashMap<String, serde_json::Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) adaptation: Option<AdaptationHeader>,
+    pub(crate) kind: FrameKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) base_sequence: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) delta: Option<PackedRanges<'a>>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "opt_byte_string::serialize"
+    )]
+    pub(crate) ciphertext: Option<&'a [u8]>,
+    pub(crate) mac: &'a [u8],
+}
+
+impl<'a> FrameRef<'a> {
+    pub(crate) fn from_frame(frame: &'a FrameEnvelope) -> Self {
+        let format = frame.channels.format();
+        Self {
+            message_type: &frame.message_type,
+            session_id: &frame.session_id,
+            stream_id: frame.stream_id,
+            epoch: frame.epoch,
+            sequence: frame.sequence,
+            timestamp_us: frame.timestamp_us,
+            priority: frame.priority,
+            channel_format: format,
+            channels: PackedChannels::Values {
+                format,
+                values: &frame.channels,
+            },
+            groups: frame
+                .groups
+                .as_ref()
+                .map(|groups| PackedGroups { format, groups }),
+            metadata: frame.metadata.as_ref(),
+            adaptation: frame.adaptation,
+            kind: frame.kind,
+            base_sequence: frame.base_sequence,
+            delta: frame
+                .delta
+                .as_deref()
+                .map(|ranges| PackedRanges::Ranges { format, ranges }),
+            ciphertext: frame.ciphertext.as_deref(),
+            mac: &frame.mac,
+        }
+    }
+}
+
+impl Serialize for FrameEnvelope {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        FrameRef::from_frame(self).serialize(serializer)
+    }
+}
+
+#[derive(Deserialize)]
+struct FrameWire {
+    #[serde(rename = "type")]
+    message_type: MessageType,
+    session_id: Uuid,
+    #[serde(default)]
+    stream_id: u8,
+    epoch: u64,
+    #[serde(default)]
+    sequence: u64,
+    timestamp_us: u64,
+    priority: u8,
+    channel_format: ChannelFormat,
+    channels: RawChannels,
+    groups: Option<HashMap<String, RawChannels>>,
+    metadata: Option<HashMap<String, serde_json::Value>>,
+    #[serde(default)]
+    adaptation: Option<AdaptationHeader>,
+    #[serde(default)]
+    kind: FrameKind,
+    #[serde(default)]
+    base_sequence: Option<u64>,
+    #[serde(default)]
+    delta: Option<Vec<RawRange>>,
+    #[serde(default, deserialize_with = "opt_byte_string::deserialize")]
+    ciphertext: Option<Vec<u8>>,
+    #[serde(default)]
+    mac: Vec<u8>,
+}
+
+impl<'de> Deserialize<'de> for FrameEnvelope {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let wire = FrameWire::deserialize(deserializer)?;
+        let format = &wire.channel_format;
+        let channels = wire.channels.unpack(format).map_err(D::Error::custom)?;
+        let delta = wire
+            .delta
+            .map(|ranges| unpack_ranges(format, ranges))
+            .transpose()
+            .map_err(D::Error::custom)?;
+        let groups = wire
+            .groups
+            .map(|groups| unpack_groups(format, groups))
+            .transpose()
+            .map_err(D::Error::custom)?;
+        Ok(FrameEnvelope {
+            message_type: wire.message_type,
+            session_id: wire.session_id,
+            stream_id: wire.stream_id,
+            epoch: wire.epoch,
+            sequence: wire.sequence,
+            timestamp_us: wire.timestamp_us,
+            priority: wire.priority,
+            channels,
+            groups,
+            metadata: wire.metadata,
+            adaptation: wire.adaptation,
+            kind: wire.kind,
+            base_sequence: wire.base_sequence,
+            delta,
+            ciphertext: wire.ciphertext,
+            mac: wire.mac,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::U24_MAX;
+
+    fn frame(channels: ChannelBuffer) -> FrameEnvelope {
+        FrameEnvelope {
+            message_type: MessageType::AlpineFrame,
+            session_id: Uuid::nil(),
+            stream_id: 0,
+            epoch: 0,
+            sequence: 1,
+            timestamp_us: 0,
+            priority: 0,
+            channels,
+            groups: None,
+            metadata: None,
+            adaptation: None,
+            kind: FrameKind::Keyframe,
+            base_sequence: None,
+            delta: None,
+            ciphertext: None,
+            mac: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn channels_pack_by_format_and_round_trip() {
+        let u8_frame = frame(ChannelBuffer::U8(vec![0, 1, 255, 128]));
+        let u16_frame = frame(ChannelBuffer::U16(vec![0, 1, 255, 65_535]));
+        let mut packed = Vec::new();
+        pack_into(&ChannelFormat::U16, &u16_frame.channels, &mut packed).unwrap();
+        assert_eq!(packed, vec![0, 0, 0, 1, 0, 255, 255, 255]);
+        let mut rgb_frame = frame(ChannelBuffer::Rgb8(vec![[1, 2, 3], [4, 5, 6]]));
+        rgb_frame.groups = Some(HashMap::from([(
+            "wash".to_string(),
+            ChannelBuffer::Rgb8(vec![[9, 9, 9]]),
+        )]));
+        let float_frame = frame(ChannelBuffer::F32(vec![0.5, 1.0]));
+        for frame in [u8_frame, u16_frame, rgb_frame, float_frame] {
+            let bytes = serde_cbor::to_vec(&frame).unwrap();
+            assert_eq!(
+                serde_cbor::from_slice::<FrameEnvelope>(&bytes).unwrap(),
+                frame
+            );
+        }
+
+        let mut delta = frame(ChannelBuffer::new(ChannelFormat::U24));
+        delta.kind = FrameKind::Delta;
+        delta.base_sequence = Some(1);
+        delta.delta = Some(vec![ChannelRange {
+            offset: 3,
+            values: ChannelBuffer::U24(vec![300, 0x12_3456]),
+        }]);
+        let bytes = serde_cbor::to_vec(&delta).unwrap();
+        assert_eq!(
+            serde_cbor::from_slice::<FrameEnvelope>(&bytes).unwrap(),
+            delta
+        );
+    }
+
+    #[test]
+    fn u8_frames_are_one_byte_per_slot() {
+        let small = serde_cbor::to_vec(&frame(ChannelBuffer::U8(vec![200; 10]))).unwrap();
+        let large = serde_cbor::to_vec(&frame(ChannelBuffer::U8(vec![200; 1_010]))).unwrap();
+        assert_eq!(large.len() - small.len(), 1_000 + 2);
+    }
+
+    #[test]
+    fn buffers_must_match_the_frame_format() {
+        let mut mismatched = frame(ChannelBuffer::U8(vec![1]));
+        mismatched.groups = Some(HashMap::from([(
+            "wash".to_string(),
+            ChannelBuffer::U16(vec![1]),
+        )]));
+        assert!(serde_cbor::to_vec(&mismatched).is_err());
+        let overflow = frame(ChannelBuffer::U24(vec![U24_MAX + 1]));
+        assert!(serde_cbor::to_vec(&overflow).is_err());
+    }
+}
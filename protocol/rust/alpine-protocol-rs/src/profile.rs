@@ -14,6 +14,191 @@ pub enum StreamIntent {
     Install,
 }
 
+/// Largest number of frames one parity datagram may cover.
+pub const MAX_FEC_GROUP_SIZE: u8 = 16;
+
+impl StreamIntent {
+    /// Built-in FEC group size: one parity datagram per this many frames, or 0
+    /// for none. Realtime never waits on parity; Install trades the most
+    /// bandwidth for resilience.
+    pub fn default_fec_group_size(self) -> u8 {
+        match self {
+            StreamIntent::Realtime => 0,
+            StreamIntent::Auto => 8,
+            StreamIntent::Install => 4,
+        }
+    }
+}
+
+/// Limits the adaptation state machine may not cross for a profile.
+///
+/// Leaving these bounds sends the stream into degraded-safe mode instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProfileBounds {
+    pub min_keyframe_interval: u8,
+    pub base_keyframe_interval: u8,
+    pub min_delta_depth: u8,
+    pub base_delta_depth: u8,
+    pub max_deadline_offset: i16,
+    pub min_deadline_offset: i16,
+}
+
+impl ProfileBounds {
+    /// Built-in bounds for each intent.
+    pub fn for_intent(intent: StreamIntent) -> Self {
+        match intent {
+            StreamIntent::Auto => Self {
+                min_keyframe_interval: 6,
+                base_keyframe_interval: 10,
+                min_delta_depth: 1,
+                base_delta_depth: 3,
+                max_deadline_offset: 15,
+                min_deadline_offset: -15,
+            },
+            StreamIntent::Realtime => Self {
+                min_keyframe_interval: 8,
+                base_keyframe_interval: 12,
+                min_delta_depth: 1,
+                base_delta_depth: 2,
+                max_deadline_offset: 0,
+                min_deadline_offset: -20,
+            },
+            StreamIntent::Install => Self {
+                min_keyframe_interval: 4,
+                base_keyframe_interval: 8,
+                min_delta_depth: 0,
+                base_delta_depth: 3,
+                max_deadline_offset: 25,
+                min_deadline_offset: -10,
+            },
+        }
+    }
+
+    fn validate(&self) -> Result<(), ProfileError> {
+        if self.min_keyframe_interval == 0
+            || self.min_keyframe_interval > self.base_keyframe_interval
+        {
+            return Err(ProfileError::InvalidBounds("keyframe interval"));
+        }
+        if self.min_delta_depth > self.base_delta_depth {
+            return Err(ProfileError::InvalidBounds("delta depth"));
+        }
+        if self.min_deadline_offset > 0 || self.max_deadline_offset < 0 {
+            return Err(ProfileError::InvalidBounds(
+                "deadline offset must include 0",
+            ));
+        }
+        Ok(())
+    }
+
+    fn hash_into(&self, hasher: &mut Sha256) {
+        hasher.update([
+            self.min_keyframe_interval,
+            self.base_keyframe_interval,
+            self.min_delta_depth,
+            self.base_delta_depth,
+        ]);
+        hasher.update(self.max_deadline_offset.to_be_bytes());
+        hasher.update(self.min_deadline_offset.to_be_bytes());
+    }
+}
+
+/// Metric thresholds consumed by the default adaptation policy.
+///
+/// Ratios are fractions in `[0, 1]`, jitter values are milliseconds, and burst
+/// values count consecutive missing sequences.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptationThresholds {
+    /// Decisions made before a new state may change again.
+    pub dwell_frames: u32,
+    pub loss_keyframe: f64,
+    pub loss_disable: f64,
+    pub loss_degrade: f64,
+    pub late_delta: f64,
+    pub jitter_delta_ms: f64,
+    pub jitter_tighten_ms: f64,
+    pub jitter_relax_ms: f64,
+    pub burst_keyframe: u64,
+    pub burst_disable: u64,
+    pub burst_degrade: u64,
+    pub deadline_step_ms: i16,
+}
+
+impl AdaptationThresholds {
+    fn validate(&self) -> Result<(), ProfileError> {
+        let ratios = [
+            self.loss_keyframe,
+            self.loss_disable,
+            self.loss_degrade,
+            self.late_delta,
+        ];
+        if ratios.iter().any(|ratio| !(0.0..=1.0).contains(ratio)) {
+            return Err(ProfileError::InvalidThresholds(
+                "ratios must be within [0, 1]",
+            ));
+        }
+        let jitter = [
+            self.jitter_delta_ms,
+            self.jitter_tighten_ms,
+            self.jitter_relax_ms,
+        ];
+        if jitter.iter().any(|ms| !ms.is_finite() || *ms < 0.0) {
+            return Err(ProfileError::InvalidThresholds(
+                "jitter must be finite and non-negative",
+            ));
+        }
+        if self.jitter_relax_ms > self.jitter_tighten_ms {
+            return Err(ProfileError::InvalidThresholds(
+                "jitter relax exceeds tighten",
+            ));
+        }
+        if self.deadline_step_ms <= 0 {
+            return Err(ProfileError::InvalidThresholds(
+                "deadline step must be positive",
+            ));
+        }
+        Ok(())
+    }
+
+    fn hash_into(&self, hasher: &mut Sha256) {
+        hasher.update(self.dwell_frames.to_be_bytes());
+        for value in [
+            self.loss_keyframe,
+            self.loss_disable,
+            self.loss_degrade,
+            self.late_delta,
+            self.jitter_delta_ms,
+            self.jitter_tighten_ms,
+            self.jitter_relax_ms,
+        ] {
+            hasher.update(value.to_bits().to_be_bytes());
+        }
+        for value in [self.burst_keyframe, self.burst_disable, self.burst_degrade] {
+            hasher.update(value.to_be_bytes());
+        }
+        hasher.update(self.deadline_step_ms.to_be_bytes());
+    }
+}
+
+impl Default for AdaptationThresholds {
+    fn default() -> Self {
+        Self {
+            dwell_frames: 8,
+            loss_keyframe: 0.30,
+            loss_disable: 0.50,
+            loss_degrade: 0.60,
+            late_delta: 0.20,
+            jitter_delta_ms: 5.0,
+            jitter_tighten_ms: 8.0,
+            jitter_relax_ms: 3.0,
+            burst_keyframe: 5,
+            burst_disable: 8,
+            burst_degrade: 10,
+            deadline_step_ms: 10,
+        }
+    }
+}
+
 /// Error produced when stream profile parameters fail validation.
 #[derive(Debug, thiserror::Error)]
 pub enum ProfileError {
@@ -23,6 +208,12 @@ pub enum ProfileError {
     ResilienceWeightOutOfRange,
     #[error("latency and resilience weights cannot both be zero")]
     ZeroTotalWeight,
+    #[error("invalid profile bounds: {0}")]
+    InvalidBounds(&'static str),
+    #[error("invalid adaptation thresholds: {0}")]
+    InvalidThresholds(&'static str),
+    #[error("fec group size {0} exceeds the maximum of {MAX_FEC_GROUP_SIZE}")]
+    FecGroupTooLarge(u8),
 }
 
 /// High-level description of stream behavior selected by callers.
@@ -33,6 +224,10 @@ pub struct StreamProfile {
     intent: StreamIntent,
     latency_weight: u8,
     resilience_weight: u8,
+    confidential: bool,
+    bounds: ProfileBounds,
+    thresholds: AdaptationThresholds,
+    fec_group_size: u8,
 }
 
 impl StreamProfile {
@@ -42,6 +237,10 @@ impl StreamProfile {
             intent: StreamIntent::Auto,
             latency_weight: 50,
             resilience_weight: 50,
+            confidential: false,
+            bounds: ProfileBounds::for_intent(StreamIntent::Auto),
+            thresholds: AdaptationThresholds::default(),
+            fec_group_size: StreamIntent::Auto.default_fec_group_size(),
         }
     }
 
@@ -51,6 +250,10 @@ impl StreamProfile {
             intent: StreamIntent::Realtime,
             latency_weight: 80,
             resilience_weight: 20,
+            confidential: false,
+            bounds: ProfileBounds::for_intent(StreamIntent::Realtime),
+            thresholds: AdaptationThresholds::default(),
+            fec_group_size: StreamIntent::Realtime.default_fec_group_size(),
         }
     }
 
@@ -60,6 +263,10 @@ impl StreamProfile {
             intent: StreamIntent::Install,
             latency_weight: 25,
             resilience_weight: 75,
+            confidential: false,
+            bounds: ProfileBounds::for_intent(StreamIntent::Install),
+            thresholds: AdaptationThresholds::default(),
+            fec_group_size: StreamIntent::Install.default_fec_group_size(),
         }
     }
 
@@ -69,14 +276,49 @@ impl StreamProfile {
             intent,
             latency_weight,
             resilience_weight,
+            confidential: false,
+            bounds: ProfileBounds::for_intent(intent),
+            thresholds: AdaptationThresholds::default(),
+            fec_group_size: intent.default_fec_group_size(),
         }
     }
 
+    /// Requests encrypted frame payloads for this profile.
+    ///
+    /// Encryption is only used when both peers advertise `encryption_supported`;
+    /// streaming fails instead of silently falling back to cleartext.
+    pub fn with_confidentiality(mut self, confidential: bool) -> Self {
+        self.confidential = confidential;
+        self
+    }
+
+    /// Overrides the intent's built-in adaptation bounds.
+    pub fn with_bounds(mut self, bounds: ProfileBounds) -> Self {
+        self.bounds = bounds;
+        self
+    }
+
+    /// Overrides the default adaptation thresholds.
+    pub fn with_thresholds(mut self, thresholds: AdaptationThresholds) -> Self {
+        self.thresholds = thresholds;
+        self
+    }
+
+    /// Overrides the intent's FEC group size (see
+    /// [`StreamIntent::default_fec_group_size`]); 0 disables parity.
+    pub fn with_fec_group_size(mut self, group_size: u8) -> Self {
+        self.fec_group_size = group_size;
+        self
+    }
+
     /// Normalizes and compiles the profile into a runtime configuration.
     ///
     /// # Guarantees
-    /// * Validates each weight and rejects unsafe combinations with explicit errors.
-    /// * Produces a deterministic `config_id` derived from the normalized weights and intent.
+    /// * Validates each weight, bound, and threshold and rejects unsafe combinations
+    ///   with explicit errors.
+    /// * Produces a deterministic `config_id` derived from the normalized weights, intent,
+    ///   confidentiality request, and any non-default bounds, thresholds, or FEC group
+    ///   size (profiles using the built-in values keep their historical IDs).
     pub fn compile(self) -> Result<CompiledStreamProfile, ProfileError> {
         if self.latency_weight > 100 {
             return Err(ProfileError::LatencyWeightOutOfRange);
@@ -87,10 +329,30 @@ impl StreamProfile {
         if self.latency_weight == 0 && self.resilience_weight == 0 {
             return Err(ProfileError::ZeroTotalWeight);
         }
+        self.bounds.validate()?;
+        self.thresholds.validate()?;
+        if self.fec_group_size > MAX_FEC_GROUP_SIZE {
+            return Err(ProfileError::FecGroupTooLarge(self.fec_group_size));
+        }
 
         let mut hasher = Sha256::new();
-        hasher.update(&[self.latency_weight, self.resilience_weight]);
-        hasher.update(&[self.intent as u8]);
+        hasher.update([self.latency_weight, self.resilience_weight]);
+        hasher.update([self.intent as u8]);
+        if self.confidential {
+            hasher.update(b"confidential");
+        }
+        if self.bounds != ProfileBounds::for_intent(self.intent) {
+            hasher.update(b"bounds");
+            self.bounds.hash_into(&mut hasher);
+        }
+        if self.thresholds != AdaptationThresholds::default() {
+            hasher.update(b"thresholds");
+            self.thresholds.hash_into(&mut hasher);
+        }
+        if self.fec_group_size != self.intent.default_fec_group_size() {
+            hasher.update(b"fec");
+            hasher.update([self.fec_group_size]);
+        }
         let digest = hasher.finalize();
         let config_id = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
 
@@ -98,6 +360,10 @@ impl StreamProfile {
             intent: self.intent,
             latency_weight: self.latency_weight,
             resilience_weight: self.resilience_weight,
+            confidential: self.confidential,
+            bounds: self.bounds,
+            thresholds: self.thresholds,
+            fec_group_size: self.fec_group_size,
             config_id,
         })
     }
@@ -116,6 +382,10 @@ pub struct CompiledStreamProfile {
     intent: StreamIntent,
     latency_weight: u8,
     resilience_weight: u8,
+    confidential: bool,
+    bounds: ProfileBounds,
+    thresholds: AdaptationThresholds,
+    fec_group_size: u8,
     config_id: String,
 }
 
@@ -139,6 +409,37 @@ impl CompiledStreamProfile {
     pub fn intent(&self) -> StreamIntent {
         self.intent
     }
+
+    /// Whether the profile requires encrypted frame payloads.
+    pub fn confidentiality(&self) -> bool {
+        self.confidential
+    }
+
+    /// Adaptation bounds the stream must stay within.
+    pub fn bounds(&self) -> &ProfileBounds {
+        &self.bounds
+    }
+
+    /// Thresholds consumed by the adaptation policy.
+    pub fn thresholds(&self) -> &AdaptationThresholds {
+        &self.thresholds
+    }
+
+    /// Frames covered by each parity datagram, or 0 when FEC is off.
+    pub fn fec_group_size(&self) -> u8 {
+        self.fec_group_size
+    }
+
+    /// Delivery budget added to a frame's `timestamp_us` to form its deadline.
+    ///
+    /// Derived from the intent only, so the value is already covered by `config_id`.
+    pub fn delivery_deadline_us(&self) -> u64 {
+        match self.intent {
+            StreamIntent::Realtime => 20_000,
+            StreamIntent::Auto => 40_000,
+            StreamIntent::Install => 80_000,
+        }
+    }
 }
 
 impl Default for StreamProfile {
@@ -180,6 +481,84 @@ mod tests {
         assert_ne!(realtime.config_id(), install.config_id());
     }
 
+    #[test]
+    fn confidentiality_changes_config_id() {
+        let plain = StreamProfile::auto().compile().unwrap();
+        let sealed = StreamProfile::auto()
+            .with_confidentiality(true)
+            .compile()
+            .unwrap();
+        assert!(sealed.confidentiality());
+        assert_ne!(plain.config_id(), sealed.config_id());
+    }
+
+    #[test]
+    fn tuned_adaptation_changes_config_id() {
+        let base = StreamProfile::install().compile().unwrap();
+        let thresholds = AdaptationThresholds {
+            loss_keyframe: 0.1,
+            ..AdaptationThresholds::default()
+        };
+        let tuned = StreamProfile::install()
+            .with_thresholds(thresholds)
+            .compile()
+            .unwrap();
+        assert_eq!(tuned.thresholds().loss_keyframe, 0.1);
+        assert_ne!(base.config_id(), tuned.config_id());
+
+        let bounds = ProfileBounds {
+            min_keyframe_interval: 2,
+            ..ProfileBounds::for_intent(StreamIntent::Install)
+        };
+        let bounded = StreamProfile::install()
+            .with_bounds(bounds)
+            .compile()
+            .unwrap();
+        assert_ne!(base.config_id(), bounded.config_id());
+        assert_ne!(tuned.config_id(), bounded.config_id());
+    }
+
+    #[test]
+    fn reject_invalid_adaptation_settings() {
+        let bounds = ProfileBounds {
+            min_keyframe_interval: 20,
+            ..ProfileBounds::for_intent(StreamIntent::Auto)
+        };
+        assert!(matches!(
+            StreamProfile::auto().with_bounds(bounds).compile(),
+            Err(ProfileError::InvalidBounds(_))
+        ));
+        let thresholds = AdaptationThresholds {
+            late_delta: 1.5,
+            ..AdaptationThresholds::default()
+        };
+        assert!(matches!(
+            StreamProfile::auto().with_thresholds(thresholds).compile(),
+            Err(ProfileError::InvalidThresholds(_))
+        ));
+    }
+
+    #[test]
+    fn fec_overhead_follows_intent() {
+        let realtime = StreamProfile::realtime().compile().unwrap();
+        let install = StreamProfile::install().compile().unwrap();
+        assert_eq!(realtime.fec_group_size(), 0);
+        assert!(install.fec_group_size() > 0);
+
+        let tuned = StreamProfile::install()
+            .with_fec_group_size(2)
+            .compile()
+            .unwrap();
+        assert_eq!(tuned.fec_group_size(), 2);
+        assert_ne!(install.config_id(), tuned.config_id());
+        assert!(matches!(
+            StreamProfile::install()
+                .with_fec_group_size(MAX_FEC_GROUP_SIZE + 1)
+                .compile(),
+            Err(ProfileError::FecGroupTooLarge(_))
+        ));
+    }
+
     #[test]
     fn reject_zero_weights() {
         let profile = StreamProfile::with_weights(StreamIntent::Auto, 0, 0);
@@ -1,3 +1,4 @@
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
@@ -10,7 +11,7 @@ use crate::handshake::{
     HandshakeError, HandshakeOutcome, HandshakeParticipant, HandshakeTransport,
 };
 use crate::messages::{CapabilitySet, DeviceIdentity, SessionEstablished};
-use crate::profile::{CompiledStreamProfile, StreamProfile};
+use crate::profile::CompiledStreamProfile;
 
 pub mod state;
 use state::{SessionState, SessionStateError};
@@ -44,8 +45,10 @@ pub struct AlnpSession {
     timeout: Duration,
     session_established: Arc<Mutex<Option<SessionEstablished>>>,
     session_keys: Arc<Mutex<Option<SessionKeys>>>,
+    local_capabilities: Arc<Mutex<Option<CapabilitySet>>>,
     compiled_profile: Arc<Mutex<Option<CompiledStreamProfile>>>,
     profile_locked: Arc<Mutex<bool>>,
+    last_stream_epoch: Arc<AtomicU64>,
 }
 
 impl AlnpSession {
@@ -59,8 +62,10 @@ impl AlnpSession {
             timeout: Duration::from_secs(10),
             session_established: Arc::new(Mutex::new(None)),
             session_keys: Arc::new(Mutex::new(None)),
+            local_capabilities: Arc::new(Mutex::new(None)),
             compiled_profile: Arc::new(Mutex::new(None)),
             profile_locked: Arc::new(Mutex::new(false)),
+            last_stream_epoch: Arc::new(AtomicU64::new(0)),
         }
     }
 
@@ -72,6 +77,32 @@ impl AlnpSession {
         self.session_keys.lock().ok().and_then(|k| k.clone())
     }
 
+    /// Capabilities this side advertised during the handshake.
+    ///
+    /// `established().capabilities` always describes the peer.
+    pub fn local_capabilities(&self) -> Option<CapabilitySet> {
+        self.local_capabilities.lock().ok().and_then(|c| c.clone())
+    }
+
+    /// Returns `true` when both peers advertised `encryption_supported`.
+    pub fn encryption_negotiated(&self) -> bool {
+        let local = self
+            .local_capabilities()
+            .map(|caps| caps.encryption_supported)
+            .unwrap_or(false);
+        let peer = self
+            .established()
+            .map(|est| est.capabilities.encryption_supported)
+            .unwrap_or(false);
+        local && peer
+    }
+
+    /// Returns a streaming epoch greater than every epoch handed out on this
+    /// session before, starting at 1.
+    pub(crate) fn next_stream_epoch(&self) -> u64 {
+        self.last_stream_epoch.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
     pub fn state(&self) -> SessionState {
         self.state
             .lock()
@@ -156,13 +187,6 @@ impl AlnpSession {
             .and_then(|guard| guard.clone())
     }
 
-    #[cfg(test)]
-    pub(crate) fn set_locked_profile_for_testing(&self, profile: CompiledStreamProfile) {
-        let mut compiled = self.compiled_profile.lock().unwrap();
-        *compiled = Some(profile);
-        *self.profile_locked.lock().unwrap() = true;
-    }
-
     pub fn set_jitter_strategy(&self, strat: JitterStrategy) {
         if let Ok(mut j) = self.jitter.lock() {
             *j = strat;
@@ -221,13 +245,16 @@ impl AlnpSession {
         self.streaming_enabled.lock().map(|f| *f).unwrap_or(false)
     }
 
-    fn apply_outcome(&self, outcome: HandshakeOutcome) {
+    fn apply_outcome(&self, outcome: HandshakeOutcome, local: CapabilitySet) {
         if let Ok(mut guard) = self.session_established.lock() {
             *guard = Some(outcome.established);
         }
         if let Ok(mut guard) = self.session_keys.lock() {
             *guard = Some(outcome.keys);
         }
+        if let Ok(mut guard) = self.local_capabilities.lock() {
+            *guard = Some(local);
+        }
     }
 
     pub async fn connect<T, A, K>(
@@ -247,7 +274,7 @@ impl AlnpSession {
         session.transition(SessionState::Handshake)?;
         let driver = ClientHandshake {
             identity,
-            capabilities,
+            capabilities: capabilities.clone(),
             authenticator,
             key_exchange,
             context,
@@ -260,7 +287,7 @@ impl AlnpSession {
         session.transition(SessionState::Ready {
             since: Instant::now(),
         })?;
-        session.apply_outcome(outcome);
+        session.apply_outcome(outcome, capabilities);
         Ok(session)
     }
 
@@ -281,7 +308,7 @@ impl AlnpSession {
         session.transition(SessionState::Handshake)?;
         let driver = ServerHandshake {
             identity,
-            capabilities,
+            capabilities: capabilities.clone(),
             authenticator,
             key_exchange,
             context,
@@ -294,7 +321,7 @@ impl AlnpSession {
         session.transition(SessionState::Ready {
             since: Instant::now(),
         })?;
-        session.apply_outcome(outcome);
+        session.apply_outcome(outcome, capabilities);
         Ok(session)
     }
 }
@@ -365,38 +392,9 @@ impl LoopbackTransport {
     }
 }
 
-#[cfg(test)]
-mod session_tests {
-    use super::*;
-
-    #[test]
-    fn profile_lock_prevents_profile_swaps() {
-        let session = AlnpSession::new(AlnpRole::Controller);
-        let compiled = StreamProfile::auto().compile().unwrap();
-        session.set_stream_profile(compiled.clone()).unwrap();
-        session.mark_streaming();
-        assert!(session.set_stream_profile(compiled).is_err());
-    }
-
-    #[test]
-    fn config_id_matches_profile() {
-        let session = AlnpSession::new(AlnpRole::Controller);
-        let compiled = StreamProfile::realtime().compile().unwrap();
-        session.set_stream_profile(compiled.clone()).unwrap();
-        assert_eq!(session.profile_config_id().unwrap(), compiled.config_id());
-    }
-
-    #[test]
-    fn config_id_stays_locked_after_streaming() {
-        let session = AlnpSession::new(AlnpRole::Controller);
-        let compiled = StreamProfile::install().compile().unwrap();
-        session.set_stream_profile(compiled.clone()).unwrap();
-        let before_config = session.profile_config_id().unwrap();
-        session.mark_streaming();
-        assert_eq!(session.profile_config_id().unwrap(), before_config);
-        assert!(session
-            .set_stream_profile(StreamProfile::default().compile().unwrap())
-            .is_err());
+impl Default for LoopbackTransport {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -449,3 +447,39 @@ pub async fn example_node_session<T: HandshakeTransport + Send>(
     )
     .await
 }
+
+#[cfg(test)]
+mod session_tests {
+    use super::*;
+    use crate::profile::StreamProfile;
+
+    #[test]
+    fn profile_lock_prevents_profile_swaps() {
+        let session = AlnpSession::new(AlnpRole::Controller);
+        let compiled = StreamProfile::auto().compile().unwrap();
+        session.set_stream_profile(compiled.clone()).unwrap();
+        session.mark_streaming();
+        assert!(session.set_stream_profile(compiled).is_err());
+    }
+
+    #[test]
+    fn config_id_matches_profile() {
+        let session = AlnpSession::new(AlnpRole::Controller);
+        let compiled = StreamProfile::realtime().compile().unwrap();
+        session.set_stream_profile(compiled.clone()).unwrap();
+        assert_eq!(session.profile_config_id().unwrap(), compiled.config_id());
+    }
+
+    #[test]
+    fn config_id_stays_locked_after_streaming() {
+        let session = AlnpSession::new(AlnpRole::Controller);
+        let compiled = StreamProfile::install().compile().unwrap();
+        session.set_stream_profile(compiled.clone()).unwrap();
+        let before_config = session.profile_config_id().unwrap();
+        session.mark_streaming();
+        assert_eq!(session.profile_config_id().unwrap(), before_config);
+        assert!(session
+            .set_stream_profile(StreamProfile::default().compile().unwrap())
+            .is_err());
+    }
+}
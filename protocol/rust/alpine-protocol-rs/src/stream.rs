@@ -1,14 +1,23 @@
-use serde_json::{json, Value};
 use std::collections::HashMap;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use thiserror::Error;
-use tracing::{info, warn};
+use tracing::{debug, info, warn};
 
-use crate::messages::{ChannelFormat, FrameEnvelope, MessageType};
+use crate::crypto::SessionKeys;
+use crate::messages::packed::{FrameRef, PackedChannels, PackedGroups, PackedRanges};
+use crate::messages::{
+    AdaptationHeader, CapabilitySet, ChannelBuffer, ChannelFormat, ChannelRange, ErrorCode,
+    KeyframeRequest, MessageType, ReceiverReport,
+};
 use crate::profile::CompiledStreamProfile;
-use crate::session::{AlnpSession, JitterStrategy};
-use crate::stream::adaptive::{decide_next_state, AdaptationState};
+use crate::session::AlnpSession;
+use crate::stream::auth::FrameScratch;
+use crate::stream::delta::{DeltaEncoder, EncodedFrame};
+use crate::stream::epoch::{new_epoch, EpochKeys, PeerEpochs};
+use crate::stream::events::EventBus;
+use crate::stream::fec::ParityEncoder;
+use crate::stream::network::smooth_rtt;
 
 /// Minimal transport for sending serialized ALPINE frames (UDP/QUIC left to the caller).
 pub trait FrameTransport: Send + Sync {
@@ -21,11 +30,119 @@ pub trait FrameTransport: Send + Sync {
 pub struct AlnpStream<T: FrameTransport> {
     session: AlnpSession,
     transport: T,
-    last_frame: parking_lot::Mutex<Option<FrameEnvelope>>,
+    stream_id: u8,
+    epoch: u64,
+    keys: parking_lot::Mutex<EpochKeys>,
+    receiver_epochs: parking_lot::Mutex<PeerEpochs>,
+    sequence: parking_lot::Mutex<u64>,
+    encoder: parking_lot::Mutex<DeltaEncoder>,
+    scratch: parking_lot::Mutex<EncodeScratch>,
     profile: CompiledStreamProfile,
     recovery: parking_lot::Mutex<RecoveryMonitor>,
     recovery_reason: parking_lot::Mutex<Option<RecoveryReason>>,
     adaptation: parking_lot::Mutex<AdaptationState>,
+    policy: Box<dyn AdaptationPolicy>,
+    last_report: parking_lot::Mutex<Option<ReceiverReport>>,
+    keyframe_requests: parking_lot::Mutex<KeyframeRequests>,
+    rtt_us: parking_lot::Mutex<Option<f64>>,
+    events: EventBus,
+    max_datagram_size: usize,
+    parity: Option<parking_lot::Mutex<ParityEncoder>>,
+    redundant_paths: RedundantPaths<dyn FrameTransport>,
+    groups: parking_lot::Mutex<ChannelGroups>,
+}
+
+/// Channel payload handed to [`AlnpStream::send_encoded`].
+enum Update<'a> {
+    Full(&'a ChannelBuffer),
+    Partial(&'a ChannelFormat, &'a [ChannelRange]),
+}
+
+/// Extra transports every datagram is duplicated onto, SMPTE 2022-7 style.
+struct RedundantPaths<P: ?Sized>(Vec<Box<P>>);
+
+impl<P: ?Sized> Default for RedundantPaths<P> {
+    fn default() -> Self {
+        Self(Vec::new())
+    }
+}
+
+impl<P: ?Sized> std::fmt::Debug for RedundantPaths<P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedundantPaths")
+            .field("count", &self.0.len())
+            .finish()
+    }
+}
+
+/// Merges the results of sending one datagram on several paths.
+#[derive(Default)]
+struct PathDelivery {
+    delivered: bool,
+    first_error: Option<String>,
+}
+
+impl PathDelivery {
+    fn record(&mut self, path: usize, result: Result<(), String>) {
+        match result {
+            Ok(()) => self.delivered = true,
+            Err(err) => {
+                warn!(target: "alpine::stream", path, "path send failed: {}", err);
+                self.first_error.get_or_insert(err);
+            }
+        }
+    }
+
+    /// Succeeds if any path accepted the datagram.
+    fn finish(self) -> Result<(), StreamError> {
+        match self.first_error {
+            Some(err) if !self.delivered => Err(StreamError::Transport(err)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Datagrams produced for one frame besides the frame bytes, which stay in
+/// the encode scratch buffer.
+struct Outgoing {
+    /// Authenticated fragments replacing an oversized frame datagram.
+    fragments: Option<Vec<Vec<u8>>>,
+    /// Parity datagram completing an FEC group.
+    parity: Option<Vec<u8>>,
+}
+
+impl Outgoing {
+    /// Datagrams to transmit, in order, for the encoded `frame`.
+    fn datagrams<'a>(&'a self, frame: &'a [u8]) -> impl Iterator<Item = &'a [u8]> {
+        let (whole, fragments) = match &self.fragments {
+            Some(fragments) => (None, fragments.as_slice()),
+            None => (Some(frame), &[][..]),
+        };
+        whole
+            .into_iter()
+            .chain(fragments.iter().map(Vec::as_slice))
+            .chain(self.parity.as_deref())
+    }
+}
+
+/// Minimum spacing between receiver-requested keyframes.
+pub const KEYFRAME_REQUEST_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How `AlnpStream` answered a keyframe request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyframeRequestOutcome {
+    /// The next frame will be a keyframe.
+    Scheduled,
+    /// A requested keyframe was already forced within [`KEYFRAME_REQUEST_INTERVAL`].
+    RateLimited,
+}
+
+#[derive(Debug, Default)]
+struct KeyframeRequests {
+    epoch: u64,
+    last_request_sequence: u64,
+    pending: bool,
+    last_scheduled_us: Option<u64>,
 }
 
 /// Errors emitted from the streaming helper.
@@ -39,47 +156,387 @@ pub enum StreamError {
     StreamingDisabled,
     #[error("no session available")]
     MissingSession,
+    #[error("decode error: {0}")]
+    Decode(String),
+    #[error("frame belongs to a different session")]
+    SessionMismatch,
+    #[error("frame authentication failed")]
+    SessionMacMismatch,
+    #[error("profile requires encryption but it was not negotiated")]
+    EncryptionUnavailable,
+    #[error("cleartext frame rejected by confidential profile")]
+    EncryptionRequired,
+    #[error("delta base frame {0} not available; waiting for keyframe")]
+    DeltaBaseMissing(u64),
+    #[error("encoded frame of {0} bytes exceeds the fragmentation limit")]
+    FrameTooLarge(usize),
+    #[error("fragment buffered; frame not yet complete")]
+    FragmentPending,
+    #[error("parity datagram had no lost frame to rebuild")]
+    ParityUnused,
+    #[error("duplicate of frame {0} already received on another path")]
+    DuplicateFrame(u64),
+    #[error("no redundant path {0} configured")]
+    UnknownPath(usize),
+    #[error("partial update needs a full frame in the same format first")]
+    PartialBaseMissing,
+    #[error("partial update range at offset {0} exceeds the channel count")]
+    PartialRangeOutOfBounds(u32),
+    #[error("partial update range at offset {0} is not in the frame's channel format")]
+    PartialRangeFormat(u32),
+    #[error("frame pacer rate must be above zero")]
+    ZeroFrameRate,
+    #[error("message belongs to stream {0}, not this stream")]
+    StreamMismatch(u8),
+    #[error("no receiver registered for stream {0}")]
+    UnknownStream(u8),
+    #[error("channel format {0:?} was not negotiated")]
+    UnsupportedChannelFormat(ChannelFormat),
+    #[error("frame has {0} channels; at most {1} were negotiated")]
+    TooManyChannels(usize, u32),
+    #[error("channel groups were not negotiated")]
+    GroupingUnsupported,
+    #[error("channel group {0:?} is not defined")]
+    UnknownGroup(String),
+    #[error("channel group {0:?} needs one value or one per member")]
+    GroupValueCount(String),
+    #[error("channel group {0:?} values are not in the frame's channel format")]
+    GroupFormat(String),
+    #[error("group member channel {0} exceeds the channel count")]
+    GroupMemberOutOfBounds(u32),
+}
+
+impl StreamError {
+    /// Maps the error onto the spec-defined error code, when one exists.
+    pub fn error_code(&self) -> Option<ErrorCode> {
+        match self {
+            StreamError::SessionMacMismatch => Some(ErrorCode::SessionMacMismatch),
+            StreamError::FrameTooLarge(_) | StreamError::TooManyChannels(..) => {
+                Some(ErrorCode::StreamTooLarge)
+            }
+            StreamError::UnsupportedChannelFormat(_) => Some(ErrorCode::StreamBadFormat),
+            StreamError::GroupingUnsupported => Some(ErrorCode::StreamUnsupportedChannelMode),
+            _ => None,
+        }
+    }
 }
 
+mod auth;
+
+mod delta;
+
+mod epoch;
+
 mod network;
 
-pub use network::{NetworkConditions, NetworkMetrics};
+pub use network::{NetworkConditions, NetworkMetrics, DEFAULT_METRICS_HORIZON};
 
 mod recovery;
 
-pub use recovery::{RecoveryEvent, RecoveryMonitor, RecoveryReason};
+pub use recovery::{RecoveryEvent, RecoveryMonitor};
 
 mod adaptive;
 
+pub use adaptive::{
+    AdaptationDecision, AdaptationPolicy, AdaptationSnapshot, AdaptationState,
+    DefaultAdaptationPolicy,
+};
+
+pub use crate::messages::{AdaptationEvent, DegradedReason, RecoveryReason};
+
+mod fec;
+
+mod groups;
+
+pub use groups::ChannelGroups;
+
+mod framebuf;
+
+pub use framebuf::FrameBuf;
+
+use framebuf::EncodeScratch;
+
+mod pacer;
+
+pub use pacer::{FramePacer, PacerHandle, PacerStats, DEFAULT_IDLE_REFRESH};
+
+mod async_stream;
+
+pub use async_stream::{AsyncAdapter, AsyncAlnpStream, AsyncFrameTransport, BlockingAdapter};
+
+mod fragment;
+
+pub use fragment::{
+    DEFAULT_MAX_DATAGRAM_SIZE, DEFAULT_REASSEMBLY_TIMEOUT, MAX_FRAGMENTS, MIN_DATAGRAM_SIZE,
+};
+
+mod events;
+
+pub use events::{StreamEvent, StreamEventKind, EVENT_CHANNEL_CAPACITY};
+
+mod jitter;
+
+pub use jitter::{ConcealedFrame, PlayoutFrame, MAX_CONCEALED_FRAMES};
+
+mod playout;
+
+pub use playout::{PlayoutBuffer, PlayoutPush, DEFAULT_REORDER_WINDOW};
+
+mod receiver;
+
+pub use receiver::{AlnpStreamReceiver, DEFAULT_REPORT_INTERVAL};
+
+mod replay;
+
+pub use replay::{ReplayWindow, REPLAY_WINDOW};
+
+mod demux;
+
+pub use demux::{peek_stream_id, StreamDemux};
+
 impl<T: FrameTransport> AlnpStream<T> {
     /// Builds a new streaming helper bound to a compiled profile.
     pub fn new(session: AlnpSession, transport: T, profile: CompiledStreamProfile) -> Self {
-        let intent = profile.intent();
+        let adaptation = AdaptationState::baseline(&profile);
+        let parity = parity_encoder(&profile, DEFAULT_MAX_DATAGRAM_SIZE);
         Self {
+            epoch: new_epoch(&session),
             session,
             transport,
-            last_frame: parking_lot::Mutex::new(None),
+            stream_id: 0,
+            keys: parking_lot::Mutex::new(EpochKeys::default()),
+            receiver_epochs: parking_lot::Mutex::new(PeerEpochs::default()),
+            sequence: parking_lot::Mutex::new(0),
+            encoder: parking_lot::Mutex::new(DeltaEncoder::default()),
+            scratch: parking_lot::Mutex::new(EncodeScratch::default()),
             profile,
             recovery: parking_lot::Mutex::new(RecoveryMonitor::new()),
             recovery_reason: parking_lot::Mutex::new(None),
-            adaptation: parking_lot::Mutex::new(AdaptationState::baseline(intent)),
+            adaptation: parking_lot::Mutex::new(adaptation),
+            policy: Box::new(DefaultAdaptationPolicy),
+            last_report: parking_lot::Mutex::new(None),
+            keyframe_requests: parking_lot::Mutex::new(KeyframeRequests::default()),
+            rtt_us: parking_lot::Mutex::new(None),
+            events: EventBus::new(),
+            max_datagram_size: DEFAULT_MAX_DATAGRAM_SIZE,
+            parity,
+            redundant_paths: RedundantPaths::default(),
+            groups: parking_lot::Mutex::new(ChannelGroups::new()),
         }
     }
 
+    /// Replaces the [`DefaultAdaptationPolicy`] with a custom decision policy.
+    pub fn with_policy(mut self, policy: impl AdaptationPolicy + 'static) -> Self {
+        self.policy = Box::new(policy);
+        self
+    }
+
+    /// Sends as logical stream `stream_id` within the session; defaults to 0.
+    ///
+    /// Concurrent streams on one session should use distinct ids. Reusing an
+    /// id is never a nonce reuse, since every sender seals under its own
+    /// [`epoch`](AlnpStream::epoch) key, but a receiver follows only the
+    /// newest sender of an id and rejects frames from the one it replaced.
+    pub fn with_stream_id(mut self, stream_id: u8) -> Self {
+        self.stream_id = stream_id;
+        self
+    }
+
+    /// Logical stream this sender stamps on its frames.
+    pub fn stream_id(&self) -> u8 {
+        self.stream_id
+    }
+
+    /// Identifier of this sender, stamped on every datagram it sends; greater
+    /// than the epoch of every stream created on the session before it.
+    ///
+    /// Frames are sealed with a key derived for the stream id and epoch, so a
+    /// stream recreated on the same session starts a fresh key instead of
+    /// repeating nonces, and receivers follow it from sequence 1 while
+    /// rejecting the older epoch it replaced.
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Splits frames whose datagram would exceed `size` bytes into fragments.
+    ///
+    /// Defaults to [`DEFAULT_MAX_DATAGRAM_SIZE`]; values below
+    /// [`MIN_DATAGRAM_SIZE`] are raised to it.
+    pub fn with_max_datagram_size(mut self, size: usize) -> Self {
+        self.max_datagram_size = size.max(MIN_DATAGRAM_SIZE);
+        self.parity = parity_encoder(&self.profile, self.max_datagram_size);
+        self
+    }
+
+    /// Duplicates every datagram onto `transport` as an additional network path.
+    ///
+    /// Paths are numbered in the order they are added, after the primary
+    /// transport (path 0). A send succeeds while at least one path accepts the
+    /// datagram; failures on individual paths are logged and otherwise ignored,
+    /// so losing one network causes no gap at a receiver that merges paths
+    /// (see [`AlnpStreamReceiver::with_paths`]).
+    pub fn with_redundant_path(mut self, transport: impl FrameTransport + 'static) -> Self {
+        self.redundant_paths.0.push(Box::new(transport));
+        self
+    }
+
+    /// Installs the channel groups the device acknowledged through
+    /// `ControlOp::DefineGroups`, replacing earlier definitions.
+    ///
+    /// Frames may only reference groups after the device has them, so call
+    /// this once the control acknowledgement arrived.
+    pub fn define_groups(&self, groups: ChannelGroups) {
+        *self.groups.lock() = groups;
+    }
+
+    /// Subscribes to the recovery and adaptation events raised by this stream.
+    ///
+    /// # Guarantees
+    /// * Delivers every [`RecoveryEvent`] and [`AdaptationEvent`] logged after
+    ///   the call, in order, each with a timestamp, the metrics behind it, and
+    ///   the resulting adaptation state.
+    /// * Never blocks the sender: a subscriber more than
+    ///   [`EVENT_CHANNEL_CAPACITY`] events behind receives `RecvError::Lagged`.
+    pub fn subscribe_events(&self) -> tokio::sync::broadcast::Receiver<StreamEvent> {
+        self.events.subscribe()
+    }
+
     /// Sends a streaming frame built from raw channel data.
     ///
     /// # Guarantees
     /// * Only sends when the session is already authenticated and streaming-enabled.
-    /// * Applies jitter strategy derived from the compiled profile; no branching on
-    ///   user-facing preferences happens at this layer.
+    /// * Sends `channels` exactly as given; jitter strategies are applied by the
+    ///   receiving node (see [`AlnpStreamReceiver::poll_playout`]).
+    /// * Stamps every frame with the next per-stream `sequence`, starting at 1.
+    /// * Emits a keyframe on the adaptive cadence, during recovery, after an
+    ///   accepted keyframe request, and whenever the format or channel count
+    ///   changes; otherwise emits a delta against a base no more than
+    ///   `delta_depth` frames deep (keyframes only in degraded-safe).
+    /// * Authenticates every frame with a tag keyed by this sender's
+    ///   [`epoch`](AlnpStream::epoch) key, derived from the session
+    ///   `stream_key`.
+    /// * Encrypts the payload when the profile asks for confidentiality; fails
+    ///   with [`StreamError::EncryptionUnavailable`] if either peer lacks
+    ///   support.
+    /// * Splits frames larger than the maximum datagram size into authenticated
+    ///   fragments (at most [`MAX_FRAGMENTS`]; larger frames fail with
+    ///   [`StreamError::FrameTooLarge`]).
+    /// * A frame that fails after taking its sequence (sealing, fragmentation
+    ///   or the transport) is followed by a keyframe, so no delta is ever
+    ///   based on a frame the receiver may not have.
+    /// * When the profile enables FEC, follows every `fec_group_size` frames
+    ///   with an authenticated XOR parity datagram, unless the parity would not
+    ///   fit in one datagram.
+    /// * Sets the member channels of every group in `groups` (see
+    ///   [`ChannelGroups`]) on top of `channels`; fails with
+    ///   [`StreamError::GroupingUnsupported`] when the peer does not advertise
+    ///   `grouping_supported`, and with the [`ChannelGroups`] errors for
+    ///   undefined groups or bad values, in both cases without consuming a
+    ///   sequence.
+    /// * Validates every frame against the peer's negotiated
+    ///   [`CapabilitySet`] before consuming a sequence: formats it does not
+    ///   list fail with [`StreamError::UnsupportedChannelFormat`] and more
+    ///   than `max_channels` channels with [`StreamError::TooManyChannels`].
+    ///   Pixel formats count every component as a channel.
+    /// * Sends in the format of `channels`; use [`ChannelBuffer::convert`] to
+    ///   reach a format the peer lists.
     pub fn send(
+        &self,
+        channels: impl Into<ChannelBuffer>,
+        priority: u8,
+        groups: Option<HashMap<String, ChannelBuffer>>,
+        metadata: Option<HashMap<String, serde_json::Value>>,
+    ) -> Result<(), StreamError> {
+        self.send_update(Update::Full(&channels.into()), priority, groups, metadata)
+    }
+
+    /// Sends a sparse update that changes only the given channel ranges.
+    ///
+    /// # Guarantees
+    /// * Applies `ranges` in order on top of the last frame sent, which must
+    ///   use the same `channel_format`; otherwise fails with
+    ///   [`StreamError::PartialBaseMissing`] without consuming a sequence.
+    ///   Ranges in another format fail with [`StreamError::PartialRangeFormat`].
+    /// * Fails with [`StreamError::PartialRangeOutOfBounds`] when a range runs
+    ///   past the current channel count; partial updates never resize.
+    /// * Otherwise behaves exactly like [`AlnpStream::send`] with the updated
+    ///   channel vector: the frame is a delta naming its `base_sequence`, so a
+    ///   receiver applies it only on top of that frame, and the keyframe
+    ///   cadence still sends the full vector as a periodic refresh.
+    /// * With empty `ranges`, `groups` alone make up the update: only their
+    ///   member channels change, and the delta carries no channel values.
+    pub fn send_partial(
         &self,
         channel_format: ChannelFormat,
-        channels: Vec<u16>,
+        ranges: Vec<ChannelRange>,
+        priority: u8,
+        groups: Option<HashMap<String, ChannelBuffer>>,
+        metadata: Option<HashMap<String, serde_json::Value>>,
+    ) -> Result<(), StreamError> {
+        self.send_update(
+            Update::Partial(&channel_format, &ranges),
+            priority,
+            groups,
+            metadata,
+        )
+    }
+
+    /// Sends a frame encoded straight from a caller-owned [`FrameBuf`].
+    ///
+    /// # Guarantees
+    /// * Behaves exactly like [`AlnpStream::send`] with the buffer's format and
+    ///   channels and no groups or metadata; receivers cannot tell the two apart.
+    /// * Leaves the channel values untouched, so the next frame only needs the
+    ///   channels that changed to be written.
+    /// * Performs no heap allocation for frames that fit in one datagram once
+    ///   the buffer and the stream have sent a frame of the same size;
+    ///   fragmentation and FEC parity still allocate.
+    pub fn send_with(&self, buf: &mut FrameBuf, priority: u8) -> Result<(), StreamError> {
+        let (channels, scratch) = buf.parts();
+        self.send_encoded(Update::Full(channels), priority, None, None, scratch)
+    }
+
+    fn send_update(
+        &self,
+        update: Update<'_>,
         priority: u8,
-        groups: Option<HashMap<String, Vec<u16>>>,
+        groups: Option<HashMap<String, ChannelBuffer>>,
         metadata: Option<HashMap<String, serde_json::Value>>,
     ) -> Result<(), StreamError> {
+        self.send_encoded(
+            update,
+            priority,
+            groups.as_ref(),
+            metadata.as_ref(),
+            &mut self.scratch.lock(),
+        )
+    }
+
+    fn send_encoded(
+        &self,
+        update: Update<'_>,
+        priority: u8,
+        groups: Option<&HashMap<String, ChannelBuffer>>,
+        metadata: Option<&HashMap<String, serde_json::Value>>,
+        scratch: &mut EncodeScratch,
+    ) -> Result<(), StreamError> {
+        let outgoing = self.encode_update(update, priority, groups, metadata, scratch)?;
+        for datagram in outgoing.datagrams(&scratch.datagram) {
+            self.resync_on_error(self.transmit(datagram))?;
+        }
+        Ok(())
+    }
+
+    /// Encodes one frame into `scratch.datagram`, along with any fragments and
+    /// parity to send with it.
+    fn encode_update(
+        &self,
+        update: Update<'_>,
+        priority: u8,
+        groups: Option<&HashMap<String, ChannelBuffer>>,
+        metadata: Option<&HashMap<String, serde_json::Value>>,
+        scratch: &mut EncodeScratch,
+    ) -> Result<Outgoing, StreamError> {
         let established = self
             .session
             .ensure_streaming_ready()
@@ -87,53 +544,361 @@ impl<T: FrameTransport> AlnpStream<T> {
         if !self.session.streaming_enabled() {
             return Err(StreamError::StreamingDisabled);
         }
+        let keys = self
+            .keys
+            .lock()
+            .get(&self.session, self.stream_id, self.epoch)?;
+        let encrypt = self.profile.confidentiality();
+        if encrypt && !self.session.encryption_negotiated() {
+            return Err(StreamError::EncryptionUnavailable);
+        }
+        let EncodeScratch {
+            packed,
+            spans,
+            frame: frame_scratch,
+            datagram,
+        } = scratch;
 
-        let adjusted_channels = self.apply_jitter(&channels);
+        // Hold the encoder across the whole update so concurrent partial
+        // updates each apply on top of the frame sent before them.
+        let mut encoder = self.encoder.lock();
+        let applied;
+        let channels = match update {
+            Update::Full(channels) => channels,
+            Update::Partial(channel_format, ranges) => {
+                applied = encoder.apply_partial(channel_format, ranges)?;
+                &applied
+            }
+        };
+        let channel_format = channels.format();
+        let group_values = groups.filter(|values| !values.is_empty());
+        check_capabilities(
+            &established.capabilities,
+            &channel_format,
+            channels.channel_count(),
+            group_values.is_some(),
+        )?;
+        let group_table = group_values.map(|_| self.groups.lock());
+        let grouped;
+        let channels = match (&group_table, group_values) {
+            (Some(table), Some(values)) => {
+                let mut channels = channels.clone();
+                table.apply(&mut channels, values)?;
+                grouped = channels;
+                &grouped
+            }
+            _ => channels,
+        };
+        packed.clear();
+        channels
+            .pack_into(packed)
+            .map_err(|e| StreamError::Transport(format!("encode: {}", e)))?;
+        let recovering = self.recovery_reason.lock().is_some();
+        let sequence = self.next_sequence();
         let mut adaptation = self.adaptation.lock();
-        let should_force_keyframe = adaptation.should_emit_keyframe();
-        let adaptation_snapshot = adaptation.clone();
+        let requested = std::mem::take(&mut self.keyframe_requests.lock().pending);
+        let should_force_keyframe = adaptation.should_emit_keyframe() || recovering || requested;
+        let delta_depth = if adaptation.degraded_safe {
+            0
+        } else {
+            adaptation.delta_depth
+        };
+        let encoded = encoder.encode(
+            sequence,
+            channels,
+            should_force_keyframe,
+            delta_depth,
+            spans,
+        );
+        drop(encoder);
+        if let (Some(table), Some(values)) = (&group_table, group_values) {
+            if encoded != EncodedFrame::Keyframe {
+                table.exclude_members(values, spans);
+            }
+        }
+        drop(group_table);
+        if encoded == EncodedFrame::Keyframe {
+            adaptation.reset_keyframe_counter();
+        }
+        let header = self.adaptation_header(should_force_keyframe, &adaptation);
         drop(adaptation);
-        let metadata =
-            self.annotate_metadata(metadata, should_force_keyframe, &adaptation_snapshot);
 
-        let envelope = FrameEnvelope {
-            message_type: MessageType::AlpineFrame,
-            session_id: established.session_id,
-            timestamp_us: Self::now_us(),
+        let (frame_channels, base_sequence, delta) = match encoded {
+            EncodedFrame::Keyframe => (PackedChannels::Packed(packed), None, None),
+            EncodedFrame::Delta { base_sequence } => (
+                PackedChannels::Packed(&[]),
+                Some(base_sequence),
+                Some(PackedRanges::Spans {
+                    packed,
+                    width: channel_format.slot_width(),
+                    spans,
+                }),
+            ),
+        };
+        let frame = FrameRef {
+            message_type: &MessageType::AlpineFrame,
+            session_id: &established.session_id,
+            stream_id: self.stream_id,
+            epoch: self.epoch,
+            sequence,
+            timestamp_us: now_us(),
             priority,
             channel_format,
-            channels: adjusted_channels,
-            groups,
+            channels: frame_channels,
+            groups: groups.map(|groups| PackedGroups {
+                format: channel_format,
+                groups,
+            }),
             metadata,
+            adaptation: Some(header),
+            kind: encoded.kind(),
+            base_sequence,
+            delta,
+            ciphertext: None,
+            mac: &[],
+        };
+        // The encoder already references this frame, so one that never
+        // reaches the receiver must be followed by a keyframe.
+        self.resync_on_error(self.seal(&keys, frame, encrypt, frame_scratch, datagram))
+    }
+
+    /// Authenticates an encoded frame into `datagram` and prepares the
+    /// fragments and parity sent with it.
+    fn seal(
+        &self,
+        keys: &SessionKeys,
+        frame: FrameRef<'_>,
+        encrypt: bool,
+        frame_scratch: &mut FrameScratch,
+        datagram: &mut Vec<u8>,
+    ) -> Result<Outgoing, StreamError> {
+        let session_id = *frame.session_id;
+        let sequence = frame.sequence;
+        auth::encode_frame_into(keys, frame, encrypt, frame_scratch, datagram)?;
+        let bytes = datagram.as_slice();
+        let fragments = if bytes.len() > self.max_datagram_size {
+            Some(fragment::fragment_frame(
+                keys,
+                session_id,
+                self.stream_id,
+                self.epoch,
+                sequence,
+                bytes,
+                self.max_datagram_size,
+            )?)
+        } else {
+            None
         };
 
-        let bytes = serde_cbor::to_vec(&envelope)
-            .map_err(|e| StreamError::Transport(format!("encode: {}", e)))?;
-        self.transport
-            .send_frame(&bytes)
-            .map_err(StreamError::Transport)?;
-        *self.last_frame.lock() = Some(envelope);
-        Ok(())
+        let parity = self.parity.as_ref().and_then(|encoder| {
+            encoder
+                .lock()
+                .push(session_id, self.stream_id, self.epoch, sequence, bytes)
+        });
+        let parity = match parity {
+            Some(mut parity) => {
+                let bytes = auth::encode_signed(keys, &mut parity)?;
+                if bytes.len() > self.max_datagram_size {
+                    debug!(
+                        target: "alpine::stream",
+                        first_sequence = parity.first_sequence,
+                        "parity exceeds datagram size; group left unprotected"
+                    );
+                    None
+                } else {
+                    Some(bytes)
+                }
+            }
+            None => None,
+        };
+        Ok(Outgoing { fragments, parity })
+    }
+
+    /// Sends a datagram on every path, succeeding if any path accepted it.
+    fn transmit(&self, bytes: &[u8]) -> Result<(), StreamError> {
+        let primary = self.transport.send_frame(bytes);
+        if self.redundant_paths.0.is_empty() {
+            return primary.map_err(StreamError::Transport);
+        }
+        let mut delivery = PathDelivery::default();
+        delivery.record(0, primary);
+        for (index, transport) in self.redundant_paths.0.iter().enumerate() {
+            delivery.record(index + 1, transport.send_frame(bytes));
+        }
+        delivery.finish()
     }
 
     /// Updates recovery state based on observed network conditions.
     pub fn observe_network_conditions(&self, conditions: &NetworkConditions) {
+        self.observe_metrics(&conditions.metrics());
+    }
+
+    /// Consumes an authenticated receiver report sent back by the node.
+    ///
+    /// # Guarantees
+    /// * Rejects reports for other sessions with [`StreamError::SessionMismatch`],
+    ///   and reports for other streams of this session with
+    ///   [`StreamError::StreamMismatch`].
+    /// * Rejects forged reports, reports whose `report_sequence` is not newer
+    ///   than the last accepted one from the same receiver `epoch`, and
+    ///   reports from a receiver epoch older than the newest one seen, with
+    ///   [`StreamError::SessionMacMismatch`]. A recreated receiver, whose
+    ///   epoch is newer, is followed from its first report.
+    /// * Measures round-trip time from the echoed frame timestamp (minus the
+    ///   node's hold time) against the local clock, RTCP-style, and smooths it
+    ///   into [`NetworkMetrics::rtt_ms`].
+    /// * Feeds accepted metrics into the `RecoveryMonitor` and adaptation state
+    ///   exactly like [`AlnpStream::observe_network_conditions`].
+    pub fn handle_receiver_report(&self, bytes: &[u8]) -> Result<ReceiverReport, StreamError> {
+        let established = self
+            .session
+            .ensure_streaming_ready()
+            .map_err(|_| StreamError::NotAuthenticated)?;
+        let mut report: ReceiverReport =
+            serde_cbor::from_slice(bytes).map_err(|e| StreamError::Decode(e.to_string()))?;
+        if report.message_type != MessageType::AlpineReceiverReport {
+            return Err(StreamError::Decode(format!(
+                "expected alpine_receiver_report, got {:?}",
+                report.message_type
+            )));
+        }
+        if report.session_id != established.session_id {
+            return Err(StreamError::SessionMismatch);
+        }
+        if report.stream_id != self.stream_id {
+            return Err(StreamError::StreamMismatch(report.stream_id));
+        }
+        let keys = self
+            .receiver_epochs
+            .lock()
+            .keys(&self.session, self.stream_id, report.epoch)?;
+        let mut last_report = self.last_report.lock();
+        let newest = last_report
+            .as_ref()
+            .filter(|last| last.epoch == report.epoch)
+            .map(|last| last.report_sequence)
+            .unwrap_or(0);
+        if report.report_sequence <= newest {
+            return Err(StreamError::SessionMacMismatch);
+        }
+        auth::open_signed(&keys, &mut report)?;
+        self.receiver_epochs.lock().follow(report.epoch)?;
+        *last_report = Some(report.clone());
+        drop(last_report);
+
+        if report.echo_timestamp_us != 0 {
+            let rtt_us = now_us()
+                .saturating_sub(report.echo_timestamp_us)
+                .saturating_sub(report.echo_delay_us);
+            let mut srtt = self.rtt_us.lock();
+            *srtt = Some(smooth_rtt(*srtt, rtt_us));
+        }
+        let metrics = self.report_metrics(&report);
+        self.observe_metrics(&metrics);
+        Ok(report)
+    }
+
+    /// Answers an authenticated keyframe request sent by the node.
+    ///
+    /// # Guarantees
+    /// * Applies the same session, authentication, and replay checks as
+    ///   [`AlnpStream::handle_receiver_report`].
+    /// * Forces the next frame to be a keyframe, at most once per
+    ///   [`KEYFRAME_REQUEST_INTERVAL`]; requests inside that window return
+    ///   [`KeyframeRequestOutcome::RateLimited`].
+    /// * Logs every scheduled keyframe as a recovery event.
+    pub fn handle_keyframe_request(
+        &self,
+        bytes: &[u8],
+    ) -> Result<KeyframeRequestOutcome, StreamError> {
+        let established = self
+            .session
+            .ensure_streaming_ready()
+            .map_err(|_| StreamError::NotAuthenticated)?;
+        let mut request: KeyframeRequest =
+            serde_cbor::from_slice(bytes).map_err(|e| StreamError::Decode(e.to_string()))?;
+        if request.message_type != MessageType::AlpineKeyframeRequest {
+            return Err(StreamError::Decode(format!(
+                "expected alpine_keyframe_request, got {:?}",
+                request.message_type
+            )));
+        }
+        if request.session_id != established.session_id {
+            return Err(StreamError::SessionMismatch);
+        }
+        if request.stream_id != self.stream_id {
+            return Err(StreamError::StreamMismatch(request.stream_id));
+        }
+        let keys =
+            self.receiver_epochs
+                .lock()
+                .keys(&self.session, self.stream_id, request.epoch)?;
+        let mut requests = self.keyframe_requests.lock();
+        if requests.epoch == request.epoch
+            && request.request_sequence <= requests.last_request_sequence
+        {
+            return Err(StreamError::SessionMacMismatch);
+        }
+        auth::open_signed(&keys, &mut request)?;
+        self.receiver_epochs.lock().follow(request.epoch)?;
+        requests.epoch = request.epoch;
+        requests.last_request_sequence = request.request_sequence;
+
+        let now = now_us();
+        let interval_us = KEYFRAME_REQUEST_INTERVAL.as_micros() as u64;
+        if requests
+            .last_scheduled_us
+            .is_some_and(|last| now.saturating_sub(last) < interval_us)
+        {
+            return Ok(KeyframeRequestOutcome::RateLimited);
+        }
+        requests.pending = true;
+        requests.last_scheduled_us = Some(now);
+        drop(requests);
+        let event = RecoveryEvent::KeyframeRequested {
+            failed_sequence: request.failed_sequence,
+        };
+        log_recovery_event(event);
+        if self.events.has_subscribers() {
+            let adaptation = self.adaptation.lock().clone();
+            self.publish_event(
+                StreamEventKind::Recovery(event),
+                self.network_metrics(),
+                adaptation,
+            );
+        }
+        Ok(KeyframeRequestOutcome::Scheduled)
+    }
+
+    /// Returns the most recent receiver report accepted from the node.
+    pub fn last_receiver_report(&self) -> Option<ReceiverReport> {
+        self.last_report.lock().clone()
+    }
+
+    /// Returns the node-observed metrics from the latest receiver report, with
+    /// the round-trip time measured by this stream.
+    pub fn network_metrics(&self) -> Option<NetworkMetrics> {
+        self.last_report
+            .lock()
+            .as_ref()
+            .map(|report| self.report_metrics(report))
+    }
+
+    fn report_metrics(&self, report: &ReceiverReport) -> NetworkMetrics {
+        NetworkMetrics {
+            loss_ratio: report.loss_ratio,
+            late_frame_rate: report.late_frame_rate,
+            jitter_ms: report.jitter_ms,
+            max_loss_gap: report.max_loss_gap,
+            rtt_ms: self.rtt_us.lock().map(|rtt| rtt / 1000.0),
+        }
+    }
+
+    fn observe_metrics(&self, metrics: &NetworkMetrics) {
         let mut monitor = self.recovery.lock();
-        if let Some(event) = monitor.feed(conditions) {
-            match event {
-                RecoveryEvent::RecoveryStarted(reason) => warn!(
-                    target: "alpine::recovery",
-                    reason = reason.as_str(),
-                    "recovery started due to {}",
-                    reason.as_str()
-                ),
-                RecoveryEvent::RecoveryComplete(reason) => info!(
-                    target: "alpine::recovery",
-                    reason = reason.as_str(),
-                    "recovery complete for {}",
-                    reason.as_str()
-                ),
-            }
+        let recovery_event = monitor.feed_metrics(metrics);
+        if let Some(event) = recovery_event {
+            log_recovery_event(event);
         }
         let reason = monitor.active_reason();
         {
@@ -143,90 +908,148 @@ impl<T: FrameTransport> AlnpStream<T> {
         drop(monitor);
 
         let mut adaptation = self.adaptation.lock();
-        let decision = decide_next_state(&adaptation, conditions, reason, self.profile.intent());
+        let decision = self
+            .policy
+            .decide(&adaptation, metrics, reason, &self.profile);
+        if let Some(event) = decision.event {
+            info!(
+                target: "alpine::adaptation",
+                event = event.as_str(),
+                "adaptation decision: {}",
+                event.as_str()
+            );
+        }
         *adaptation = decision.state;
+
+        if self.events.has_subscribers() {
+            let kinds = recovery_event
+                .map(StreamEventKind::Recovery)
+                .into_iter()
+                .chain(decision.event.map(StreamEventKind::Adaptation));
+            for kind in kinds {
+                self.publish_event(kind, Some(*metrics), adaptation.clone());
+            }
+        }
     }
 
-    fn annotate_metadata(
+    fn publish_event(
+        &self,
+        kind: StreamEventKind,
+        metrics: Option<NetworkMetrics>,
+        adaptation: AdaptationState,
+    ) {
+        self.events.publish(StreamEvent {
+            timestamp_us: now_us(),
+            kind,
+            metrics,
+            adaptation,
+            recovery: *self.recovery_reason.lock(),
+        });
+    }
+
+    fn adaptation_header(
         &self,
-        metadata: Option<HashMap<String, Value>>,
         force_keyframe: bool,
-        adaptation_snapshot: &AdaptationState,
-    ) -> Option<HashMap<String, Value>> {
-        let mut map = metadata.unwrap_or_default();
-        if let Some(reason) = *self.recovery_reason.lock() {
-            map.insert(
-                "alpine_recovery".to_string(),
-                json!({
-                    "phase": "recovery",
-                    "reason": reason.as_str(),
-                }),
-            );
+        adaptation: &AdaptationState,
+    ) -> AdaptationHeader {
+        AdaptationHeader {
+            keyframe_interval: adaptation.keyframe_interval,
+            delta_depth: adaptation.delta_depth,
+            deadline_offset_ms: adaptation.deadline_offset_ms,
+            frames_since_keyframe: adaptation.frames_since_keyframe,
+            degraded_safe: adaptation.degraded_safe,
+            force_keyframe,
+            event: adaptation.last_event,
+            recovery: *self.recovery_reason.lock(),
         }
+    }
 
-        let event_name = adaptation_snapshot
-            .last_event
-            .map(|event| event.as_str())
-            .unwrap_or("steady");
-        map.insert(
-            "alpine_adaptation".to_string(),
-            json!({
-                "keyframe_interval": adaptation_snapshot.keyframe_interval,
-                "delta_depth": adaptation_snapshot.delta_depth,
-                "deadline_offset_ms": adaptation_snapshot.deadline_offset_ms,
-                "degraded_safe": adaptation_snapshot.degraded_safe,
-                "frames_since_keyframe": adaptation_snapshot.frames_since_keyframe,
-                "force_keyframe": force_keyframe,
-                "event": event_name,
-            }),
-        );
-        Some(map)
+    /// Forces the next frame to be a keyframe, bypassing the request rate limit.
+    fn schedule_keyframe(&self) {
+        self.keyframe_requests.lock().pending = true;
     }
 
-    fn apply_jitter(&self, channels: &[u16]) -> Vec<u16> {
-        match self.jitter_strategy_from_profile() {
-            JitterStrategy::HoldLast => {
-                if channels.is_empty() {
-                    if let Some(last) = self.last_frame.lock().as_ref() {
-                        return last.channels.clone();
-                    }
-                }
-                channels.to_vec()
-            }
-            JitterStrategy::Drop => {
-                if channels.is_empty() {
-                    Vec::new()
-                } else {
-                    channels.to_vec()
-                }
-            }
-            JitterStrategy::Lerp => {
-                if let Some(last) = self.last_frame.lock().as_ref() {
-                    let mut blended = Vec::with_capacity(channels.len());
-                    for (idx, value) in channels.iter().enumerate() {
-                        let prev = last.channels.get(idx).cloned().unwrap_or(0);
-                        blended.push(((prev as u32 + *value as u32) / 2) as u16);
-                    }
-                    blended
-                } else {
-                    channels.to_vec()
-                }
-            }
+    /// Schedules a keyframe when a frame the encoder already recorded as a
+    /// delta base failed to go out.
+    pub(crate) fn resync_on_error<R>(
+        &self,
+        result: Result<R, StreamError>,
+    ) -> Result<R, StreamError> {
+        if result.is_err() {
+            self.schedule_keyframe();
         }
+        result
     }
 
-    fn now_us() -> u64 {
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_micros() as u64
+    fn next_sequence(&self) -> u64 {
+        let mut sequence = self.sequence.lock();
+        *sequence = sequence.wrapping_add(1);
+        *sequence
     }
+}
 
-    fn jitter_strategy_from_profile(&self) -> JitterStrategy {
-        if self.profile.latency_weight() >= self.profile.resilience_weight() {
-            JitterStrategy::HoldLast
-        } else {
-            JitterStrategy::Lerp
-        }
+fn parity_encoder(
+    profile: &CompiledStreamProfile,
+    max_datagram: usize,
+) -> Option<parking_lot::Mutex<ParityEncoder>> {
+    let group_size = profile.fec_group_size();
+    (group_size > 0).then(|| parking_lot::Mutex::new(ParityEncoder::new(group_size, max_datagram)))
+}
+
+fn log_recovery_event(event: RecoveryEvent) {
+    match event {
+        RecoveryEvent::RecoveryStarted(reason) => warn!(
+            target: "alpine::recovery",
+            reason = reason.as_str(),
+            "recovery started due to {}",
+            reason.as_str()
+        ),
+        RecoveryEvent::RecoveryComplete(reason) => info!(
+            target: "alpine::recovery",
+            reason = reason.as_str(),
+            "recovery complete for {}",
+            reason.as_str()
+        ),
+        RecoveryEvent::KeyframeRequested { failed_sequence } => warn!(
+            target: "alpine::recovery",
+            failed_sequence,
+            "keyframe requested by receiver after sequence {}",
+            failed_sequence
+        ),
     }
 }
+
+/// Checks a frame against the capabilities its receiver advertised.
+///
+/// Fails with [`StreamError::UnsupportedChannelFormat`] for formats outside
+/// `channel_formats`, [`StreamError::TooManyChannels`] above `max_channels`,
+/// and [`StreamError::GroupingUnsupported`] for group assignments without
+/// `grouping_supported`.
+pub(crate) fn check_capabilities(
+    capabilities: &CapabilitySet,
+    channel_format: &ChannelFormat,
+    channel_count: usize,
+    grouped: bool,
+) -> Result<(), StreamError> {
+    if !capabilities.channel_formats.contains(channel_format) {
+        return Err(StreamError::UnsupportedChannelFormat(*channel_format));
+    }
+    if channel_count > capabilities.max_channels as usize {
+        return Err(StreamError::TooManyChannels(
+            channel_count,
+            capabilities.max_channels,
+        ));
+    }
+    if grouped && !capabilities.grouping_supported {
+        return Err(StreamError::GroupingUnsupported);
+    }
+    Ok(())
+}
+
+/// Wall-clock timestamp in microseconds shared by the sender and receiver paths.
+pub(crate) fn now_us() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_micros() as u64
+}
@@ -3,24 +3,18 @@
 //! This module defines the pure decision logic that takes deterministic network
 //! metrics plus recovery signals and produces the next conservative adaptation
 //! state. There are no side effects, no logging, and no streaming plumbing here.
-use crate::profile::StreamIntent;
-use crate::stream::network::NetworkConditions;
-use crate::stream::recovery::RecoveryReason;
-
-const DWELL_FRAMES: u32 = 8;
-
-const LOSS_THRESHOLD_KEYFRAME: f64 = 0.30;
-const LOSS_THRESHOLD_DISABLE: f64 = 0.50;
-const LATE_THRESHOLD_DELTA: f64 = 0.20;
-const JITTER_THRESHOLD_DELTA: f64 = 5.0;
-const JITTER_TIGHTEN: f64 = 8.0;
-const JITTER_RELAX: f64 = 3.0;
-const BURST_THRESHOLD_KEYFRAME: u64 = 5;
-const BURST_THRESHOLD_DISABLE: u64 = 8;
-const BURST_THRESHOLD_DEGRADE: u64 = 10;
-const LOSS_THRESHOLD_DEGRADE: f64 = 0.60;
-const DEADLINE_STEP_MS: i16 = 10;
+//!
+//! The logic is exposed through the [`AdaptationPolicy`] trait so deployments
+//! can swap in their own decisions; [`DefaultAdaptationPolicy`] is the stock
+//! behavior, driven by the thresholds and bounds carried in the
+//! `CompiledStreamProfile` (and therefore covered by its `config_id`).
+use std::fmt::Debug;
+
+use crate::messages::{AdaptationEvent, DegradedReason, RecoveryReason};
+use crate::profile::CompiledStreamProfile;
+use crate::stream::network::NetworkMetrics;
 
+/// Last known-good parameters restored when degraded-safe exits.
 #[derive(Debug, Clone)]
 pub struct AdaptationSnapshot {
     keyframe_interval: u8,
@@ -40,50 +34,9 @@ impl AdaptationSnapshot {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct ProfileBounds {
-    pub min_keyframe_interval: u8,
-    pub base_keyframe_interval: u8,
-    pub min_delta_depth: u8,
-    pub base_delta_depth: u8,
-    pub max_deadline_offset: i16,
-    pub min_deadline_offset: i16,
-}
-
-impl ProfileBounds {
-    fn for_intent(intent: StreamIntent) -> Self {
-        match intent {
-            StreamIntent::Auto => Self {
-                min_keyframe_interval: 6,
-                base_keyframe_interval: 10,
-                min_delta_depth: 1,
-                base_delta_depth: 3,
-                max_deadline_offset: 15,
-                min_deadline_offset: -15,
-            },
-            StreamIntent::Realtime => Self {
-                min_keyframe_interval: 8,
-                base_keyframe_interval: 12,
-                min_delta_depth: 1,
-                base_delta_depth: 2,
-                max_deadline_offset: 0,
-                min_deadline_offset: -20,
-            },
-            StreamIntent::Install => Self {
-                min_keyframe_interval: 4,
-                base_keyframe_interval: 8,
-                min_delta_depth: 0,
-                base_delta_depth: 3,
-                max_deadline_offset: 25,
-                min_deadline_offset: -10,
-            },
-        }
-    }
-}
-
+/// Current adaptive streaming parameters for one stream.
 #[derive(Debug, Clone)]
 pub struct AdaptationState {
-    pub profile_intent: StreamIntent,
     pub keyframe_interval: u8,
     pub delta_depth: u8,
     pub deadline_offset_ms: i16,
@@ -95,14 +48,14 @@ pub struct AdaptationState {
 }
 
 impl AdaptationState {
-    pub fn baseline(intent: StreamIntent) -> Self {
-        let bounds = ProfileBounds::for_intent(intent);
+    /// Starting state for a profile: base cadence and depth, no deadline offset.
+    pub fn baseline(profile: &CompiledStreamProfile) -> Self {
+        let bounds = profile.bounds();
         Self {
-            profile_intent: intent,
             keyframe_interval: bounds.base_keyframe_interval,
             delta_depth: bounds.base_delta_depth,
             deadline_offset_ms: 0,
-            frames_in_state: DWELL_FRAMES,
+            frames_in_state: profile.thresholds().dwell_frames,
             frames_since_keyframe: 0,
             degraded_safe: false,
             last_safe_snapshot: None,
@@ -118,7 +71,7 @@ impl AdaptationState {
         self.frames_in_state = 0;
     }
 
-    fn reset_keyframe_counter(&mut self) {
+    pub(crate) fn reset_keyframe_counter(&mut self) {
         self.frames_since_keyframe = 0;
     }
 
@@ -131,50 +84,9 @@ impl AdaptationState {
             false
         }
     }
-
-    fn would_violate_bounds(
-        &self,
-        bounds: &ProfileBounds,
-        next_interval: u8,
-        next_delta: u8,
-        next_deadline: i16,
-    ) -> bool {
-        next_interval < bounds.min_keyframe_interval
-            || next_delta < bounds.min_delta_depth
-            || next_deadline < bounds.min_deadline_offset
-            || next_deadline > bounds.max_deadline_offset
-    }
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum DegradedReason {
-    ExceededProfileBounds,
-    UnrecoverableBurst,
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum AdaptationEvent {
-    KeyframeCadenceIncreased,
-    DeltaDepthReduced,
-    DeltaDisabled,
-    DeadlineAdjusted,
-    EnteredDegradedSafe(DegradedReason),
-    ExitedDegradedSafe,
-}
-
-impl AdaptationEvent {
-    pub fn as_str(&self) -> &'static str {
-        match self {
-            AdaptationEvent::KeyframeCadenceIncreased => "keyframe_cadence_increased",
-            AdaptationEvent::DeltaDepthReduced => "delta_depth_reduced",
-            AdaptationEvent::DeltaDisabled => "delta_disabled",
-            AdaptationEvent::DeadlineAdjusted => "deadline_adjusted",
-            AdaptationEvent::EnteredDegradedSafe(_) => "entered_degraded_safe",
-            AdaptationEvent::ExitedDegradedSafe => "exited_degraded_safe",
-        }
-    }
 }
 
+/// Next state plus the event that produced it, if any.
 #[derive(Debug)]
 pub struct AdaptationDecision {
     pub state: AdaptationState,
@@ -188,21 +100,53 @@ impl AdaptationDecision {
     }
 }
 
+/// Pluggable decision logic for stream adaptation.
+///
+/// Implementations must be deterministic: the same state, metrics, recovery
+/// signal, and profile always produce the same decision.
+pub trait AdaptationPolicy: Debug + Send + Sync {
+    /// Produces the next adaptation state from the latest observations.
+    fn decide(
+        &self,
+        current: &AdaptationState,
+        metrics: &NetworkMetrics,
+        recovery: Option<RecoveryReason>,
+        profile: &CompiledStreamProfile,
+    ) -> AdaptationDecision;
+}
+
+/// Stock policy: conservative steps bounded by the profile.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultAdaptationPolicy;
+
+impl AdaptationPolicy for DefaultAdaptationPolicy {
+    fn decide(
+        &self,
+        current: &AdaptationState,
+        metrics: &NetworkMetrics,
+        recovery: Option<RecoveryReason>,
+        profile: &CompiledStreamProfile,
+    ) -> AdaptationDecision {
+        decide_next_state(current, metrics, recovery, profile)
+    }
+}
+
+/// Stock decision logic behind [`DefaultAdaptationPolicy`].
 pub fn decide_next_state(
     current: &AdaptationState,
-    network: &NetworkConditions,
+    metrics: &NetworkMetrics,
     recovery: Option<RecoveryReason>,
-    intent: StreamIntent,
+    profile: &CompiledStreamProfile,
 ) -> AdaptationDecision {
     let mut next = current.clone();
     next.record_frame();
-    let bounds = ProfileBounds::for_intent(intent);
-    let metrics = network.metrics();
-    let gap = network.max_loss_gap();
+    let bounds = profile.bounds();
+    let thresholds = profile.thresholds();
+    let gap = metrics.max_loss_gap;
 
     if current.degraded_safe {
-        if metrics.loss_ratio <= LOSS_THRESHOLD_DISABLE
-            && gap <= BURST_THRESHOLD_DISABLE
+        if metrics.loss_ratio <= thresholds.loss_disable
+            && gap <= thresholds.burst_disable
             && recovery.is_none()
         {
             if let Some(snapshot) = current.last_safe_snapshot.clone() {
@@ -218,7 +162,7 @@ pub fn decide_next_state(
         return AdaptationDecision::with_event(next, None);
     }
 
-    if metrics.loss_ratio >= LOSS_THRESHOLD_DEGRADE && gap >= BURST_THRESHOLD_DEGRADE {
+    if metrics.loss_ratio >= thresholds.loss_degrade && gap >= thresholds.burst_degrade {
         next.degraded_safe = true;
         next.last_safe_snapshot = Some(AdaptationSnapshot::from_state(current));
         next.reset_frames();
@@ -231,13 +175,13 @@ pub fn decide_next_state(
         );
     }
 
-    if next.frames_in_state < DWELL_FRAMES {
+    if next.frames_in_state < thresholds.dwell_frames {
         return AdaptationDecision::with_event(next, None);
     }
 
     let jitter_ms = metrics.jitter_ms.unwrap_or(0.0);
 
-    if gap >= BURST_THRESHOLD_DISABLE && recovery == Some(RecoveryReason::BurstLoss) {
+    if gap >= thresholds.burst_disable && recovery == Some(RecoveryReason::BurstLoss) {
         let next_delta = 0;
         if current.delta_depth != next_delta {
             next.delta_depth = next_delta;
@@ -247,7 +191,7 @@ pub fn decide_next_state(
         }
     }
 
-    if metrics.loss_ratio >= LOSS_THRESHOLD_KEYFRAME || gap >= BURST_THRESHOLD_KEYFRAME {
+    if metrics.loss_ratio >= thresholds.loss_keyframe || gap >= thresholds.burst_keyframe {
         let next_interval = current.keyframe_interval.saturating_sub(1);
         if next_interval < bounds.min_keyframe_interval {
             next.degraded_safe = true;
@@ -270,8 +214,8 @@ pub fn decide_next_state(
         );
     }
 
-    if metrics.late_frame_rate >= LATE_THRESHOLD_DELTA
-        && jitter_ms > JITTER_THRESHOLD_DELTA
+    if metrics.late_frame_rate >= thresholds.late_delta
+        && jitter_ms > thresholds.jitter_delta_ms
         && current.delta_depth > bounds.min_delta_depth
     {
         let next_delta = current.delta_depth.saturating_sub(1);
@@ -293,8 +237,8 @@ pub fn decide_next_state(
         return AdaptationDecision::with_event(next, Some(AdaptationEvent::DeltaDepthReduced));
     }
 
-    if jitter_ms > JITTER_TIGHTEN {
-        let next_deadline = current.deadline_offset_ms - DEADLINE_STEP_MS;
+    if jitter_ms > thresholds.jitter_tighten_ms {
+        let next_deadline = current.deadline_offset_ms - thresholds.deadline_step_ms;
         if next_deadline < bounds.min_deadline_offset {
             next.degraded_safe = true;
             next.last_safe_snapshot = Some(AdaptationSnapshot::from_state(current));
@@ -312,21 +256,14 @@ pub fn decide_next_state(
         return AdaptationDecision::with_event(next, Some(AdaptationEvent::DeadlineAdjusted));
     }
 
-    if jitter_ms < JITTER_RELAX {
-        let next_deadline = current.deadline_offset_ms + DEADLINE_STEP_MS;
-        if next_deadline > bounds.max_deadline_offset {
-            next.degraded_safe = true;
-            next.last_safe_snapshot = Some(AdaptationSnapshot::from_state(current));
-            next.reset_frames();
-            next.reset_keyframe_counter();
-            return AdaptationDecision::with_event(
-                next,
-                Some(AdaptationEvent::EnteredDegradedSafe(
-                    DegradedReason::ExceededProfileBounds,
-                )),
-            );
-        }
-        next.deadline_offset_ms = next_deadline;
+    // Relaxing is never a reason to degrade: it stops at the profile bound.
+    if jitter_ms < thresholds.jitter_relax_ms
+        && current.deadline_offset_ms < bounds.max_deadline_offset
+    {
+        next.deadline_offset_ms = current
+            .deadline_offset_ms
+            .saturating_add(thresholds.deadline_step_ms)
+            .min(bounds.max_deadline_offset);
         next.reset_frames();
         return AdaptationDecision::with_event(next, Some(AdaptationEvent::DeadlineAdjusted));
     }
@@ -337,32 +274,32 @@ pub fn decide_next_state(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::profile::StreamProfile;
-    use crate::stream::recovery::RecoveryReason;
+    use crate::profile::{AdaptationThresholds, StreamProfile};
+    use crate::stream::network::NetworkConditions;
 
-    fn high_loss_conditions() -> NetworkConditions {
+    fn high_loss_metrics() -> NetworkMetrics {
         let mut cond = NetworkConditions::new();
-        cond.record_frame(1, 0, 0);
-        cond.record_frame(2, 1_000, 0);
-        cond.record_frame(10, 2_000, 0);
-        cond
+        cond.record_frame(1, 0, 0, 0);
+        cond.record_frame(2, 1_000, 1_000, 0);
+        cond.record_frame(10, 2_000, 2_000, 0);
+        cond.metrics()
     }
 
-    fn low_loss_conditions() -> NetworkConditions {
+    fn low_loss_metrics() -> NetworkMetrics {
         let mut cond = NetworkConditions::new();
-        cond.record_frame(1, 0, 0);
-        cond.record_frame(2, 1_000, 0);
-        cond.record_frame(3, 2_000, 0);
-        cond.record_frame(4, 3_000, 0);
-        cond
+        cond.record_frame(1, 0, 0, 0);
+        cond.record_frame(2, 1_000, 1_000, 0);
+        cond.record_frame(3, 2_000, 2_000, 0);
+        cond.record_frame(4, 3_000, 3_000, 0);
+        cond.metrics()
     }
 
     #[test]
     fn keyframe_cadence_increases_on_loss() {
-        let profile = StreamProfile::auto();
-        let state = AdaptationState::baseline(profile.intent());
-        let network = high_loss_conditions();
-        let decision = decide_next_state(&state, &network, None, profile.intent());
+        let profile = StreamProfile::auto().compile().unwrap();
+        let state = AdaptationState::baseline(&profile);
+        let network = high_loss_metrics();
+        let decision = decide_next_state(&state, &network, None, &profile);
         assert_eq!(
             decision.event,
             Some(AdaptationEvent::KeyframeCadenceIncreased)
@@ -372,12 +309,12 @@ mod tests {
 
     #[test]
     fn degraded_safe_when_bounds_block_keyframe() {
-        let profile = StreamProfile::auto();
-        let mut state = AdaptationState::baseline(profile.intent());
-        state.keyframe_interval = ProfileBounds::for_intent(profile.intent()).min_keyframe_interval;
-        state.frames_in_state = DWELL_FRAMES;
+        let profile = StreamProfile::auto().compile().unwrap();
+        let mut state = AdaptationState::baseline(&profile);
+        state.keyframe_interval = profile.bounds().min_keyframe_interval;
+        state.frames_in_state = profile.thresholds().dwell_frames;
 
-        let decision = decide_next_state(&state, &high_loss_conditions(), None, profile.intent());
+        let decision = decide_next_state(&state, &high_loss_metrics(), None, &profile);
         assert_eq!(
             decision.event,
             Some(AdaptationEvent::EnteredDegradedSafe(
@@ -387,47 +324,83 @@ mod tests {
         assert!(decision.state.degraded_safe);
     }
 
+    #[test]
+    fn relaxing_stops_at_the_deadline_bound() {
+        let profile = StreamProfile::auto().compile().unwrap();
+        let max = profile.bounds().max_deadline_offset;
+        let mut state = AdaptationState::baseline(&profile);
+        state.deadline_offset_ms = max - 1;
+        state.frames_in_state = profile.thresholds().dwell_frames;
+
+        let decision = decide_next_state(&state, &low_loss_metrics(), None, &profile);
+        assert_eq!(decision.event, Some(AdaptationEvent::DeadlineAdjusted));
+        assert_eq!(decision.state.deadline_offset_ms, max);
+
+        let mut state = decision.state;
+        state.frames_in_state = profile.thresholds().dwell_frames;
+        let decision = decide_next_state(&state, &low_loss_metrics(), None, &profile);
+        assert_eq!(decision.event, None);
+        assert_eq!(decision.state.deadline_offset_ms, max);
+        assert!(!decision.state.degraded_safe);
+    }
+
     #[test]
     fn degraded_safe_exits_when_metrics_clear() {
-        let profile = StreamProfile::auto();
-        let mut state = AdaptationState::baseline(profile.intent());
+        let profile = StreamProfile::auto().compile().unwrap();
+        let mut state = AdaptationState::baseline(&profile);
         state.degraded_safe = true;
         state.last_safe_snapshot = Some(AdaptationSnapshot::from_state(&state));
-        state.frames_in_state = DWELL_FRAMES;
+        state.frames_in_state = profile.thresholds().dwell_frames;
 
-        let decision = decide_next_state(&state, &low_loss_conditions(), None, profile.intent());
+        let decision = decide_next_state(&state, &low_loss_metrics(), None, &profile);
         assert_eq!(decision.event, Some(AdaptationEvent::ExitedDegradedSafe));
         assert!(!decision.state.degraded_safe);
     }
 
     #[test]
     fn delta_disable_requires_burst_loss_recovery() {
-        let profile = StreamProfile::auto();
-        let state = AdaptationState::baseline(profile.intent());
+        let profile = StreamProfile::auto().compile().unwrap();
+        let state = AdaptationState::baseline(&profile);
         let network = {
             let mut cond = NetworkConditions::new();
-            cond.record_frame(1, 0, 0);
-            cond.record_frame(2, 1_000, 0);
-            cond.record_frame(12, 2_000, 0);
-            cond
+            cond.record_frame(1, 0, 0, 0);
+            cond.record_frame(2, 1_000, 1_000, 0);
+            cond.record_frame(12, 2_000, 2_000, 0);
+            cond.metrics()
         };
-        let decision = decide_next_state(
-            &state,
-            &network,
-            Some(RecoveryReason::BurstLoss),
-            profile.intent(),
-        );
+        let decision =
+            decide_next_state(&state, &network, Some(RecoveryReason::BurstLoss), &profile);
         assert_eq!(decision.event, Some(AdaptationEvent::DeltaDisabled));
         assert_eq!(decision.state.delta_depth, 0);
     }
 
     #[test]
     fn no_oscillation_before_dwell() {
-        let profile = StreamProfile::auto();
-        let mut state = AdaptationState::baseline(profile.intent());
+        let profile = StreamProfile::auto().compile().unwrap();
+        let mut state = AdaptationState::baseline(&profile);
         state.frames_in_state = 1;
-        let decision = decide_next_state(&state, &high_loss_conditions(), None, profile.intent());
+        let decision = decide_next_state(&state, &high_loss_metrics(), None, &profile);
         assert!(decision.event.is_none());
         assert_eq!(decision.state.frames_in_state, 2);
     }
+
+    #[test]
+    fn profile_thresholds_drive_decisions() {
+        let thresholds = AdaptationThresholds {
+            loss_keyframe: 0.95,
+            burst_keyframe: 50,
+            ..AdaptationThresholds::default()
+        };
+        let profile = StreamProfile::auto()
+            .with_thresholds(thresholds)
+            .compile()
+            .unwrap();
+        let state = AdaptationState::baseline(&profile);
+        let decision = DefaultAdaptationPolicy.decide(&state, &high_loss_metrics(), None, &profile);
+        assert_ne!(
+            decision.event,
+            Some(AdaptationEvent::KeyframeCadenceIncreased)
+        );
+        assert_eq!(decision.state.keyframe_interval, state.keyframe_interval);
+    }
 }
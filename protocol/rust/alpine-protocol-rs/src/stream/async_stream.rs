@@ -0,0 +1,260 @@
+//! Async frame transports and the tokio-native stream built on them.
+//!
+//! [`FrameTransport::send_frame`] is blocking, which forces tokio-based
+//! controllers either to use blocking sockets inside async code or to hop to
+//! `spawn_blocking` for every frame. [`AsyncAlnpStream`] awaits an
+//! [`AsyncFrameTransport`] instead. It shares the frame encoder with
+//! [`AlnpStream`], so sequencing, keyframe/delta encoding, adaptation,
+//! recovery, fragmentation, parity and redundant paths behave identically;
+//! only the final datagram sends differ.
+//!
+//! [`AsyncAdapter`] and [`BlockingAdapter`] convert transports in either
+//! direction, so one transport implementation serves both stream variants.
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use tokio::runtime::Handle;
+
+use crate::messages::{ChannelBuffer, ChannelFormat, ChannelRange, ReceiverReport};
+use crate::profile::CompiledStreamProfile;
+use crate::session::AlnpSession;
+use crate::stream::framebuf::EncodeScratch;
+use crate::stream::{
+    AdaptationPolicy, AlnpStream, ChannelGroups, FrameBuf, FrameTransport, KeyframeRequestOutcome,
+    NetworkConditions, NetworkMetrics, Outgoing, PathDelivery, RedundantPaths, StreamError,
+    StreamEvent, Update,
+};
+
+/// Async transport for sending serialized ALPINE frames.
+#[async_trait]
+pub trait AsyncFrameTransport: Send + Sync {
+    /// Sends the provided serialized frame.
+    async fn send_frame(&self, bytes: &[u8]) -> Result<(), String>;
+}
+
+/// Exposes a blocking [`FrameTransport`] as an [`AsyncFrameTransport`].
+///
+/// The send runs inline on the calling task, which suits transports that do
+/// not block for long, such as UDP sockets.
+#[derive(Debug)]
+pub struct AsyncAdapter<T> {
+    transport: T,
+}
+
+impl<T: FrameTransport> AsyncAdapter<T> {
+    /// Wraps a blocking `transport`.
+    pub fn new(transport: T) -> Self {
+        Self { transport }
+    }
+
+    /// Returns the wrapped transport.
+    pub fn into_inner(self) -> T {
+        self.transport
+    }
+}
+
+#[async_trait]
+impl<T: FrameTransport> AsyncFrameTransport for AsyncAdapter<T> {
+    async fn send_frame(&self, bytes: &[u8]) -> Result<(), String> {
+        self.transport.send_frame(bytes)
+    }
+}
+
+/// Exposes an [`AsyncFrameTransport`] as a blocking [`FrameTransport`] by
+/// running each send to completion on `runtime`.
+///
+/// Meant for threads outside the runtime, such as render threads or
+/// `spawn_blocking` tasks; sending from inside an async task panics.
+#[derive(Debug)]
+pub struct BlockingAdapter<T> {
+    transport: T,
+    runtime: Handle,
+}
+
+impl<T: AsyncFrameTransport> BlockingAdapter<T> {
+    /// Wraps an async `transport` whose sends run on `runtime`, which must not
+    /// be the runtime driving the calling thread.
+    pub fn new(transport: T, runtime: Handle) -> Self {
+        Self { transport, runtime }
+    }
+
+    /// Returns the wrapped transport.
+    pub fn into_inner(self) -> T {
+        self.transport
+    }
+}
+
+impl<T: AsyncFrameTransport> FrameTransport for BlockingAdapter<T> {
+    fn send_frame(&self, bytes: &[u8]) -> Result<(), String> {
+        self.runtime.block_on(self.transport.send_frame(bytes))
+    }
+}
+
+/// Placeholder transport for the encoder inside [`AsyncAlnpStream`], which
+/// never sends through it.
+#[derive(Debug)]
+struct Detached;
+
+impl FrameTransport for Detached {
+    fn send_frame(&self, _bytes: &[u8]) -> Result<(), String> {
+        Err("async stream sends through its own transport".into())
+    }
+}
+
+/// [`AlnpStream`] variant that sends through an [`AsyncFrameTransport`].
+#[derive(Debug)]
+pub struct AsyncAlnpStream<T: AsyncFrameTransport> {
+    stream: AlnpStream<Detached>,
+    transport: T,
+    redundant_paths: RedundantPaths<dyn AsyncFrameTransport>,
+    scratch: tokio::sync::Mutex<EncodeScratch>,
+}
+
+impl<T: AsyncFrameTransport> AsyncAlnpStream<T> {
+    /// Builds a new async streaming helper bound to a compiled profile.
+    pub fn new(session: AlnpSession, transport: T, profile: CompiledStreamProfile) -> Self {
+        Self {
+            stream: AlnpStream::new(session, Detached, profile),
+            transport,
+            redundant_paths: RedundantPaths::default(),
+            scratch: tokio::sync::Mutex::new(EncodeScratch::default()),
+        }
+    }
+
+    /// See [`AlnpStream::with_policy`].
+    pub fn with_policy(mut self, policy: impl AdaptationPolicy + 'static) -> Self {
+        self.stream = self.stream.with_policy(policy);
+        self
+    }
+
+    /// See [`AlnpStream::with_stream_id`].
+    pub fn with_stream_id(mut self, stream_id: u8) -> Self {
+        self.stream = self.stream.with_stream_id(stream_id);
+        self
+    }
+
+    /// See [`AlnpStream::with_max_datagram_size`].
+    pub fn with_max_datagram_size(mut self, size: usize) -> Self {
+        self.stream = self.stream.with_max_datagram_size(size);
+        self
+    }
+
+    /// See [`AlnpStream::with_redundant_path`].
+    pub fn with_redundant_path(mut self, transport: impl AsyncFrameTransport + 'static) -> Self {
+        self.redundant_paths.0.push(Box::new(transport));
+        self
+    }
+
+    /// See [`AlnpStream::define_groups`].
+    pub fn define_groups(&self, groups: ChannelGroups) {
+        self.stream.define_groups(groups);
+    }
+
+    /// See [`AlnpStream::subscribe_events`].
+    pub fn subscribe_events(&self) -> tokio::sync::broadcast::Receiver<StreamEvent> {
+        self.stream.subscribe_events()
+    }
+
+    /// Sends a streaming frame built from raw channel data.
+    ///
+    /// # Guarantees
+    /// * Same as [`AlnpStream::send`]; the frame's datagrams are awaited on
+    ///   the transport instead of sent with a blocking call.
+    /// * Concurrent sends on one stream are serialized.
+    pub async fn send(
+        &self,
+        channels: impl Into<ChannelBuffer>,
+        priority: u8,
+        groups: Option<HashMap<String, ChannelBuffer>>,
+        metadata: Option<HashMap<String, serde_json::Value>>,
+    ) -> Result<(), StreamError> {
+        let channels = channels.into();
+        let mut scratch = self.scratch.lock().await;
+        let outgoing = self.stream.encode_update(
+            Update::Full(&channels),
+            priority,
+            groups.as_ref(),
+            metadata.as_ref(),
+            &mut scratch,
+        )?;
+        self.transmit_all(&outgoing, &scratch.datagram).await
+    }
+
+    /// Sends a sparse update; see [`AlnpStream::send_partial`].
+    pub async fn send_partial(
+        &self,
+        channel_format: ChannelFormat,
+        ranges: Vec<ChannelRange>,
+        priority: u8,
+        groups: Option<HashMap<String, ChannelBuffer>>,
+        metadata: Option<HashMap<String, serde_json::Value>>,
+    ) -> Result<(), StreamError> {
+        let mut scratch = self.scratch.lock().await;
+        let outgoing = self.stream.encode_update(
+            Update::Partial(&channel_format, &ranges),
+            priority,
+            groups.as_ref(),
+            metadata.as_ref(),
+            &mut scratch,
+        )?;
+        self.transmit_all(&outgoing, &scratch.datagram).await
+    }
+
+    /// Sends a frame from a caller-owned buffer; see [`AlnpStream::send_with`].
+    pub async fn send_with(&self, buf: &mut FrameBuf, priority: u8) -> Result<(), StreamError> {
+        let (channels, scratch) = buf.parts();
+        let outgoing =
+            self.stream
+                .encode_update(Update::Full(channels), priority, None, None, scratch)?;
+        self.transmit_all(&outgoing, &scratch.datagram).await
+    }
+
+    /// See [`AlnpStream::observe_network_conditions`].
+    pub fn observe_network_conditions(&self, conditions: &NetworkConditions) {
+        self.stream.observe_network_conditions(conditions);
+    }
+
+    /// See [`AlnpStream::handle_receiver_report`].
+    pub fn handle_receiver_report(&self, bytes: &[u8]) -> Result<ReceiverReport, StreamError> {
+        self.stream.handle_receiver_report(bytes)
+    }
+
+    /// See [`AlnpStream::handle_keyframe_request`].
+    pub fn handle_keyframe_request(
+        &self,
+        bytes: &[u8],
+    ) -> Result<KeyframeRequestOutcome, StreamError> {
+        self.stream.handle_keyframe_request(bytes)
+    }
+
+    /// See [`AlnpStream::last_receiver_report`].
+    pub fn last_receiver_report(&self) -> Option<ReceiverReport> {
+        self.stream.last_receiver_report()
+    }
+
+    /// See [`AlnpStream::network_metrics`].
+    pub fn network_metrics(&self) -> Option<NetworkMetrics> {
+        self.stream.network_metrics()
+    }
+
+    async fn transmit_all(&self, outgoing: &Outgoing, frame: &[u8]) -> Result<(), StreamError> {
+        for datagram in outgoing.datagrams(frame) {
+            self.stream.resync_on_error(self.transmit(datagram).await)?;
+        }
+        Ok(())
+    }
+
+    /// Sends a datagram on every path, succeeding if any path accepted it.
+    async fn transmit(&self, bytes: &[u8]) -> Result<(), StreamError> {
+        let primary = self.transport.send_frame(bytes).await;
+        if self.redundant_paths.0.is_empty() {
+            return primary.map_err(StreamError::Transport);
+        }
+        let mut delivery = PathDelivery::default();
+        delivery.record(0, primary);
+        for (index, transport) in self.redundant_paths.0.iter().enumerate() {
+            delivery.record(index + 1, transport.send_frame(bytes).await);
+        }
+        delivery.finish()
+    }
+}
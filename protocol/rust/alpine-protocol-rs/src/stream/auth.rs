@@ -0,0 +1,359 @@
+//! Per-frame authentication for ALPINE streaming.
+//!
+//! Every frame carries a ChaCha20-Poly1305 tag keyed by its sender's epoch
+//! key, which is derived from the session's `stream_key` for the frame's
+//! `stream_id` and `epoch` (see [`SessionKeys::for_stream_epoch`]). The nonce
+//! is derived from a message domain, the `stream_id` (byte 1) and the frame
+//! `sequence`, so it never repeats under one key, and the tag covers a
+//! canonical CBOR record of the frame (maps are sorted) with the session ID as
+//! additional data, mirroring how control envelopes are authenticated.
+//! Fragments of oversized frames additionally place their `fragment_index` in
+//! nonce bytes 2..4, so every fragment of a frame gets its own nonce.
+//!
+//! When confidentiality is negotiated the same AEAD operation also encrypts the
+//! application payload (`channels`, `delta`, `groups`, `metadata`) into
+//! `ciphertext`, so
+//! the tag covers both the cleartext header and the encrypted payload.
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::crypto::{open_stream, seal_stream, seal_stream_tag, SessionKeys};
+use crate::messages::packed::{
+    unpack_groups, unpack_ranges, FrameRef, PackedChannels, PackedGroups, PackedRanges,
+    RawChannels, RawRange,
+};
+use crate::messages::{
+    is_default_stream, AdaptationHeader, ChannelFormat, FrameEnvelope, FrameFragment, FrameKind,
+    FrameParity, KeyframeRequest, MessageType, ReceiverReport,
+};
+use crate::stream::StreamError;
+
+/// Separates nonce spaces for the different messages sealed under one epoch key.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum NonceDomain {
+    Frame = 1,
+    Report = 2,
+    KeyframeRequest = 3,
+    Fragment = 4,
+    Parity = 5,
+}
+
+/// Builds the 96-bit AEAD nonce for a streaming message.
+pub(crate) fn stream_nonce(domain: NonceDomain, stream_id: u8, sequence: u64) -> [u8; 12] {
+    let mut nonce = [0u8; 12];
+    nonce[0] = domain as u8;
+    nonce[1] = stream_id;
+    nonce[4..].copy_from_slice(&sequence.to_be_bytes());
+    nonce
+}
+
+#[derive(Serialize)]
+struct FrameMacRecord<'a> {
+    #[serde(rename = "type")]
+    message_type: &'a MessageType,
+    session_id: &'a Uuid,
+    #[serde(skip_serializing_if = "is_default_stream")]
+    stream_id: u8,
+    epoch: u64,
+    sequence: u64,
+    timestamp_us: u64,
+    priority: u8,
+    channel_format: ChannelFormat,
+    channels: PackedChannels<'a>,
+    groups: Option<PackedGroups<'a>>,
+    metadata: Option<BTreeMap<&'a String, &'a serde_json::Value>>,
+    adaptation: Option<AdaptationHeader>,
+    kind: FrameKind,
+    base_sequence: Option<u64>,
+    delta: Option<PackedRanges<'a>>,
+}
+
+impl<'a> FrameMacRecord<'a> {
+    fn from_view(frame: &FrameRef<'a>) -> Self {
+        Self {
+            message_type: frame.message_type,
+            session_id: frame.session_id,
+            stream_id: frame.stream_id,
+            epoch: frame.epoch,
+            sequence: frame.sequence,
+            timestamp_us: frame.timestamp_us,
+            priority: frame.priority,
+            channel_format: frame.channel_format,
+            channels: frame.channels,
+            groups: frame.groups,
+            metadata: frame.metadata.map(sorted),
+            adaptation: frame.adaptation,
+            kind: frame.kind,
+            base_sequence: frame.base_sequence,
+            delta: frame.delta,
+        }
+    }
+}
+
+/// Application payload moved into `ciphertext` for encrypted frames, with
+/// channels packed per the cleartext `channel_format`.
+#[derive(Serialize)]
+struct SealedPayload<'a> {
+    channels: PackedChannels<'a>,
+    delta: Option<PackedRanges<'a>>,
+    groups: Option<PackedGroups<'a>>,
+    metadata: Option<&'a HashMap<String, serde_json::Value>>,
+}
+
+/// [`SealedPayload`] as decrypted, before unpacking.
+#[derive(Deserialize)]
+struct OpenedPayload {
+    channels: RawChannels,
+    delta: Option<Vec<RawRange>>,
+    groups: Option<HashMap<String, RawChannels>>,
+    metadata: Option<HashMap<String, serde_json::Value>>,
+}
+
+fn sorted<V>(map: &HashMap<String, V>) -> BTreeMap<&String, &V> {
+    map.iter().collect()
+}
+
+fn write_cbor<T: Serialize>(out: &mut Vec<u8>, value: &T) -> Result<(), StreamError> {
+    out.clear();
+    serde_cbor::to_writer(&mut *out, value)
+        .map_err(|e| StreamError::Transport(format!("encode: {}", e)))
+}
+
+/// Reusable buffers for [`encode_frame_into`].
+#[derive(Debug, Default)]
+pub(crate) struct FrameScratch {
+    aad: Vec<u8>,
+    sealed: Vec<u8>,
+}
+
+/// Authenticates (and optionally encrypts) a borrowed frame, writing its wire
+/// bytes into `out`.
+///
+/// Once the buffers have grown to the frame size this allocates only for
+/// `groups`/`metadata`, which the tag covers in sorted order.
+pub(crate) fn encode_frame_into(
+    keys: &SessionKeys,
+    frame: FrameRef<'_>,
+    encrypt: bool,
+    scratch: &mut FrameScratch,
+    out: &mut Vec<u8>,
+) -> Result<(), StreamError> {
+    let nonce = stream_nonce(NonceDomain::Frame, frame.stream_id, frame.sequence);
+    let seal_error = |e| StreamError::Transport(format!("seal: {}", e));
+    if !encrypt {
+        write_cbor(&mut scratch.aad, &FrameMacRecord::from_view(&frame))?;
+        let tag = seal_stream_tag(keys, &nonce, &scratch.aad, &mut []).map_err(seal_error)?;
+        return write_cbor(out, &FrameRef { mac: &tag, ..frame });
+    }
+
+    write_cbor(
+        &mut scratch.sealed,
+        &SealedPayload {
+            channels: frame.channels,
+            delta: frame.delta,
+            groups: frame.groups,
+            metadata: frame.metadata,
+        },
+    )?;
+    let header = FrameRef {
+        channels: PackedChannels::Packed(&[]),
+        delta: None,
+        groups: None,
+        metadata: None,
+        ..frame
+    };
+    write_cbor(&mut scratch.aad, &FrameMacRecord::from_view(&header))?;
+    let tag =
+        seal_stream_tag(keys, &nonce, &scratch.aad, &mut scratch.sealed).map_err(seal_error)?;
+    write_cbor(
+        out,
+        &FrameRef {
+            ciphertext: Some(&scratch.sealed),
+            mac: &tag,
+            ..header
+        },
+    )
+}
+
+fn frame_aad(frame: &FrameEnvelope) -> Result<Vec<u8>, StreamError> {
+    let mut aad = Vec::new();
+    write_cbor(
+        &mut aad,
+        &FrameMacRecord::from_view(&FrameRef::from_frame(frame)),
+    )?;
+    Ok(aad)
+}
+
+/// Verifies the tag carried in `frame.mac` and decrypts `ciphertext` in place.
+pub(crate) fn open_frame(keys: &SessionKeys, frame: &mut FrameEnvelope) -> Result<(), StreamError> {
+    let aad = frame_aad(frame).map_err(|_| StreamError::SessionMacMismatch)?;
+    let nonce = stream_nonce(NonceDomain::Frame, frame.stream_id, frame.sequence);
+    match frame.ciphertext.take() {
+        None => open_stream(keys, &nonce, &aad, &mut [], &frame.mac)
+            .map_err(|_| StreamError::SessionMacMismatch),
+        Some(mut buffer) => {
+            if !frame.channels.is_empty()
+                || frame.delta.is_some()
+                || frame.groups.is_some()
+                || frame.metadata.is_some()
+            {
+                return Err(StreamError::SessionMacMismatch);
+            }
+            open_stream(keys, &nonce, &aad, &mut buffer, &frame.mac)
+                .map_err(|_| StreamError::SessionMacMismatch)?;
+            let payload: OpenedPayload =
+                serde_cbor::from_slice(&buffer).map_err(|e| StreamError::Decode(e.to_string()))?;
+            let format = &frame.channels.format();
+            frame.channels = payload
+                .channels
+                .unpack(format)
+                .map_err(StreamError::Decode)?;
+            frame.delta = payload
+                .delta
+                .map(|ranges| unpack_ranges(format, ranges))
+                .transpose()
+                .map_err(StreamError::Decode)?;
+            frame.groups = payload
+                .groups
+                .map(|groups| unpack_groups(format, groups))
+                .transpose()
+                .map_err(StreamError::Decode)?;
+            frame.metadata = payload.metadata;
+            Ok(())
+        }
+    }
+}
+
+/// Feedback messages authenticated as a whole, with `mac` cleared for the aad.
+pub(crate) trait SignedMessage: Serialize {
+    const DOMAIN: NonceDomain;
+    fn stream_id(&self) -> u8;
+    fn sequence(&self) -> u64;
+    fn mac_mut(&mut self) -> &mut Vec<u8>;
+    fn mac(&self) -> &[u8];
+
+    fn nonce(&self) -> [u8; 12] {
+        stream_nonce(Self::DOMAIN, self.stream_id(), self.sequence())
+    }
+}
+
+impl SignedMessage for ReceiverReport {
+    const DOMAIN: NonceDomain = NonceDomain::Report;
+
+    fn stream_id(&self) -> u8 {
+        self.stream_id
+    }
+
+    fn sequence(&self) -> u64 {
+        self.report_sequence
+    }
+
+    fn mac_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.mac
+    }
+
+    fn mac(&self) -> &[u8] {
+        &self.mac
+    }
+}
+
+impl SignedMessage for KeyframeRequest {
+    const DOMAIN: NonceDomain = NonceDomain::KeyframeRequest;
+
+    fn stream_id(&self) -> u8 {
+        self.stream_id
+    }
+
+    fn sequence(&self) -> u64 {
+        self.request_sequence
+    }
+
+    fn mac_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.mac
+    }
+
+    fn mac(&self) -> &[u8] {
+        &self.mac
+    }
+}
+
+impl SignedMessage for FrameFragment {
+    const DOMAIN: NonceDomain = NonceDomain::Fragment;
+
+    fn stream_id(&self) -> u8 {
+        self.stream_id
+    }
+
+    fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    fn mac_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.mac
+    }
+
+    fn mac(&self) -> &[u8] {
+        &self.mac
+    }
+
+    fn nonce(&self) -> [u8; 12] {
+        let mut nonce = stream_nonce(Self::DOMAIN, self.stream_id, self.sequence);
+        nonce[2..4].copy_from_slice(&self.fragment_index.to_be_bytes());
+        nonce
+    }
+}
+
+impl SignedMessage for FrameParity {
+    const DOMAIN: NonceDomain = NonceDomain::Parity;
+
+    fn stream_id(&self) -> u8 {
+        self.stream_id
+    }
+
+    fn sequence(&self) -> u64 {
+        self.first_sequence
+    }
+
+    fn mac_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.mac
+    }
+
+    fn mac(&self) -> &[u8] {
+        &self.mac
+    }
+}
+
+fn signed_aad<M: SignedMessage>(message: &mut M) -> Result<Vec<u8>, StreamError> {
+    let mac = std::mem::take(message.mac_mut());
+    let aad =
+        serde_cbor::to_vec(message).map_err(|e| StreamError::Transport(format!("encode: {}", e)));
+    *message.mac_mut() = mac;
+    aad
+}
+
+/// Authenticates a feedback message and returns its wire bytes.
+pub(crate) fn encode_signed<M: SignedMessage>(
+    keys: &SessionKeys,
+    message: &mut M,
+) -> Result<Vec<u8>, StreamError> {
+    message.mac_mut().clear();
+    let aad = signed_aad(message)?;
+    let nonce = message.nonce();
+    *message.mac_mut() = seal_stream(keys, &nonce, &aad, &mut [])
+        .map_err(|e| StreamError::Transport(format!("seal: {}", e)))?;
+    serde_cbor::to_vec(message).map_err(|e| StreamError::Transport(format!("encode: {}", e)))
+}
+
+/// Verifies the tag carried in a feedback message.
+pub(crate) fn open_signed<M: SignedMessage>(
+    keys: &SessionKeys,
+    message: &mut M,
+) -> Result<(), StreamError> {
+    let aad = signed_aad(message).map_err(|_| StreamError::SessionMacMismatch)?;
+    let nonce = message.nonce();
+    open_stream(keys, &nonce, &aad, &mut [], message.mac())
+        .map_err(|_| StreamError::SessionMacMismatch)
+}
@@ -0,0 +1,428 @@
+//! Keyframe/delta frame encoding for Phase 3.3 adaptive streaming.
+//!
+//! A keyframe carries the full channel vector. A delta frame carries only the
+//! changed channel ranges relative to a `base_sequence` the receiver already
+//! reconstructed. Deltas may chain (delta-on-delta) up to the adaptive
+//! `delta_depth`; once the chain is that deep the next delta is encoded against
+//! the last keyframe again, so a single lost delta only invalidates frames that
+//! depend on it. A `delta_depth` of zero means keyframes only.
+//!
+//! Partial updates reuse the same machinery: the sender applies the caller's
+//! ranges to the last frame it sent and encodes the result like any other
+//! frame, so they inherit the base-sequence ordering and keyframe refresh.
+use std::ops::Range;
+
+use crate::messages::{same_format, ChannelBuffer, ChannelFormat, ChannelRange, FrameKind};
+use crate::stream::StreamError;
+
+/// Unchanged channels tolerated inside a single range before it is split.
+const RANGE_MERGE_GAP: usize = 4;
+
+/// Per-range overhead, in channel slots, used to decide when a delta is not worth it.
+const RANGE_OVERHEAD_SLOTS: usize = 2;
+
+#[derive(Debug, Clone)]
+struct Reference {
+    sequence: u64,
+    channels: ChannelBuffer,
+    depth: u8,
+}
+
+impl Reference {
+    fn matches(&self, channels: &ChannelBuffer) -> bool {
+        self.channels.format() == channels.format() && self.channels.len() == channels.len()
+    }
+}
+
+/// Encoded representation chosen for one outgoing frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum EncodedFrame {
+    Keyframe,
+    /// Changed channels, as index spans into the frame, are left in the
+    /// caller's span buffer.
+    Delta {
+        base_sequence: u64,
+    },
+}
+
+impl EncodedFrame {
+    pub(crate) fn kind(&self) -> FrameKind {
+        match self {
+            EncodedFrame::Keyframe => FrameKind::Keyframe,
+            EncodedFrame::Delta { .. } => FrameKind::Delta,
+        }
+    }
+}
+
+/// Sender-side state that decides between keyframes and deltas.
+#[derive(Debug, Default)]
+pub(crate) struct DeltaEncoder {
+    keyframe: Option<Reference>,
+    previous: Option<Reference>,
+}
+
+impl DeltaEncoder {
+    /// Encodes `channels` for `sequence`, recording it as the next reference.
+    ///
+    /// For deltas the changed spans are written to `spans`. References are
+    /// updated in place, so steady-state encoding does not allocate. A frame
+    /// that is not delivered after this must be followed by a keyframe.
+    pub(crate) fn encode(
+        &mut self,
+        sequence: u64,
+        channels: &ChannelBuffer,
+        force_keyframe: bool,
+        delta_depth: u8,
+        spans: &mut Vec<Range<usize>>,
+    ) -> EncodedFrame {
+        let encoded = self.choose(channels, force_keyframe, delta_depth, spans);
+        let depth = match encoded {
+            EncodedFrame::Keyframe => 0,
+            EncodedFrame::Delta { base_sequence } => {
+                self.reference(base_sequence)
+                    .map(|base| base.depth)
+                    .unwrap_or(0)
+                    + 1
+            }
+        };
+        record(&mut self.previous, sequence, channels, depth);
+        if depth == 0 {
+            record(&mut self.keyframe, sequence, channels, depth);
+        }
+        encoded
+    }
+
+    /// Returns the last encoded channel vector with `ranges` applied.
+    ///
+    /// Fails with [`StreamError::PartialBaseMissing`] before the first frame or
+    /// after a format or channel-count change,
+    /// [`StreamError::PartialRangeFormat`] for ranges in another format, and
+    /// [`StreamError::PartialRangeOutOfBounds`] for ranges past the end.
+    pub(crate) fn apply_partial(
+        &self,
+        channel_format: &ChannelFormat,
+        ranges: &[ChannelRange],
+    ) -> Result<ChannelBuffer, StreamError> {
+        let previous = self
+            .previous
+            .as_ref()
+            .filter(|previous| previous.channels.format() == *channel_format)
+            .ok_or(StreamError::PartialBaseMissing)?;
+        if let Some(range) = ranges
+            .iter()
+            .find(|range| range.values.format() != *channel_format)
+        {
+            return Err(StreamError::PartialRangeFormat(range.offset));
+        }
+        let mut channels = previous.channels.clone();
+        apply_ranges(&mut channels, ranges).map_err(StreamError::PartialRangeOutOfBounds)?;
+        Ok(channels)
+    }
+
+    fn choose(
+        &self,
+        channels: &ChannelBuffer,
+        force_keyframe: bool,
+        delta_depth: u8,
+        spans: &mut Vec<Range<usize>>,
+    ) -> EncodedFrame {
+        if force_keyframe || delta_depth == 0 {
+            return EncodedFrame::Keyframe;
+        }
+        let keyframe = match &self.keyframe {
+            Some(keyframe) if keyframe.matches(channels) => keyframe,
+            _ => return EncodedFrame::Keyframe,
+        };
+        let base = match &self.previous {
+            Some(previous) if previous.depth < delta_depth && previous.matches(channels) => {
+                previous
+            }
+            _ => keyframe,
+        };
+        same_format!(&base.channels, channels, (base, next) => diff_spans(base, next, spans), _ => {
+            return EncodedFrame::Keyframe
+        });
+        let encoded_slots: usize = spans
+            .iter()
+            .map(|span| span.len() + RANGE_OVERHEAD_SLOTS)
+            .sum();
+        if encoded_slots >= channels.len() {
+            return EncodedFrame::Keyframe;
+        }
+        EncodedFrame::Delta {
+            base_sequence: base.sequence,
+        }
+    }
+
+    fn reference(&self, sequence: u64) -> Option<&Reference> {
+        [self.previous.as_ref(), self.keyframe.as_ref()]
+            .into_iter()
+            .flatten()
+            .find(|reference| reference.sequence == sequence)
+    }
+}
+
+/// Receiver-side state that rebuilds full channel vectors from deltas.
+#[derive(Debug, Default)]
+pub(crate) struct DeltaDecoder {
+    keyframe: Option<Reference>,
+    previous: Option<Reference>,
+}
+
+impl DeltaDecoder {
+    /// Reconstructs the full channel vector for an authenticated frame.
+    ///
+    /// Keyframes pass `channels` through unchanged; deltas are applied on top of
+    /// the referenced base, which must be in the format of `channels`.
+    /// `overlay` then edits the rebuilt vector before it becomes a base, and a
+    /// failing overlay leaves the decoder untouched. Fails with
+    /// [`StreamError::DeltaBaseMissing`] when the base was lost, leaving the
+    /// decoder waiting for the next keyframe.
+    pub(crate) fn decode(
+        &mut self,
+        sequence: u64,
+        kind: FrameKind,
+        mut channels: ChannelBuffer,
+        base_sequence: Option<u64>,
+        ranges: Option<&[ChannelRange]>,
+        overlay: impl FnOnce(&mut ChannelBuffer) -> Result<(), StreamError>,
+    ) -> Result<ChannelBuffer, StreamError> {
+        match kind {
+            FrameKind::Keyframe => {
+                overlay(&mut channels)?;
+                let reference = Reference {
+                    sequence,
+                    channels,
+                    depth: 0,
+                };
+                let channels = reference.channels.clone();
+                self.keyframe = Some(reference.clone());
+                self.previous = Some(reference);
+                Ok(channels)
+            }
+            FrameKind::Delta => {
+                let base_sequence = base_sequence
+                    .ok_or_else(|| StreamError::Decode("delta frame without base".into()))?;
+                let base = [self.previous.as_ref(), self.keyframe.as_ref()]
+                    .into_iter()
+                    .flatten()
+                    .find(|reference| reference.sequence == base_sequence)
+                    .filter(|reference| reference.channels.format() == channels.format())
+                    .ok_or(StreamError::DeltaBaseMissing(base_sequence))?;
+                let mut rebuilt = base.channels.clone();
+                apply_ranges(&mut rebuilt, ranges.unwrap_or_default())
+                    .map_err(|_| StreamError::Decode("delta range exceeds channel count".into()))?;
+                overlay(&mut rebuilt)?;
+                self.previous = Some(Reference {
+                    sequence,
+                    channels: rebuilt.clone(),
+                    depth: base.depth.saturating_add(1),
+                });
+                Ok(rebuilt)
+            }
+        }
+    }
+}
+
+/// Overwrites `channels` with each range, failing with the offset of the first
+/// range that does not fit.
+fn apply_ranges(channels: &mut ChannelBuffer, ranges: &[ChannelRange]) -> Result<(), u32> {
+    for range in ranges {
+        if !channels.splice(range.offset as usize, &range.values) {
+            return Err(range.offset);
+        }
+    }
+    Ok(())
+}
+
+/// Stores `reference` in `slot`, reusing the existing channel buffer.
+fn record(slot: &mut Option<Reference>, sequence: u64, channels: &ChannelBuffer, depth: u8) {
+    match slot {
+        Some(reference) => {
+            reference.sequence = sequence;
+            reference.channels.copy_from(channels);
+            reference.depth = depth;
+        }
+        None => {
+            *slot = Some(Reference {
+                sequence,
+                channels: channels.clone(),
+                depth,
+            })
+        }
+    }
+}
+
+/// Writes the changed index spans between two equally sized channel vectors.
+fn diff_spans<T: PartialEq>(base: &[T], next: &[T], spans: &mut Vec<Range<usize>>) {
+    spans.clear();
+    let mut idx = 0;
+    while idx < next.len() {
+        if base.get(idx) == Some(&next[idx]) {
+            idx += 1;
+            continue;
+        }
+        let start = idx;
+        let mut end = idx + 1;
+        let mut unchanged = 0;
+        let mut cursor = end;
+        while cursor < next.len() && unchanged <= RANGE_MERGE_GAP {
+            if base.get(cursor) == Some(&next[cursor]) {
+                unchanged += 1;
+            } else {
+                unchanged = 0;
+                end = cursor + 1;
+            }
+            cursor += 1;
+        }
+        spans.push(start..end);
+        idx = end;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(
+        encoder: &mut DeltaEncoder,
+        sequence: u64,
+        channels: &[u8],
+        depth: u8,
+    ) -> EncodedFrame {
+        encoder.encode(
+            sequence,
+            &ChannelBuffer::U8(channels.to_vec()),
+            false,
+            depth,
+            &mut Vec::new(),
+        )
+    }
+
+    fn ranges(channels: &ChannelBuffer, spans: &[Range<usize>]) -> Vec<ChannelRange> {
+        spans
+            .iter()
+            .map(|span| ChannelRange {
+                offset: span.start as u32,
+                values: channels.slice(span.clone()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn diff_merges_nearby_changes() {
+        let base = vec![0u16; 16];
+        let mut next = base.clone();
+        next[1] = 5;
+        next[3] = 6;
+        next[12] = 7;
+        let mut spans = Vec::new();
+        diff_spans(&base, &next, &mut spans);
+        assert_eq!(spans, vec![1..4, 12..13]);
+    }
+
+    #[test]
+    fn delta_chain_respects_depth_then_rebases_on_keyframe() {
+        let mut encoder = DeltaEncoder::default();
+        let mut frame = vec![0u8; 32];
+        assert_eq!(encode(&mut encoder, 1, &frame, 2), EncodedFrame::Keyframe);
+        let mut bases = Vec::new();
+        for seq in 2..=5 {
+            frame[seq as usize] = seq as u8;
+            match encode(&mut encoder, seq, &frame, 2) {
+                EncodedFrame::Delta { base_sequence } => bases.push(base_sequence),
+                EncodedFrame::Keyframe => panic!("expected delta"),
+            }
+        }
+        assert_eq!(bases, vec![1, 2, 1, 4]);
+    }
+
+    #[test]
+    fn zero_depth_and_large_changes_fall_back_to_keyframes() {
+        let mut encoder = DeltaEncoder::default();
+        encode(&mut encoder, 1, &[0; 8], 3);
+        assert_eq!(encode(&mut encoder, 2, &[1; 8], 3), EncodedFrame::Keyframe);
+        assert_eq!(encode(&mut encoder, 3, &[1; 8], 0), EncodedFrame::Keyframe);
+    }
+
+    #[test]
+    fn partial_updates_apply_to_the_last_frame() {
+        let mut encoder = DeltaEncoder::default();
+        let ranges = [ChannelRange {
+            offset: 6,
+            values: ChannelBuffer::U8(vec![1, 2]),
+        }];
+        assert!(matches!(
+            encoder.apply_partial(&ChannelFormat::U8, &ranges),
+            Err(StreamError::PartialBaseMissing)
+        ));
+        encode(&mut encoder, 1, &[0; 8], 3);
+        assert_eq!(
+            encoder.apply_partial(&ChannelFormat::U8, &ranges).unwrap(),
+            ChannelBuffer::U8(vec![0, 0, 0, 0, 0, 0, 1, 2])
+        );
+        let wide = [ChannelRange {
+            offset: 6,
+            values: ChannelBuffer::U16(vec![1, 2]),
+        }];
+        assert!(matches!(
+            encoder.apply_partial(&ChannelFormat::U8, &wide),
+            Err(StreamError::PartialRangeFormat(6))
+        ));
+        assert!(matches!(
+            encoder.apply_partial(&ChannelFormat::U16, &ranges),
+            Err(StreamError::PartialBaseMissing)
+        ));
+        let overflow = [ChannelRange {
+            offset: 7,
+            values: ChannelBuffer::U8(vec![1, 2]),
+        }];
+        assert!(matches!(
+            encoder.apply_partial(&ChannelFormat::U8, &overflow),
+            Err(StreamError::PartialRangeOutOfBounds(7))
+        ));
+    }
+
+    #[test]
+    fn decoder_rebuilds_and_detects_missing_base() {
+        let mut encoder = DeltaEncoder::default();
+        let mut decoder = DeltaDecoder::default();
+        let key = ChannelBuffer::Rgb8(vec![[10, 20, 30]; 16]);
+        encoder.encode(1, &key, false, 3, &mut Vec::new());
+        decoder
+            .decode(1, FrameKind::Keyframe, key.clone(), None, None, |_| Ok(()))
+            .unwrap();
+
+        let mut next = key.clone();
+        if let ChannelBuffer::Rgb8(pixels) = &mut next {
+            pixels[4] = [99, 0, 0];
+        }
+        let mut spans = Vec::new();
+        let EncodedFrame::Delta { base_sequence } = encoder.encode(2, &next, false, 3, &mut spans)
+        else {
+            panic!("expected delta");
+        };
+        let ranges = ranges(&next, &spans);
+        let rebuilt = decoder
+            .decode(
+                2,
+                FrameKind::Delta,
+                ChannelBuffer::new(ChannelFormat::Rgb8),
+                Some(base_sequence),
+                Some(&ranges),
+                |_| Ok(()),
+            )
+            .unwrap();
+        assert_eq!(rebuilt, next);
+
+        let missing = decoder.decode(
+            4,
+            FrameKind::Delta,
+            ChannelBuffer::new(ChannelFormat::Rgb8),
+            Some(3),
+            Some(&ranges),
+            |_| Ok(()),
+        );
+        assert!(matches!(missing, Err(StreamError::DeltaBaseMissing(3))));
+    }
+}
@@ -0,0 +1,91 @@
+//! Routing of datagrams to the logical streams of one session.
+//!
+//! A controller can run several [`AlnpStream`](crate::stream::AlnpStream)s
+//! over one session, e.g. one per universe group or per profile, each with its
+//! own `stream_id`, sequence space and adaptation state. Every datagram on the
+//! wire carries its stream id, so a node keeps one
+//! [`AlnpStreamReceiver`] per stream and a [`StreamDemux`] hands each inbound
+//! datagram to the receiver it belongs to. Controllers route receiver reports
+//! and keyframe requests back to the right sender with [`peek_stream_id`].
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+use crate::messages::FrameEnvelope;
+use crate::stream::playout::PlayoutPush;
+use crate::stream::receiver::AlnpStreamReceiver;
+use crate::stream::StreamError;
+
+/// Fields read from a datagram before it is routed.
+#[derive(Deserialize)]
+struct StreamTag {
+    #[serde(default)]
+    stream_id: u8,
+}
+
+/// Reads the logical stream id of any streaming datagram without
+/// authenticating it.
+///
+/// Frames, fragments, parity, receiver reports and keyframe requests are all
+/// accepted; datagrams from senders that predate stream ids read as stream 0.
+/// The id only selects which stream authenticates the datagram, so a forged
+/// id at worst reaches a stream that then rejects it.
+pub fn peek_stream_id(bytes: &[u8]) -> Result<u8, StreamError> {
+    serde_cbor::from_slice::<StreamTag>(bytes)
+        .map(|tag| tag.stream_id)
+        .map_err(|e| StreamError::Decode(e.to_string()))
+}
+
+/// Set of receivers for the logical streams of one session, keyed by stream id.
+#[derive(Debug, Default)]
+pub struct StreamDemux {
+    streams: BTreeMap<u8, AlnpStreamReceiver>,
+}
+
+impl StreamDemux {
+    /// Creates a demultiplexer without any streams.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `receiver` under its [`AlnpStreamReceiver::stream_id`],
+    /// replacing any receiver already registered for that id.
+    pub fn with_stream(mut self, receiver: AlnpStreamReceiver) -> Self {
+        self.streams.insert(receiver.stream_id(), receiver);
+        self
+    }
+
+    /// Receiver registered for `stream_id`, if any.
+    pub fn stream(&self, stream_id: u8) -> Option<&AlnpStreamReceiver> {
+        self.streams.get(&stream_id)
+    }
+
+    /// Registered receivers in stream id order.
+    pub fn streams(&self) -> impl Iterator<Item = &AlnpStreamReceiver> {
+        self.streams.values()
+    }
+
+    /// Receiver the datagram belongs to.
+    ///
+    /// Fails with [`StreamError::UnknownStream`] when no receiver is registered
+    /// for the datagram's stream id.
+    pub fn route(&self, bytes: &[u8]) -> Result<&AlnpStreamReceiver, StreamError> {
+        let stream_id = peek_stream_id(bytes)?;
+        self.stream(stream_id)
+            .ok_or(StreamError::UnknownStream(stream_id))
+    }
+
+    /// Decodes a datagram on the stream it belongs to; see
+    /// [`AlnpStreamReceiver::receive`].
+    pub fn receive(&self, bytes: &[u8]) -> Result<(u8, FrameEnvelope), StreamError> {
+        let receiver = self.route(bytes)?;
+        Ok((receiver.stream_id(), receiver.receive(bytes)?))
+    }
+
+    /// Queues a datagram for playout on the stream it belongs to; see
+    /// [`AlnpStreamReceiver::enqueue`].
+    pub fn enqueue(&self, bytes: &[u8]) -> Result<(u8, PlayoutPush), StreamError> {
+        let receiver = self.route(bytes)?;
+        Ok((receiver.stream_id(), receiver.enqueue(bytes)?))
+    }
+}
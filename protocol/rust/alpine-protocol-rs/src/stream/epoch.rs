@@ -0,0 +1,100 @@
+//! Per-instance keys for streaming messages.
+//!
+//! Streaming nonces are built from a stream ID and a counter that starts at 1
+//! in every `AlnpStream` and `AlnpStreamReceiver`, so recreating either on the
+//! same session would repeat nonces under the session `stream_key`. Each
+//! instance therefore takes an `epoch` from its session, stamps it on every
+//! datagram it sends, and seals with a key derived from `stream_key` for its
+//! `(stream_id, epoch)`.
+//!
+//! A session hands out strictly increasing epochs, which lets the peer tell a
+//! restarted instance from a replayed one: once a message from a newer epoch
+//! authenticates, the peer follows it from sequence 1, and messages from any
+//! older epoch are rejected from then on. An epoch that authenticates but is
+//! older than the current one is a replay of an instance that was replaced,
+//! so it never displaces the live one.
+use crate::crypto::SessionKeys;
+use crate::session::AlnpSession;
+use crate::stream::StreamError;
+
+/// Takes the epoch of a new stream instance from `session`.
+pub(crate) fn new_epoch(session: &AlnpSession) -> u64 {
+    session.next_stream_epoch()
+}
+
+/// Derived keys of the epoch used last, so HKDF runs once per epoch instead
+/// of once per message.
+#[derive(Debug, Default)]
+pub(crate) struct EpochKeys {
+    cached: Option<(u8, u64, SessionKeys)>,
+}
+
+impl EpochKeys {
+    /// Returns the keys of `(stream_id, epoch)` under the session's `stream_key`.
+    pub(crate) fn get(
+        &mut self,
+        session: &AlnpSession,
+        stream_id: u8,
+        epoch: u64,
+    ) -> Result<SessionKeys, StreamError> {
+        if let Some((id, cached_epoch, keys)) = &self.cached {
+            if *id == stream_id && *cached_epoch == epoch {
+                return Ok(keys.clone());
+            }
+        }
+        let keys = session
+            .keys()
+            .ok_or(StreamError::NotAuthenticated)?
+            .for_stream_epoch(stream_id, epoch)
+            .map_err(|e| StreamError::Transport(format!("derive: {}", e)))?;
+        self.cached = Some((stream_id, epoch, keys.clone()));
+        Ok(keys)
+    }
+}
+
+/// Epochs seen from the peer of a stream.
+///
+/// Only the newest authenticated epoch is kept: every older one is rejected,
+/// so no list of replaced epochs has to be remembered.
+#[derive(Debug, Default)]
+pub(crate) struct PeerEpochs {
+    current: Option<u64>,
+    keys: EpochKeys,
+}
+
+impl PeerEpochs {
+    /// Returns the keys to authenticate a peer message from `epoch` with.
+    ///
+    /// Fails with [`StreamError::SessionMacMismatch`] for epochs older than the
+    /// current one.
+    pub(crate) fn keys(
+        &mut self,
+        session: &AlnpSession,
+        stream_id: u8,
+        epoch: u64,
+    ) -> Result<SessionKeys, StreamError> {
+        self.check(epoch)?;
+        self.keys.get(session, stream_id, epoch)
+    }
+
+    /// Makes an authenticated `epoch` current if it is newer than the current
+    /// one.
+    ///
+    /// Returns `true` when the peer restarted, i.e. an earlier epoch was
+    /// replaced, and fails with [`StreamError::SessionMacMismatch`] when a
+    /// newer epoch became current after `epoch` was checked.
+    pub(crate) fn follow(&mut self, epoch: u64) -> Result<bool, StreamError> {
+        self.check(epoch)?;
+        Ok(self
+            .current
+            .replace(epoch)
+            .is_some_and(|previous| previous != epoch))
+    }
+
+    fn check(&self, epoch: u64) -> Result<(), StreamError> {
+        match self.current {
+            Some(current) if epoch < current => Err(StreamError::SessionMacMismatch),
+            _ => Ok(()),
+        }
+    }
+}
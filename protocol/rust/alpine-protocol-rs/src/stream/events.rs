@@ -0,0 +1,131 @@
+//! Subscribable adaptation and recovery events for `AlnpStream`.
+//!
+//! Every recovery transition and adaptation decision is still logged through
+//! `tracing`; this module additionally fans them out as typed [`StreamEvent`]s
+//! over a `tokio::sync::broadcast` channel so operator tooling can explain why
+//! a stream changed behavior without scraping logs. Publishing never blocks
+//! the streaming path: with no subscribers events are discarded, and a
+//! subscriber that falls more than [`EVENT_CHANNEL_CAPACITY`] events behind
+//! sees `RecvError::Lagged` instead of stalling the sender.
+use tokio::sync::broadcast;
+
+use crate::messages::{AdaptationEvent, RecoveryReason};
+use crate::stream::adaptive::AdaptationState;
+use crate::stream::network::NetworkMetrics;
+use crate::stream::recovery::RecoveryEvent;
+
+/// Number of events buffered per subscriber before it starts lagging.
+pub const EVENT_CHANNEL_CAPACITY: usize = 64;
+
+/// What happened to the stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamEventKind {
+    /// A recovery transition or a receiver-initiated keyframe.
+    Recovery(RecoveryEvent),
+    /// An adaptation decision changed the stream parameters.
+    Adaptation(AdaptationEvent),
+}
+
+/// A recovery or adaptation event with the context that caused it.
+#[derive(Debug, Clone)]
+pub struct StreamEvent {
+    /// Local wall-clock time the event was raised, in microseconds.
+    pub timestamp_us: u64,
+    pub kind: StreamEventKind,
+    /// Metrics that drove the decision, or the latest receiver-reported
+    /// metrics for events raised outside a metrics update.
+    pub metrics: Option<NetworkMetrics>,
+    /// Adaptation state after the event was applied.
+    pub adaptation: AdaptationState,
+    /// Recovery reason active after the event was applied.
+    pub recovery: Option<RecoveryReason>,
+}
+
+/// Broadcast sender shared by every subscriber of one stream.
+#[derive(Debug)]
+pub(crate) struct EventBus {
+    sender: broadcast::Sender<StreamEvent>,
+}
+
+impl EventBus {
+    pub(crate) fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        Self { sender }
+    }
+
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<StreamEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Returns `true` when at least one subscriber is listening, so callers
+    /// can skip building snapshots nobody will read.
+    pub(crate) fn has_subscribers(&self) -> bool {
+        self.sender.receiver_count() > 0
+    }
+
+    pub(crate) fn publish(&self, event: StreamEvent) {
+        // Sending only fails when every subscriber has been dropped.
+        let _ = self.sender.send(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::StreamProfile;
+
+    fn event(kind: StreamEventKind) -> StreamEvent {
+        let profile = StreamProfile::auto().compile().unwrap();
+        StreamEvent {
+            timestamp_us: 1,
+            kind,
+            metrics: None,
+            adaptation: AdaptationState::baseline(&profile),
+            recovery: None,
+        }
+    }
+
+    #[test]
+    fn publishes_to_every_subscriber_in_order() {
+        let bus = EventBus::new();
+        assert!(!bus.has_subscribers());
+        bus.publish(event(StreamEventKind::Adaptation(
+            AdaptationEvent::DeltaDisabled,
+        )));
+
+        let mut first = bus.subscribe();
+        let mut second = bus.subscribe();
+        assert!(bus.has_subscribers());
+        let started = RecoveryEvent::RecoveryStarted(RecoveryReason::BurstLoss);
+        bus.publish(event(StreamEventKind::Recovery(started)));
+        bus.publish(event(StreamEventKind::Adaptation(
+            AdaptationEvent::DeltaDisabled,
+        )));
+        for receiver in [&mut first, &mut second] {
+            assert_eq!(
+                receiver.try_recv().unwrap().kind,
+                StreamEventKind::Recovery(started)
+            );
+            assert_eq!(
+                receiver.try_recv().unwrap().kind,
+                StreamEventKind::Adaptation(AdaptationEvent::DeltaDisabled)
+            );
+            assert!(receiver.try_recv().is_err());
+        }
+    }
+
+    #[test]
+    fn slow_subscribers_lag_instead_of_blocking() {
+        let bus = EventBus::new();
+        let mut receiver = bus.subscribe();
+        for _ in 0..EVENT_CHANNEL_CAPACITY + 1 {
+            bus.publish(event(StreamEventKind::Adaptation(
+                AdaptationEvent::DeltaDisabled,
+            )));
+        }
+        assert!(matches!(
+            receiver.try_recv(),
+            Err(broadcast::error::TryRecvError::Lagged(1))
+        ));
+    }
+}
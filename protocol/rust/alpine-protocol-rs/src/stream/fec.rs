@@ -0,0 +1,233 @@
+//! XOR forward error correction across groups of frames.
+//!
+//! Streaming never retransmits, so profiles that favor resilience can ask the
+//! sender to follow every `fec_group_size` consecutive frames with one
+//! `FrameParity` datagram: the XOR of the group's encoded frame datagrams
+//! (zero-padded to the longest) plus the XOR of their lengths. A receiver that
+//! is missing exactly one frame of the group rebuilds its datagram from the
+//! parity and the frames it did receive, then authenticates it like any other
+//! frame, so parity can restore a lost frame but never inject one.
+//!
+//! Groups whose frames needed fragmentation are not protected: their parity
+//! would not fit in a single datagram.
+use std::collections::BTreeMap;
+
+use uuid::Uuid;
+
+use crate::messages::{FrameParity, MessageType};
+use crate::profile::MAX_FEC_GROUP_SIZE;
+
+/// Frame datagrams a receiver keeps for rebuilding, counted back from the newest.
+const RECOVERY_HISTORY: u64 = 2 * MAX_FEC_GROUP_SIZE as u64;
+
+/// Sender-side accumulator for the parity of the current group.
+#[derive(Debug)]
+pub(crate) struct ParityEncoder {
+    group_size: u8,
+    max_datagram: usize,
+    first_sequence: u64,
+    count: u8,
+    length_xor: u32,
+    parity: Vec<u8>,
+    oversized: bool,
+}
+
+impl ParityEncoder {
+    pub(crate) fn new(group_size: u8, max_datagram: usize) -> Self {
+        Self {
+            group_size,
+            max_datagram,
+            first_sequence: 0,
+            count: 0,
+            length_xor: 0,
+            parity: Vec::new(),
+            oversized: false,
+        }
+    }
+
+    /// Adds a sent frame datagram and returns the unsigned parity message once
+    /// its group is complete.
+    ///
+    /// A sequence that does not continue the current group (for example after
+    /// a failed send) starts a new group.
+    pub(crate) fn push(
+        &mut self,
+        session_id: Uuid,
+        stream_id: u8,
+        epoch: u64,
+        sequence: u64,
+        datagram: &[u8],
+    ) -> Option<FrameParity> {
+        if self.count > 0 && sequence != self.first_sequence + self.count as u64 {
+            self.reset();
+        }
+        if self.count == 0 {
+            self.first_sequence = sequence;
+        }
+        self.count += 1;
+        if datagram.len() > self.max_datagram {
+            self.oversized = true;
+        }
+        if !self.oversized {
+            xor_into(&mut self.parity, datagram);
+            self.length_xor ^= datagram.len() as u32;
+        }
+        if self.count < self.group_size {
+            return None;
+        }
+        let parity = (!self.oversized).then(|| FrameParity {
+            message_type: MessageType::AlpineFrameParity,
+            session_id,
+            stream_id,
+            epoch,
+            first_sequence: self.first_sequence,
+            group_size: self.group_size,
+            length_xor: self.length_xor,
+            payload: std::mem::take(&mut self.parity),
+            mac: Vec::new(),
+        });
+        self.reset();
+        parity
+    }
+
+    fn reset(&mut self) {
+        self.count = 0;
+        self.length_xor = 0;
+        self.parity.clear();
+        self.oversized = false;
+    }
+}
+
+/// Receiver-side history of authenticated frame datagrams used for rebuilding.
+#[derive(Debug, Default)]
+pub(crate) struct ParityDecoder {
+    received: BTreeMap<u64, Vec<u8>>,
+}
+
+impl ParityDecoder {
+    /// Remembers an authenticated frame datagram.
+    pub(crate) fn record(&mut self, sequence: u64, datagram: &[u8]) {
+        self.received.insert(sequence, datagram.to_vec());
+        let newest = self.received.keys().next_back().copied().unwrap_or(0);
+        let oldest = newest.saturating_sub(RECOVERY_HISTORY);
+        self.received = self.received.split_off(&oldest);
+    }
+
+    /// Rebuilds the datagram of the single frame missing from an authenticated
+    /// parity's group, returning its sequence and bytes.
+    ///
+    /// Returns `None` when nothing or more than one frame is missing, or when
+    /// the group is older than the kept history.
+    pub(crate) fn recover(&self, parity: &FrameParity) -> Option<(u64, Vec<u8>)> {
+        if parity.group_size == 0 || parity.group_size > MAX_FEC_GROUP_SIZE {
+            return None;
+        }
+        let newest = self.received.keys().next_back().copied()?;
+        if parity.first_sequence < newest.saturating_sub(RECOVERY_HISTORY) {
+            return None;
+        }
+        let mut missing = None;
+        let mut datagram = parity.payload.clone();
+        let mut length = parity.length_xor;
+        for sequence in parity.first_sequence..parity.first_sequence + parity.group_size as u64 {
+            match self.received.get(&sequence) {
+                Some(received) => {
+                    xor_into(&mut datagram, received);
+                    length ^= received.len() as u32;
+                }
+                None if missing.is_none() => missing = Some(sequence),
+                None => return None,
+            }
+        }
+        let sequence = missing?;
+        let length = length as usize;
+        if length > datagram.len() {
+            return None;
+        }
+        datagram.truncate(length);
+        Some((sequence, datagram))
+    }
+}
+
+fn xor_into(acc: &mut Vec<u8>, bytes: &[u8]) {
+    if acc.len() < bytes.len() {
+        acc.resize(bytes.len(), 0);
+    }
+    for (acc, byte) in acc.iter_mut().zip(bytes) {
+        *acc ^= byte;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn datagrams() -> Vec<Vec<u8>> {
+        vec![
+            vec![1, 2, 3],
+            vec![9; 7],
+            vec![4, 5],
+            vec![200, 100, 50, 25],
+        ]
+    }
+
+    fn parity_for(datagrams: &[Vec<u8>]) -> FrameParity {
+        let mut encoder = ParityEncoder::new(datagrams.len() as u8, 1_200);
+        let mut parity = None;
+        for (index, datagram) in datagrams.iter().enumerate() {
+            parity = encoder.push(Uuid::nil(), 0, 1, 10 + index as u64, datagram);
+        }
+        parity.expect("group complete")
+    }
+
+    #[test]
+    fn rebuilds_any_single_missing_frame() {
+        let datagrams = datagrams();
+        let parity = parity_for(&datagrams);
+        assert_eq!(parity.first_sequence, 10);
+        for lost in 0..datagrams.len() {
+            let mut decoder = ParityDecoder::default();
+            for (index, datagram) in datagrams.iter().enumerate() {
+                if index != lost {
+                    decoder.record(10 + index as u64, datagram);
+                }
+            }
+            assert_eq!(
+                decoder.recover(&parity),
+                Some((10 + lost as u64, datagrams[lost].clone()))
+            );
+        }
+    }
+
+    #[test]
+    fn cannot_rebuild_two_losses_or_nothing() {
+        let datagrams = datagrams();
+        let parity = parity_for(&datagrams);
+        let mut decoder = ParityDecoder::default();
+        decoder.record(10, &datagrams[0]);
+        decoder.record(13, &datagrams[3]);
+        assert_eq!(decoder.recover(&parity), None);
+        decoder.record(11, &datagrams[1]);
+        decoder.record(12, &datagrams[2]);
+        assert_eq!(decoder.recover(&parity), None);
+    }
+
+    #[test]
+    fn skipped_sequences_restart_the_group() {
+        let mut encoder = ParityEncoder::new(2, 1_200);
+        assert!(encoder.push(Uuid::nil(), 0, 1, 1, &[1]).is_none());
+        assert!(encoder.push(Uuid::nil(), 0, 1, 3, &[3]).is_none());
+        let parity = encoder.push(Uuid::nil(), 0, 1, 4, &[4]).unwrap();
+        assert_eq!(parity.first_sequence, 3);
+        assert_eq!(parity.payload, vec![3 ^ 4]);
+    }
+
+    #[test]
+    fn groups_with_fragmented_frames_get_no_parity() {
+        let mut encoder = ParityEncoder::new(2, 4);
+        assert!(encoder.push(Uuid::nil(), 0, 1, 1, &[0; 8]).is_none());
+        assert!(encoder.push(Uuid::nil(), 0, 1, 2, &[1]).is_none());
+        assert!(encoder.push(Uuid::nil(), 0, 1, 3, &[1]).is_none());
+        assert!(encoder.push(Uuid::nil(), 0, 1, 4, &[2]).is_some());
+    }
+}
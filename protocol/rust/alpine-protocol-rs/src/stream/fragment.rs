@@ -0,0 +1,258 @@
+//! Fragmentation and reassembly for frames larger than one datagram.
+//!
+//! `AlnpStream` authenticates every frame as a single `FrameEnvelope`
+//! datagram. When that datagram is larger than the configured maximum it is
+//! split into `FrameFragment`s, each carrying a slice of the encoded bytes and
+//! its own tag under the sender's epoch key, so forged fragments are rejected
+//! before they are buffered. Receivers collect the fragments of a sequence in
+//! any order and then process the rebuilt datagram exactly like an
+//! unfragmented one, including the frame tag and replay checks.
+//!
+//! A frame with a missing fragment is never partially applied: it is dropped
+//! once its reassembly timeout expires, or when too many newer frames are
+//! pending, and then shows up as an ordinary sequence gap.
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use uuid::Uuid;
+
+use crate::crypto::SessionKeys;
+use crate::messages::{FrameFragment, MessageType};
+use crate::stream::auth::encode_signed;
+use crate::stream::StreamError;
+
+/// Default largest datagram emitted by a stream; fits a 1280-byte IPv6 MTU.
+pub const DEFAULT_MAX_DATAGRAM_SIZE: usize = 1200;
+
+/// Smallest configurable datagram size; leaves room for the fragment header.
+pub const MIN_DATAGRAM_SIZE: usize = 256;
+
+/// Upper bound on fragments per frame, which also bounds reassembly memory.
+pub const MAX_FRAGMENTS: u16 = 1024;
+
+/// How long a receiver waits for the remaining fragments of a frame.
+pub const DEFAULT_REASSEMBLY_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Incomplete frames kept at once; the oldest is dropped beyond this.
+const MAX_PENDING_FRAMES: usize = 8;
+
+/// Size of the ChaCha20-Poly1305 tag carried in `mac`.
+const TAG_LEN: usize = 16;
+
+/// Worst-case growth of the CBOR byte-string header over an empty payload.
+const PAYLOAD_HEADER_SLACK: usize = 4;
+
+/// Splits an encoded frame datagram into authenticated fragments that each
+/// encode to at most `max_datagram` bytes.
+pub(crate) fn fragment_frame(
+    keys: &SessionKeys,
+    session_id: Uuid,
+    stream_id: u8,
+    epoch: u64,
+    sequence: u64,
+    bytes: &[u8],
+    max_datagram: usize,
+) -> Result<Vec<Vec<u8>>, StreamError> {
+    let template = FrameFragment {
+        message_type: MessageType::AlpineFrameFragment,
+        session_id,
+        stream_id,
+        epoch,
+        sequence,
+        fragment_index: u16::MAX,
+        fragment_count: u16::MAX,
+        payload: Vec::new(),
+        mac: vec![u8::MAX; TAG_LEN],
+    };
+    let overhead = serde_cbor::to_vec(&template)
+        .map_err(|e| StreamError::Transport(format!("encode: {}", e)))?
+        .len()
+        + PAYLOAD_HEADER_SLACK;
+    let chunk = max_datagram.max(MIN_DATAGRAM_SIZE).saturating_sub(overhead);
+    if chunk == 0 {
+        return Err(StreamError::FrameTooLarge(bytes.len()));
+    }
+    let count = bytes.len().div_ceil(chunk);
+    if count > MAX_FRAGMENTS as usize {
+        return Err(StreamError::FrameTooLarge(bytes.len()));
+    }
+    bytes
+        .chunks(chunk)
+        .enumerate()
+        .map(|(index, payload)| {
+            let mut fragment = FrameFragment {
+                fragment_index: index as u16,
+                fragment_count: count as u16,
+                payload: payload.to_vec(),
+                mac: Vec::new(),
+                ..template.clone()
+            };
+            encode_signed(keys, &mut fragment)
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+struct PartialFrame {
+    first_arrival_us: u64,
+    pieces: Vec<Option<Vec<u8>>>,
+    missing: usize,
+}
+
+/// Collects authenticated fragments until their frame datagram is complete.
+#[derive(Debug)]
+pub(crate) struct Reassembler {
+    timeout_us: u64,
+    pending: BTreeMap<u64, PartialFrame>,
+}
+
+impl Reassembler {
+    pub(crate) fn new(timeout: Duration) -> Self {
+        Self {
+            timeout_us: timeout.as_micros() as u64,
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// Adds an authenticated fragment and returns the rebuilt datagram once
+    /// every fragment of its frame has arrived.
+    ///
+    /// Duplicate fragments are ignored. Fragments whose index or count is out
+    /// of range, or whose count disagrees with earlier fragments of the same
+    /// sequence, are rejected.
+    pub(crate) fn push(
+        &mut self,
+        fragment: FrameFragment,
+        arrival_us: u64,
+    ) -> Result<Option<Vec<u8>>, StreamError> {
+        let count = fragment.fragment_count;
+        if count == 0 || count > MAX_FRAGMENTS || fragment.fragment_index >= count {
+            return Err(StreamError::Decode(format!(
+                "fragment {} of {} out of range",
+                fragment.fragment_index, count
+            )));
+        }
+        self.expire(arrival_us);
+        let partial = self
+            .pending
+            .entry(fragment.sequence)
+            .or_insert_with(|| PartialFrame {
+                first_arrival_us: arrival_us,
+                pieces: vec![None; count as usize],
+                missing: count as usize,
+            });
+        if partial.pieces.len() != count as usize {
+            return Err(StreamError::Decode(format!(
+                "fragment count {} does not match {} for sequence {}",
+                count,
+                partial.pieces.len(),
+                fragment.sequence
+            )));
+        }
+        let slot = &mut partial.pieces[fragment.fragment_index as usize];
+        if slot.is_some() {
+            return Ok(None);
+        }
+        *slot = Some(fragment.payload);
+        partial.missing -= 1;
+        if partial.missing == 0 {
+            let partial = self
+                .pending
+                .remove(&fragment.sequence)
+                .expect("complete frame is pending");
+            return Ok(Some(
+                partial.pieces.into_iter().flatten().flatten().collect(),
+            ));
+        }
+        while self.pending.len() > MAX_PENDING_FRAMES {
+            self.pending.pop_first();
+        }
+        Ok(None)
+    }
+
+    /// Drops incomplete frames whose first fragment arrived more than the
+    /// timeout before `now_us` and returns how many were dropped.
+    pub(crate) fn expire(&mut self, now_us: u64) -> usize {
+        let before = self.pending.len();
+        let timeout_us = self.timeout_us;
+        self.pending
+            .retain(|_, partial| now_us.saturating_sub(partial.first_arrival_us) <= timeout_us);
+        before - self.pending.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::auth::open_signed;
+
+    fn keys() -> SessionKeys {
+        SessionKeys {
+            shared_secret: Vec::new(),
+            control_key: [1u8; 32],
+            stream_key: [2u8; 32],
+        }
+    }
+
+    fn fragments(len: usize, max_datagram: usize) -> (Vec<u8>, Vec<FrameFragment>) {
+        let bytes: Vec<u8> = (0..len).map(|i| i as u8).collect();
+        let fragments = fragment_frame(&keys(), Uuid::nil(), 0, 1, 7, &bytes, max_datagram)
+            .unwrap()
+            .iter()
+            .map(|datagram| {
+                assert!(datagram.len() <= max_datagram);
+                let mut fragment: FrameFragment = serde_cbor::from_slice(datagram).unwrap();
+                open_signed(&keys(), &mut fragment).unwrap();
+                fragment
+            })
+            .collect();
+        (bytes, fragments)
+    }
+
+    #[test]
+    fn fragments_fit_datagram_and_reassemble_out_of_order() {
+        let (bytes, mut fragments) = fragments(5_000, 600);
+        assert!(fragments.len() > 8);
+        fragments.reverse();
+        let mut reassembler = Reassembler::new(DEFAULT_REASSEMBLY_TIMEOUT);
+        let last = fragments.pop().unwrap();
+        for fragment in fragments {
+            assert_eq!(reassembler.push(fragment, 0).unwrap(), None);
+        }
+        assert_eq!(reassembler.push(last, 0).unwrap(), Some(bytes));
+        assert_eq!(reassembler.pending.len(), 0);
+    }
+
+    #[test]
+    fn fragment_tags_are_bound_to_their_index() {
+        let (_, fragments) = fragments(2_000, 600);
+        let mut moved = fragments[1].clone();
+        moved.fragment_index = 0;
+        assert!(matches!(
+            open_signed(&keys(), &mut moved),
+            Err(StreamError::SessionMacMismatch)
+        ));
+    }
+
+    #[test]
+    fn incomplete_frames_time_out() {
+        let (_, mut fragments) = fragments(2_000, 600);
+        let mut reassembler = Reassembler::new(Duration::from_millis(10));
+        let last = fragments.pop().unwrap();
+        for fragment in fragments {
+            reassembler.push(fragment, 0).unwrap();
+        }
+        assert_eq!(reassembler.expire(20_000), 1);
+        assert_eq!(reassembler.push(last, 20_000).unwrap(), None);
+        assert_eq!(reassembler.pending.len(), 1);
+    }
+
+    #[test]
+    fn oversized_frames_are_rejected() {
+        let bytes = vec![0u8; MAX_FRAGMENTS as usize * MIN_DATAGRAM_SIZE];
+        assert!(matches!(
+            fragment_frame(&keys(), Uuid::nil(), 0, 1, 1, &bytes, MIN_DATAGRAM_SIZE),
+            Err(StreamError::FrameTooLarge(_))
+        ));
+    }
+}
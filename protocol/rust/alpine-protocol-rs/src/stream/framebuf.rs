@@ -0,0 +1,85 @@
+//! Caller-owned buffers for the allocation-free send path.
+//!
+//! [`AlnpStream::send`](crate::stream::AlnpStream::send) takes the channel
+//! vector by value and builds an owned `FrameEnvelope` for every frame. At
+//! high frame rates and universe counts those per-frame allocations dominate
+//! the encode cost, so [`AlnpStream::send_with`](crate::stream::AlnpStream::send_with)
+//! instead encodes straight from a [`FrameBuf`]: the caller writes channel
+//! values in place and every intermediate (packed channels, delta spans, tag
+//! input, wire bytes) lives in buffers that are reused from frame to frame.
+use std::ops::Range;
+
+use crate::messages::{ChannelBuffer, ChannelFormat};
+use crate::stream::auth::FrameScratch;
+
+/// Reusable buffers for encoding one frame.
+#[derive(Debug, Default)]
+pub(crate) struct EncodeScratch {
+    /// Channel values packed per the frame's format.
+    pub(crate) packed: Vec<u8>,
+    /// Changed channel spans of a delta frame.
+    pub(crate) spans: Vec<Range<usize>>,
+    pub(crate) frame: FrameScratch,
+    /// Wire bytes of the last encoded frame.
+    pub(crate) datagram: Vec<u8>,
+}
+
+/// Channel vector plus encode buffers reused across frames.
+///
+/// # Guarantees
+/// * Channel values keep their format, length and contents between sends;
+///   only the caller changes them, through [`FrameBuf::channels_mut`].
+/// * Once the buffers have grown to the frame size, sending a frame that fits
+///   in one datagram performs no heap allocation for encoding.
+#[derive(Debug)]
+pub struct FrameBuf {
+    channels: ChannelBuffer,
+    pub(crate) scratch: EncodeScratch,
+}
+
+impl FrameBuf {
+    /// Creates an empty buffer for `channel_format` frames.
+    pub fn new(channel_format: ChannelFormat) -> Self {
+        Self::with_channels(channel_format, 0)
+    }
+
+    /// Creates a buffer holding `slot_count` zeroed slots, with encode
+    /// buffers sized for them up front.
+    pub fn with_channels(channel_format: ChannelFormat, slot_count: usize) -> Self {
+        let mut scratch = EncodeScratch::default();
+        scratch
+            .packed
+            .reserve(slot_count * channel_format.slot_width());
+        Self {
+            channels: ChannelBuffer::zeroed(channel_format, slot_count),
+            scratch,
+        }
+    }
+
+    /// Channel format of the frames sent from this buffer.
+    pub fn channel_format(&self) -> ChannelFormat {
+        self.channels.format()
+    }
+
+    /// Channel values of the next frame.
+    pub fn channels(&self) -> &ChannelBuffer {
+        &self.channels
+    }
+
+    /// Mutable channel values of the next frame. Resizing is allowed, and so
+    /// is replacing the buffer with one of another format, after which the
+    /// next frame sent is a keyframe.
+    pub fn channels_mut(&mut self) -> &mut ChannelBuffer {
+        &mut self.channels
+    }
+
+    /// Wire bytes of the last frame sent from this buffer, before any
+    /// fragmentation.
+    pub fn datagram(&self) -> &[u8] {
+        &self.scratch.datagram
+    }
+
+    pub(crate) fn parts(&mut self) -> (&ChannelBuffer, &mut EncodeScratch) {
+        (&self.channels, &mut self.scratch)
+    }
+}
@@ -0,0 +1,219 @@
+//! Named channel groups referenced by ID in streaming frames.
+//!
+//! A controller declares its groups to the device once over the control plane
+//! (`ControlOp::DefineGroups`, with a [`ChannelGroups`] payload) and from then
+//! on sets a whole group with one entry in `FrameEnvelope.groups`: a single
+//! value sets every member slot, or one value per member sets them in
+//! declaration order. Members are slot indices, so a group of RGB pixels is
+//! set with one colour. A group assignment acts as a mask over the frame's
+//! channels: member channels take the group values, every other channel keeps
+//! the value the frame carries. Groups may share members; assignments are
+//! applied in sorted group ID order, so the last ID to name a channel sets it
+//! on both ends no matter how either side's map happens to iterate.
+//!
+//! Both ends apply assignments after rebuilding the channel vector, so the
+//! result is what the delta chain continues from. The sender therefore leaves
+//! member channels out of delta ranges, and receivers rebuild them from the
+//! group values alone.
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::messages::ChannelBuffer;
+use crate::stream::StreamError;
+
+/// Group table shared by controller and device, keyed by group ID.
+///
+/// Serialized as a map from group ID to member channel indices, which is the
+/// payload of `ControlOp::DefineGroups`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ChannelGroups {
+    groups: BTreeMap<String, Vec<u32>>,
+}
+
+impl ChannelGroups {
+    /// Creates an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declares group `id` with its member channel indices, replacing any
+    /// previous group of that ID.
+    pub fn with_group(mut self, id: impl Into<String>, members: Vec<u32>) -> Self {
+        self.groups.insert(id.into(), members);
+        self
+    }
+
+    /// Member channels of group `id`, in declaration order.
+    pub fn members(&self, id: &str) -> Option<&[u32]> {
+        self.groups.get(id).map(Vec::as_slice)
+    }
+
+    /// Declared group IDs in sorted order.
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.groups.keys().map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Writes the group `values` into the member channels, in sorted group ID
+    /// order so overlapping groups resolve identically on both ends.
+    ///
+    /// Fails with [`StreamError::UnknownGroup`] for undeclared IDs,
+    /// [`StreamError::GroupFormat`] for values in another format than
+    /// `channels`, [`StreamError::GroupValueCount`] unless a group gets one
+    /// value or one per member, and [`StreamError::GroupMemberOutOfBounds`] for
+    /// members past the end of `channels`. On failure `channels` may be
+    /// partially written.
+    pub(crate) fn apply(
+        &self,
+        channels: &mut ChannelBuffer,
+        values: &HashMap<String, ChannelBuffer>,
+    ) -> Result<(), StreamError> {
+        let mut values: Vec<_> = values.iter().collect();
+        values.sort_unstable_by_key(|(id, _)| *id);
+        for (id, group_values) in values {
+            let members = self
+                .members(id)
+                .ok_or_else(|| StreamError::UnknownGroup(id.clone()))?;
+            if group_values.format() != channels.format() {
+                return Err(StreamError::GroupFormat(id.clone()));
+            }
+            if group_values.len() != 1 && group_values.len() != members.len() {
+                return Err(StreamError::GroupValueCount(id.clone()));
+            }
+            for (index, &member) in members.iter().enumerate() {
+                let from = index.min(group_values.len() - 1);
+                if !channels.copy_slot(member as usize, group_values, from) {
+                    return Err(StreamError::GroupMemberOutOfBounds(member));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the members of the assigned groups from delta `spans`, since
+    /// receivers rebuild them from the group values.
+    pub(crate) fn exclude_members(
+        &self,
+        values: &HashMap<String, ChannelBuffer>,
+        spans: &mut Vec<Range<usize>>,
+    ) {
+        let mut members: Vec<usize> = values
+            .keys()
+            .filter_map(|id| self.members(id))
+            .flatten()
+            .map(|&member| member as usize)
+            .collect();
+        members.sort_unstable();
+        members.dedup();
+        let mut kept = Vec::with_capacity(spans.len());
+        for span in spans.drain(..) {
+            let first = members.partition_point(|&member| member < span.start);
+            let mut start = span.start;
+            for &member in members[first..].iter().take_while(|&&m| m < span.end) {
+                if member > start {
+                    kept.push(start..member);
+                }
+                start = member + 1;
+            }
+            if start < span.end {
+                kept.push(start..span.end);
+            }
+        }
+        *spans = kept;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> ChannelGroups {
+        ChannelGroups::new()
+            .with_group("wash", vec![1, 3, 5])
+            .with_group("spot", vec![6, 7])
+    }
+
+    fn values(entries: &[(&str, &[u8])]) -> HashMap<String, ChannelBuffer> {
+        entries
+            .iter()
+            .map(|(id, values)| (id.to_string(), ChannelBuffer::U8(values.to_vec())))
+            .collect()
+    }
+
+    #[test]
+    fn one_value_sets_every_member() {
+        let mut channels = ChannelBuffer::U8(vec![0; 8]);
+        table()
+            .apply(&mut channels, &values(&[("wash", &[9]), ("spot", &[1, 2])]))
+            .unwrap();
+        assert_eq!(channels, ChannelBuffer::U8(vec![0, 9, 0, 9, 0, 9, 1, 2]));
+
+        let mut pixels = ChannelBuffer::Rgb8(vec![[0; 3]; 8]);
+        let red = HashMap::from([("spot".to_string(), ChannelBuffer::Rgb8(vec![[255, 0, 0]]))]);
+        table().apply(&mut pixels, &red).unwrap();
+        let ChannelBuffer::Rgb8(pixels) = pixels else {
+            unreachable!()
+        };
+        assert_eq!(pixels[5..], [[0, 0, 0], [255, 0, 0], [255, 0, 0]]);
+    }
+
+    #[test]
+    fn assignments_are_validated() {
+        let mut channels = ChannelBuffer::U8(vec![0; 6]);
+        assert!(matches!(
+            table().apply(&mut channels, &values(&[("fog", &[1])])),
+            Err(StreamError::UnknownGroup(id)) if id == "fog"
+        ));
+        assert!(matches!(
+            table().apply(&mut channels, &values(&[("wash", &[1, 2])])),
+            Err(StreamError::GroupValueCount(id)) if id == "wash"
+        ));
+        assert!(matches!(
+            table().apply(&mut channels, &values(&[("spot", &[1])])),
+            Err(StreamError::GroupMemberOutOfBounds(6))
+        ));
+        let wide = HashMap::from([("wash".to_string(), ChannelBuffer::U16(vec![1]))]);
+        assert!(matches!(
+            table().apply(&mut channels, &wide),
+            Err(StreamError::GroupFormat(id)) if id == "wash"
+        ));
+    }
+
+    #[test]
+    fn overlapping_groups_apply_in_id_order() {
+        let overlapping = ChannelGroups::new()
+            .with_group("b", vec![1, 2])
+            .with_group("a", vec![0, 1]);
+        for _ in 0..16 {
+            // Fresh maps iterate in different orders; the result must not.
+            let mut channels = ChannelBuffer::U8(vec![0; 4]);
+            overlapping
+                .apply(&mut channels, &values(&[("b", &[7]), ("a", &[5])]))
+                .unwrap();
+            assert_eq!(channels, ChannelBuffer::U8(vec![5, 7, 7, 0]));
+        }
+    }
+
+    #[test]
+    fn member_channels_are_cut_out_of_spans() {
+        let mut spans = vec![0..4, 5..8, 10..12];
+        table().exclude_members(&values(&[("wash", &[1])]), &mut spans);
+        assert_eq!(spans, vec![0..1, 2..3, 6..8, 10..12]);
+    }
+
+    #[test]
+    fn table_serializes_as_a_plain_map() {
+        let json = serde_json::to_value(table()).unwrap();
+        assert_eq!(json, serde_json::json!({"spot": [6, 7], "wash": [1, 3, 5]}));
+        assert_eq!(
+            serde_json::from_value::<ChannelGroups>(json).unwrap(),
+            table()
+        );
+    }
+}
@@ -0,0 +1,288 @@
+//! Node-side jitter strategies for the playout path.
+//!
+//! The device decides what to output when frames go missing, so the
+//! `JitterStrategy` variants live here rather than on the sender. Gaps are
+//! detected from `sequence` numbers as frames leave the playout buffer:
+//! * `HoldLast` repeats the last output for every missing frame.
+//! * `Drop` outputs nothing for missing frames.
+//! * `Lerp` interpolates each missing frame between the timestamped frames on
+//!   either side of the gap.
+//!
+//! Received frames are never altered, so intentional changes pass through at
+//! full size.
+use crate::messages::{ChannelBuffer, FrameEnvelope};
+use crate::session::JitterStrategy;
+
+/// Gaps longer than this are treated as an outage and not filled in.
+pub const MAX_CONCEALED_FRAMES: u64 = 8;
+
+/// Synthesized output standing in for a frame that never arrived.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConcealedFrame {
+    /// Sequence of the missing frame.
+    pub sequence: u64,
+    /// Estimated sender timestamp of the missing frame.
+    pub timestamp_us: u64,
+    pub channels: ChannelBuffer,
+}
+
+/// Output of the playout path, in sequence order.
+///
+/// Frames are handed straight to the caller, so `Received` is not boxed.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone)]
+pub enum PlayoutFrame {
+    /// An authenticated frame with its channels fully reconstructed.
+    Received(FrameEnvelope),
+    /// A frame produced by the jitter strategy to cover a sequence gap.
+    Concealed(ConcealedFrame),
+}
+
+impl PlayoutFrame {
+    pub fn sequence(&self) -> u64 {
+        match self {
+            PlayoutFrame::Received(frame) => frame.sequence,
+            PlayoutFrame::Concealed(frame) => frame.sequence,
+        }
+    }
+
+    pub fn timestamp_us(&self) -> u64 {
+        match self {
+            PlayoutFrame::Received(frame) => frame.timestamp_us,
+            PlayoutFrame::Concealed(frame) => frame.timestamp_us,
+        }
+    }
+
+    pub fn channels(&self) -> &ChannelBuffer {
+        match self {
+            PlayoutFrame::Received(frame) => &frame.channels,
+            PlayoutFrame::Concealed(frame) => &frame.channels,
+        }
+    }
+
+    /// Returns `true` when the frame was synthesized by the jitter strategy.
+    pub fn is_concealed(&self) -> bool {
+        matches!(self, PlayoutFrame::Concealed(_))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct OutputFrame {
+    sequence: u64,
+    timestamp_us: u64,
+    channels: ChannelBuffer,
+}
+
+/// Fills sequence gaps in the playout output according to a `JitterStrategy`.
+#[derive(Debug, Default)]
+pub(crate) struct JitterConcealer {
+    last: Option<OutputFrame>,
+}
+
+impl JitterConcealer {
+    /// Records `frame` as the next output and returns the frames that cover the
+    /// gap before it, oldest first.
+    pub(crate) fn conceal(
+        &mut self,
+        strategy: JitterStrategy,
+        frame: &FrameEnvelope,
+    ) -> Vec<ConcealedFrame> {
+        let next = OutputFrame {
+            sequence: frame.sequence,
+            timestamp_us: frame.timestamp_us,
+            channels: frame.channels.clone(),
+        };
+        let concealed = match self.last.as_ref() {
+            Some(last) => fill_gap(strategy, last, &next),
+            None => Vec::new(),
+        };
+        self.last = Some(next);
+        concealed
+    }
+
+    /// Time at which `next`, due at `target_us`, should leave the playout
+    /// buffer: the target of the first frame `strategy` fills in before it,
+    /// or `target_us` when there is no gap to cover.
+    ///
+    /// Missing frames share the deadline budget of `next`, so each concealed
+    /// frame is due when its estimated timestamp plus that budget passes.
+    pub(crate) fn release_us(
+        &self,
+        strategy: JitterStrategy,
+        next: &FrameEnvelope,
+        target_us: u64,
+    ) -> u64 {
+        let Some(last) = self.last.as_ref() else {
+            return target_us;
+        };
+        let Some(span) = concealed_span(last.sequence, next.sequence) else {
+            return target_us;
+        };
+        if strategy == JitterStrategy::Drop {
+            return target_us;
+        }
+        let elapsed_us = next.timestamp_us.saturating_sub(last.timestamp_us);
+        let budget_us = target_us.saturating_sub(next.timestamp_us);
+        (last.timestamp_us + elapsed_us / span)
+            .saturating_add(budget_us)
+            .min(target_us)
+    }
+}
+
+/// Distance from `last` to `next` when the frames between them are few enough
+/// to conceal.
+fn concealed_span(last: u64, next: u64) -> Option<u64> {
+    let span = next.saturating_sub(last);
+    (span > 1 && span - 1 <= MAX_CONCEALED_FRAMES).then_some(span)
+}
+
+fn fill_gap(
+    strategy: JitterStrategy,
+    last: &OutputFrame,
+    next: &OutputFrame,
+) -> Vec<ConcealedFrame> {
+    let Some(span) = concealed_span(last.sequence, next.sequence) else {
+        return Vec::new();
+    };
+    let comparable = last.channels.format() == next.channels.format()
+        && last.channels.len() == next.channels.len();
+    let elapsed_us = next.timestamp_us.saturating_sub(last.timestamp_us);
+    (1..span)
+        .filter_map(|step| {
+            let timestamp_us = last.timestamp_us + elapsed_us * step / span;
+            let channels = match strategy {
+                JitterStrategy::Drop => return None,
+                JitterStrategy::Lerp if comparable => {
+                    lerp(last, next, timestamp_us, step as f64 / span as f64)
+                }
+                JitterStrategy::HoldLast | JitterStrategy::Lerp => last.channels.clone(),
+            };
+            Some(ConcealedFrame {
+                sequence: last.sequence + step,
+                timestamp_us,
+                channels,
+            })
+        })
+        .collect()
+}
+
+/// Interpolates at `timestamp_us`, falling back to sequence spacing when both
+/// frames carry the same timestamp. Pixels interpolate component by component.
+fn lerp(
+    last: &OutputFrame,
+    next: &OutputFrame,
+    timestamp_us: u64,
+    step_fraction: f64,
+) -> ChannelBuffer {
+    let elapsed_us = next.timestamp_us.saturating_sub(last.timestamp_us);
+    let fraction = if elapsed_us == 0 {
+        step_fraction
+    } else {
+        (timestamp_us - last.timestamp_us) as f64 / elapsed_us as f64
+    };
+    last.channels
+        .lerp(&next.channels, fraction)
+        .unwrap_or_else(|| last.channels.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::{FrameKind, MessageType};
+    use uuid::Uuid;
+
+    fn frame(sequence: u64, timestamp_us: u64, channels: Vec<u8>) -> FrameEnvelope {
+        FrameEnvelope {
+            message_type: MessageType::AlpineFrame,
+            session_id: Uuid::nil(),
+            stream_id: 0,
+            epoch: 0,
+            sequence,
+            timestamp_us,
+            priority: 0,
+            channels: ChannelBuffer::U8(channels),
+            groups: None,
+            metadata: None,
+            adaptation: None,
+            kind: FrameKind::Keyframe,
+            base_sequence: None,
+            delta: None,
+            ciphertext: None,
+            mac: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn consecutive_frames_need_no_concealment() {
+        let mut concealer = JitterConcealer::default();
+        for strategy in [
+            JitterStrategy::HoldLast,
+            JitterStrategy::Drop,
+            JitterStrategy::Lerp,
+        ] {
+            assert!(concealer
+                .conceal(strategy, &frame(1, 0, vec![0]))
+                .is_empty());
+            assert!(concealer
+                .conceal(strategy, &frame(2, 1_000, vec![200]))
+                .is_empty());
+            concealer = JitterConcealer::default();
+        }
+    }
+
+    #[test]
+    fn hold_last_and_drop_act_on_sequence_gaps() {
+        let mut concealer = JitterConcealer::default();
+        concealer.conceal(JitterStrategy::HoldLast, &frame(1, 0, vec![10, 20]));
+        let held = concealer.conceal(JitterStrategy::HoldLast, &frame(4, 3_000, vec![0, 0]));
+        assert_eq!(held.len(), 2);
+        assert_eq!(held[0].sequence, 2);
+        assert_eq!(held[1].channels, ChannelBuffer::U8(vec![10, 20]));
+
+        let mut concealer = JitterConcealer::default();
+        concealer.conceal(JitterStrategy::Drop, &frame(1, 0, vec![10]));
+        assert!(concealer
+            .conceal(JitterStrategy::Drop, &frame(3, 2_000, vec![0]))
+            .is_empty());
+    }
+
+    #[test]
+    fn lerp_interpolates_between_timestamps() {
+        let mut concealer = JitterConcealer::default();
+        concealer.conceal(JitterStrategy::Lerp, &frame(1, 0, vec![0, 200]));
+        let filled = concealer.conceal(JitterStrategy::Lerp, &frame(3, 2_000, vec![100, 0]));
+        assert_eq!(filled.len(), 1);
+        assert_eq!(filled[0].timestamp_us, 1_000);
+        assert_eq!(filled[0].channels, ChannelBuffer::U8(vec![50, 100]));
+    }
+
+    #[test]
+    fn gaps_release_at_the_first_concealed_slot() {
+        let mut concealer = JitterConcealer::default();
+        concealer.conceal(JitterStrategy::HoldLast, &frame(1, 0, vec![0]));
+        let next = frame(4, 3_000, vec![0]);
+        assert_eq!(
+            concealer.release_us(JitterStrategy::HoldLast, &next, 8_000),
+            6_000
+        );
+        assert_eq!(
+            concealer.release_us(JitterStrategy::Drop, &next, 8_000),
+            8_000
+        );
+        let consecutive = frame(2, 1_000, vec![0]);
+        assert_eq!(
+            concealer.release_us(JitterStrategy::HoldLast, &consecutive, 6_000),
+            6_000
+        );
+    }
+
+    #[test]
+    fn outages_beyond_limit_are_not_filled() {
+        let mut concealer = JitterConcealer::default();
+        concealer.conceal(JitterStrategy::HoldLast, &frame(1, 0, vec![1]));
+        let gap = MAX_CONCEALED_FRAMES + 2;
+        assert!(concealer
+            .conceal(JitterStrategy::HoldLast, &frame(1 + gap, 0, vec![1]))
+            .is_empty());
+    }
+}
@@ -3,8 +3,10 @@
 //! Phase 3.1 introduces deterministic metrics for packet loss, late frames, and
 //! jitter so we can reason about what the network is doing without changing
 //! runtime behavior yet. Each session gets its own `NetworkConditions` tracker,
-//! and the metrics snapshot exposes `loss_ratio`, `late_frame_rate`, and
-//! `jitter_ms` derived from observed arrival timelines.
+//! and the metrics snapshot exposes `loss_ratio`, `late_frame_rate`,
+//! `jitter_ms`, and `max_loss_gap` derived from observed arrival timelines
+//! over a sliding window of recent sequences.
+use std::collections::VecDeque;
 
 /// Snapshot of the observed network metrics for a single session.
 #[derive(Debug, Clone, Copy)]
@@ -13,117 +15,218 @@ pub struct NetworkMetrics {
     pub loss_ratio: f64,
     /// Fraction of observed frames that missed their delivery deadline.
     pub late_frame_rate: f64,
-    /// Average jitter in milliseconds between consecutive arrivals.
+    /// RFC 3550 interarrival jitter in milliseconds: a smoothed (gain 1/16)
+    /// estimate of the variation in transit time (`arrival - timestamp_us`)
+    /// between consecutive frames.
     pub jitter_ms: Option<f64>,
+    /// Largest run of consecutive missing sequences.
+    pub max_loss_gap: u64,
+    /// Smoothed round-trip time in milliseconds (RFC 6298 SRTT, gain 1/8),
+    /// when an echo path has produced samples; always `None` on receivers.
+    pub rtt_ms: Option<f64>,
+}
+
+/// Default number of sequences covered by the metrics window.
+pub const DEFAULT_METRICS_HORIZON: u64 = 128;
+
+/// Per-arrival sample kept while it falls inside the metrics window.
+#[derive(Debug, Clone, Copy)]
+struct FrameSample {
+    sequence: u64,
+    lost_before: u64,
+    late: bool,
 }
 
 /// Determines the network conditions for an ALPINE streaming session.
+///
+/// Metrics cover a sliding window of the most recent `horizon` sequences, so a
+/// past burst stops influencing `loss_ratio` and `max_loss_gap` once the
+/// stream has moved past it and recovery or degraded-safe can exit. Jitter and
+/// RTT are the standard smoothed estimators and carry their own decay.
+#[derive(Debug, Clone)]
 pub struct NetworkConditions {
+    horizon: u64,
     last_sequence: Option<u64>,
-    total_expected: u64,
-    observed_frames: u64,
-    lost_frames: u64,
-    late_frames: u64,
+    last_timestamp: Option<u64>,
     last_arrival: Option<u64>,
-    last_interval: Option<u64>,
-    total_jitter_ns: u128,
-    jitter_samples: u64,
-    max_loss_gap: u64,
+    last_transit: Option<i64>,
+    jitter_us: Option<f64>,
+    rtt_us: Option<f64>,
+    samples: VecDeque<FrameSample>,
 }
 
 impl NetworkConditions {
-    /// Creates a fresh tracker.
+    /// Creates a fresh tracker with the [`DEFAULT_METRICS_HORIZON`].
     pub fn new() -> Self {
+        Self::with_horizon(DEFAULT_METRICS_HORIZON)
+    }
+
+    /// Creates a tracker whose metrics cover the last `horizon` sequences (minimum 1).
+    pub fn with_horizon(horizon: u64) -> Self {
         Self {
+            horizon: horizon.max(1),
             last_sequence: None,
-            total_expected: 0,
-            observed_frames: 0,
-            lost_frames: 0,
-            late_frames: 0,
+            last_timestamp: None,
             last_arrival: None,
-            last_interval: None,
-            total_jitter_ns: 0,
-            jitter_samples: 0,
-            max_loss_gap: 0,
+            last_transit: None,
+            jitter_us: None,
+            rtt_us: None,
+            samples: VecDeque::new(),
         }
     }
 
+    /// Number of sequences covered by the metrics window.
+    pub fn horizon(&self) -> u64 {
+        self.horizon
+    }
+
     /// Records an observed frame arrival.
     ///
-    /// The stream encodes `sequence`, `arrival_us`, and the caller-supplied
-    /// `deadline_us` so we can independently reason about lateness, loss, and
-    /// jitter. All calculations are deterministic and rely solely on these
-    /// inputs.
-    pub fn record_frame(&mut self, sequence: u64, arrival_us: u64, deadline_us: u64) {
-        if let Some(last_seq) = self.last_sequence {
-            if sequence <= last_seq {
-                // Out-of-order or duplicate frames do not affect the metrics.
-                return;
-            }
-            let delta = sequence - last_seq;
-            self.total_expected = self.total_expected.saturating_add(delta);
-            if delta > 1 {
-                self.lost_frames = self.lost_frames.saturating_add(delta - 1);
-                self.max_loss_gap = self.max_loss_gap.max(delta - 1);
-            }
-        } else {
-            self.total_expected = self.total_expected.saturating_add(1);
-        }
-
+    /// The stream encodes `sequence`, the sender `timestamp_us`, the local
+    /// `arrival_us`, and the caller-supplied `deadline_us` so we can
+    /// independently reason about lateness, loss, and jitter. All calculations
+    /// are deterministic and rely solely on these inputs; clock offsets between
+    /// sender and receiver cancel out of the jitter estimate.
+    pub fn record_frame(
+        &mut self,
+        sequence: u64,
+        timestamp_us: u64,
+        arrival_us: u64,
+        deadline_us: u64,
+    ) {
+        let lost_before = match self.last_sequence {
+            // Out-of-order or duplicate frames do not affect the metrics.
+            Some(last_seq) if sequence <= last_seq => return,
+            Some(last_seq) => sequence - last_seq - 1,
+            None => 0,
+        };
         self.last_sequence = Some(sequence);
-        self.observed_frames = self.observed_frames.saturating_add(1);
-
-        if arrival_us > deadline_us {
-            self.late_frames = self.late_frames.saturating_add(1);
-        }
 
-        if let Some(last) = self.last_arrival {
-            let interval = arrival_us.saturating_sub(last);
-            if let Some(prev_interval) = self.last_interval {
-                let jitter = if interval > prev_interval {
-                    interval - prev_interval
-                } else {
-                    prev_interval - interval
-                };
-                self.total_jitter_ns = self.total_jitter_ns.saturating_add(jitter as u128);
-                self.jitter_samples = self.jitter_samples.saturating_add(1);
-            }
-            self.last_interval = Some(interval);
+        // RFC 3550 §6.4.1: J += (|D(i-1, i)| - J) / 16.
+        let transit = arrival_us as i64 - timestamp_us as i64;
+        if let Some(last_transit) = self.last_transit {
+            let d = transit.abs_diff(last_transit) as f64;
+            let jitter = self.jitter_us.unwrap_or(0.0);
+            self.jitter_us = Some(jitter + (d - jitter) / 16.0);
         }
+        self.last_transit = Some(transit);
+        self.last_timestamp = Some(timestamp_us);
         self.last_arrival = Some(arrival_us);
+
+        self.samples.push_back(FrameSample {
+            sequence,
+            lost_before,
+            late: arrival_us > deadline_us,
+        });
+        self.evict();
     }
 
-    /// Returns the latest metrics snapshot.
-    pub fn metrics(&self) -> NetworkMetrics {
-        let total_expected = self.total_expected.max(self.observed_frames);
-        let loss_ratio = if total_expected == 0 {
-            0.0
-        } else {
-            self.lost_frames as f64 / total_expected as f64
+    /// Records a frame that arrived behind the stream and missed its playout slot.
+    ///
+    /// `record_frame` ignores out-of-order arrivals, so the playout stage reports
+    /// them here; they count as observed and late without changing loss.
+    pub fn record_late_frame(&mut self) {
+        let Some(sequence) = self.last_sequence else {
+            return;
         };
+        self.samples.push_back(FrameSample {
+            sequence,
+            lost_before: 0,
+            late: true,
+        });
+        self.evict();
+    }
+
+    /// Returns the latest metrics snapshot over the current window.
+    pub fn metrics(&self) -> NetworkMetrics {
+        let observed = self.samples.len() as u64;
+        let lost: u64 = self.samples.iter().map(|sample| sample.lost_before).sum();
+        let late = self.samples.iter().filter(|sample| sample.late).count() as u64;
 
-        let late_frame_rate = if self.observed_frames == 0 {
+        let expected = observed + lost;
+        let loss_ratio = if expected == 0 {
             0.0
         } else {
-            self.late_frames as f64 / self.observed_frames as f64
+            lost as f64 / expected as f64
         };
 
-        let jitter_ms = if self.jitter_samples == 0 {
-            None
+        let late_frame_rate = if observed == 0 {
+            0.0
         } else {
-            Some(self.total_jitter_ns as f64 / self.jitter_samples as f64 / 1000.0)
+            late as f64 / observed as f64
         };
 
         NetworkMetrics {
             loss_ratio,
             late_frame_rate,
-            jitter_ms,
+            jitter_ms: self.jitter_us.map(|jitter| jitter / 1000.0),
+            max_loss_gap: self.max_loss_gap(),
+            rtt_ms: self.rtt_us.map(|rtt| rtt / 1000.0),
         }
     }
 
-    /// Returns the largest sequence gap observed for burst detection.
+    /// Records a round-trip time sample obtained from an echo exchange.
+    ///
+    /// Sender-side only: `AlnpStream` measures RTT itself from the timestamps
+    /// echoed in receiver reports, and a receiver has no echo path, so its
+    /// metrics (and the reports built from them) carry `rtt_ms: None`. Call
+    /// this when feeding [`AlnpStream::observe_network_conditions`] from an
+    /// echo exchange of your own.
+    ///
+    /// [`AlnpStream::observe_network_conditions`]: crate::stream::AlnpStream::observe_network_conditions
+    pub fn record_rtt(&mut self, rtt_us: u64) {
+        self.rtt_us = Some(smooth_rtt(self.rtt_us, rtt_us));
+    }
+
+    /// Sender timestamp and local arrival time of the newest frame, used to
+    /// echo timing back to the sender.
+    pub fn last_echo(&self) -> Option<(u64, u64)> {
+        self.last_timestamp.zip(self.last_arrival)
+    }
+
+    /// Returns the highest sequence recorded so far.
+    pub fn last_sequence(&self) -> Option<u64> {
+        self.last_sequence
+    }
+
+    /// Returns the largest sequence gap inside the window for burst detection.
     pub fn max_loss_gap(&self) -> u64 {
-        self.max_loss_gap
+        self.samples
+            .iter()
+            .map(|sample| sample.lost_before)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Drops samples that fell out of the window. A gap belongs to the frame
+    /// that ended it, so it ages out together with that frame.
+    fn evict(&mut self) {
+        let Some(last) = self.last_sequence else {
+            return;
+        };
+        let oldest = last.saturating_sub(self.horizon - 1);
+        while self
+            .samples
+            .front()
+            .is_some_and(|sample| sample.sequence < oldest)
+        {
+            self.samples.pop_front();
+        }
+    }
+}
+
+/// RFC 6298 smoothed RTT update (gain 1/8); the first sample seeds the estimate.
+pub(crate) fn smooth_rtt(srtt_us: Option<f64>, sample_us: u64) -> f64 {
+    let sample = sample_us as f64;
+    match srtt_us {
+        Some(srtt) => srtt + (sample - srtt) / 8.0,
+        None => sample,
+    }
+}
+
+impl Default for NetworkConditions {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -134,9 +237,9 @@ mod tests {
     #[test]
     fn loss_ratio_accounts_for_missing_sequences() {
         let mut net = NetworkConditions::new();
-        net.record_frame(1, 0, 1);
-        net.record_frame(2, 1_000, 2_000);
-        net.record_frame(4, 3_000, 4_000);
+        net.record_frame(1, 0, 0, 1);
+        net.record_frame(2, 1_000, 1_000, 2_000);
+        net.record_frame(4, 3_000, 3_000, 4_000);
         let metrics = net.metrics();
         assert!((metrics.loss_ratio - (1.0 / 4.0)).abs() < f64::EPSILON);
     }
@@ -144,22 +247,80 @@ mod tests {
     #[test]
     fn late_frame_rate_counts_deadlines() {
         let mut net = NetworkConditions::new();
-        net.record_frame(1, 0, 0);
-        net.record_frame(2, 5_000, 3_000);
-        net.record_frame(3, 6_000, 6_000);
+        net.record_frame(1, 0, 0, 0);
+        net.record_frame(2, 5_000, 5_000, 3_000);
+        net.record_frame(3, 6_000, 6_000, 6_000);
         let metrics = net.metrics();
         assert!((metrics.late_frame_rate - (1.0 / 3.0)).abs() < f64::EPSILON);
     }
 
     #[test]
-    fn jitter_ms_average() {
+    fn late_playout_frames_raise_late_rate() {
+        let mut net = NetworkConditions::new();
+        net.record_frame(1, 0, 0, 1_000);
+        net.record_frame(3, 1_000, 1_000, 2_000);
+        net.record_late_frame();
+        let metrics = net.metrics();
+        assert!((metrics.late_frame_rate - (1.0 / 3.0)).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn late_playout_frames_age_out_of_window() {
+        let mut net = NetworkConditions::with_horizon(4);
+        net.record_frame(1, 0, 0, 1_000);
+        net.record_late_frame();
+        assert!((net.metrics().late_frame_rate - 0.5).abs() < f64::EPSILON);
+        for seq in 2..=5 {
+            net.record_frame(seq, seq * 1_000, seq * 1_000, seq * 1_000);
+        }
+        assert_eq!(net.metrics().late_frame_rate, 0.0);
+    }
+
+    #[test]
+    fn jitter_follows_rfc3550() {
+        let mut net = NetworkConditions::new();
+        net.record_frame(1, 0, 0, 0);
+        assert_eq!(net.metrics().jitter_ms, None);
+        net.record_frame(2, 1_000, 1_000, 2_000);
+        net.record_frame(3, 2_000, 2_500, 4_000);
+        net.record_frame(4, 3_000, 3_900, 5_000);
+        let metrics = net.metrics();
+        // transits: 0, 0, 500, 900 -> |D|: 0, 500, 400
+        // J: 0 -> 31.25 -> 31.25 + (400 - 31.25) / 16 = 54.296875 µs
+        assert_eq!(metrics.jitter_ms, Some(0.054296875));
+    }
+
+    #[test]
+    fn jitter_ignores_constant_clock_offset() {
+        let mut net = NetworkConditions::new();
+        for seq in 1..=5u64 {
+            net.record_frame(seq, seq * 1_000, 7_000_000 + seq * 1_000, u64::MAX);
+        }
+        assert_eq!(net.metrics().jitter_ms, Some(0.0));
+    }
+
+    #[test]
+    fn rtt_is_smoothed() {
         let mut net = NetworkConditions::new();
-        net.record_frame(1, 0, 0);
-        net.record_frame(2, 1_000, 2_000);
-        net.record_frame(3, 2_500, 4_000);
-        net.record_frame(4, 3_900, 5_000);
+        assert_eq!(net.metrics().rtt_ms, None);
+        net.record_rtt(8_000);
+        net.record_rtt(16_000);
+        assert_eq!(net.metrics().rtt_ms, Some(9.0));
+    }
+
+    #[test]
+    fn burst_ages_out_of_window() {
+        let mut net = NetworkConditions::with_horizon(8);
+        net.record_frame(1, 0, 0, 1_000);
+        net.record_frame(6, 1_000, 1_000, 2_000);
+        assert_eq!(net.max_loss_gap(), 4);
+        for seq in 7..=13 {
+            net.record_frame(seq, seq * 1_000, seq * 1_000, seq * 1_000);
+        }
+        assert_eq!(net.max_loss_gap(), 4);
+        net.record_frame(14, 14_000, 14_000, 14_000);
         let metrics = net.metrics();
-        // intervals: 1000, 1500, 1400 -> diffs: 500, 100 -> avg = 300 µs => 0.3 ms
-        assert_eq!(metrics.jitter_ms, Some(0.3));
+        assert_eq!(metrics.max_loss_gap, 0);
+        assert_eq!(metrics.loss_ratio, 0.0);
     }
 }
@@ -0,0 +1,264 @@
+//! Fixed-rate frame pacing for `AlnpStream`.
+//!
+//! Render loops rarely produce frames on a steady clock, and calling
+//! `AlnpStream::send` from them turns every burst of updates into a burst of
+//! datagrams. A [`FramePacer`] takes ownership of the stream and sends from a
+//! tokio task on a fixed tick instead: updates between two ticks coalesce into
+//! the latest state, a tick without a new update sends nothing, and once the
+//! state has been idle for the refresh interval it is resent as a keyframe so
+//! receivers that missed frames resynchronize. The rate is capped by the
+//! peer's advertised `max_frame_rate_hz`.
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::time::{self, Instant, MissedTickBehavior};
+use tracing::{debug, warn};
+
+use crate::messages::{ChannelBuffer, ChannelFormat};
+use crate::stream::{AlnpStream, FrameBuf, FrameTransport, StreamError};
+
+/// Default time without updates after which the latest state is resent.
+pub const DEFAULT_IDLE_REFRESH: Duration = Duration::from_secs(1);
+
+/// Counters describing what a pacer has sent so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PacerStats {
+    /// Frames sent, including idle refreshes and retries.
+    pub frames_sent: u64,
+    /// Updates replaced by a newer one before their tick came.
+    pub updates_coalesced: u64,
+    /// Keyframes resent because the state was idle.
+    pub idle_refreshes: u64,
+    /// Sends that failed; the state is resent as a keyframe on the next tick.
+    pub send_errors: u64,
+}
+
+/// Latest state handed to the pacer, shared with its task.
+#[derive(Debug)]
+struct Latest {
+    channels: ChannelBuffer,
+    dirty: bool,
+    stats: PacerStats,
+}
+
+/// Builder for a fixed-rate sender that owns an [`AlnpStream`].
+#[derive(Debug)]
+pub struct FramePacer<T: FrameTransport> {
+    stream: AlnpStream<T>,
+    rate_hz: u16,
+    idle_refresh: Duration,
+    priority: u8,
+}
+
+impl<T: FrameTransport + 'static> FramePacer<T> {
+    /// Paces `stream` at `rate_hz` frames per second (e.g. 44, 60 or 120).
+    pub fn new(stream: AlnpStream<T>, rate_hz: u16) -> Self {
+        Self {
+            stream,
+            rate_hz,
+            idle_refresh: DEFAULT_IDLE_REFRESH,
+            priority: 0,
+        }
+    }
+
+    /// Resends the latest state as a keyframe after `interval` without updates.
+    ///
+    /// Defaults to [`DEFAULT_IDLE_REFRESH`].
+    pub fn with_idle_refresh(mut self, interval: Duration) -> Self {
+        self.idle_refresh = interval;
+        self
+    }
+
+    /// Priority stamped on every paced frame; defaults to 0.
+    pub fn with_priority(mut self, priority: u8) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Rate the pacer will run at: the requested rate capped by the peer's
+    /// advertised `max_frame_rate_hz`.
+    pub fn effective_rate_hz(&self) -> u16 {
+        let peer_max = self
+            .stream
+            .session
+            .established()
+            .and_then(|established| established.capabilities.max_frame_rate_hz);
+        match peer_max {
+            Some(max) => self.rate_hz.min(max),
+            None => self.rate_hz,
+        }
+    }
+
+    /// Starts the pacing task on the current tokio runtime.
+    ///
+    /// # Guarantees
+    /// * Sends at most one frame per tick of the effective rate, always with
+    ///   the latest state passed to [`PacerHandle::update`]; older pending
+    ///   states are dropped and counted as coalesced.
+    /// * Sends nothing before the first update or on ticks without an update,
+    ///   except the idle refresh, which resends the latest state as a
+    ///   keyframe once per idle interval.
+    /// * Never sends faster than the peer's `max_frame_rate_hz`; fails with
+    ///   [`StreamError::ZeroFrameRate`] when the effective rate is zero.
+    ///
+    /// # Panics
+    /// Panics when called outside a tokio runtime.
+    pub fn spawn(self) -> Result<PacerHandle<T>, StreamError> {
+        let rate_hz = self.effective_rate_hz();
+        if rate_hz == 0 {
+            return Err(StreamError::ZeroFrameRate);
+        }
+        if rate_hz < self.rate_hz {
+            debug!(
+                target: "alpine::stream",
+                requested = self.rate_hz,
+                rate_hz,
+                "frame rate capped by peer capabilities"
+            );
+        }
+        let stream = Arc::new(self.stream);
+        let latest = Arc::new(parking_lot::Mutex::new(Latest {
+            channels: ChannelBuffer::default(),
+            dirty: false,
+            stats: PacerStats::default(),
+        }));
+        let (stop, stopped) = oneshot::channel();
+        let task = tokio::spawn(run(
+            stream.clone(),
+            latest.clone(),
+            Duration::from_nanos(1_000_000_000 / rate_hz as u64),
+            self.idle_refresh,
+            self.priority,
+            stopped,
+        ));
+        Ok(PacerHandle {
+            stream,
+            latest,
+            rate_hz,
+            stop: Some(stop),
+            task: Some(task),
+        })
+    }
+}
+
+/// Handle to a running [`FramePacer`]; dropping it stops the pacer.
+#[derive(Debug)]
+pub struct PacerHandle<T: FrameTransport> {
+    stream: Arc<AlnpStream<T>>,
+    latest: Arc<parking_lot::Mutex<Latest>>,
+    rate_hz: u16,
+    stop: Option<oneshot::Sender<()>>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl<T: FrameTransport> PacerHandle<T> {
+    /// Replaces the state sent on the next tick.
+    ///
+    /// Never blocks on the network; the values are copied into a buffer the
+    /// pacer reuses.
+    pub fn update(&self, channels: &ChannelBuffer) {
+        let mut latest = self.latest.lock();
+        if latest.dirty {
+            latest.stats.updates_coalesced += 1;
+        }
+        latest.channels.copy_from(channels);
+        latest.dirty = true;
+    }
+
+    /// The paced stream, for receiver reports, keyframe requests and events.
+    pub fn stream(&self) -> &AlnpStream<T> {
+        &self.stream
+    }
+
+    /// Rate the pacer runs at, after capping by the peer's capabilities.
+    pub fn rate_hz(&self) -> u16 {
+        self.rate_hz
+    }
+
+    /// Counters for what the pacer has sent so far.
+    pub fn stats(&self) -> PacerStats {
+        self.latest.lock().stats
+    }
+
+    /// Stops pacing and returns the stream; an update still pending is not sent.
+    pub async fn stop(mut self) -> AlnpStream<T> {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        if let Some(task) = self.task.take() {
+            let _ = task.await;
+        }
+        let stream = self.stream.clone();
+        drop(self);
+        match Arc::try_unwrap(stream) {
+            Ok(stream) => stream,
+            Err(_) => unreachable!("the finished pacer task released the stream"),
+        }
+    }
+}
+
+impl<T: FrameTransport> Drop for PacerHandle<T> {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+}
+
+async fn run<T: FrameTransport>(
+    stream: Arc<AlnpStream<T>>,
+    latest: Arc<parking_lot::Mutex<Latest>>,
+    period: Duration,
+    idle_refresh: Duration,
+    priority: u8,
+    mut stopped: oneshot::Receiver<()>,
+) {
+    let mut ticker = time::interval(period);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let mut buf = FrameBuf::new(ChannelFormat::U8);
+    let mut last_sent: Option<Instant> = None;
+    let mut retry = false;
+    loop {
+        tokio::select! {
+            _ = &mut stopped => return,
+            _ = ticker.tick() => {}
+        }
+        let now = Instant::now();
+        let refresh = {
+            let mut latest = latest.lock();
+            if latest.dirty {
+                latest.dirty = false;
+                buf.channels_mut().copy_from(&latest.channels);
+                false
+            } else if retry
+                || last_sent.is_some_and(|sent| now.duration_since(sent) >= idle_refresh)
+            {
+                true
+            } else {
+                continue;
+            }
+        };
+        if refresh {
+            stream.schedule_keyframe();
+        }
+        let result = stream.send_with(&mut buf, priority);
+        last_sent = Some(now);
+        let mut latest = latest.lock();
+        match result {
+            Ok(()) => {
+                latest.stats.frames_sent += 1;
+                if refresh && !retry {
+                    latest.stats.idle_refreshes += 1;
+                }
+                retry = false;
+            }
+            Err(err) => {
+                warn!(target: "alpine::stream", "paced send failed: {}", err);
+                latest.stats.send_errors += 1;
+                retry = true;
+            }
+        }
+    }
+}
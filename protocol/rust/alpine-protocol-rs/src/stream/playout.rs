@@ -0,0 +1,195 @@
+//! Receiver-side jitter buffer and playout scheduling.
+//!
+//! Frames are held until their target time (`timestamp_us` plus the
+//! profile-bound delivery deadline and any adaptive deadline offset) instead of
+//! being output on arrival, so output timing follows the sender's clock rather
+//! than network jitter. Frames are released strictly in sequence order; a frame
+//! that arrives after a later frame has already been played is reported late
+//! and dropped, and the buffer never holds more than the reorder window.
+use std::collections::BTreeMap;
+
+use crate::messages::FrameEnvelope;
+
+/// Default number of frames held for reordering before the oldest is forced out.
+pub const DEFAULT_REORDER_WINDOW: usize = 4;
+
+/// Result of handing a frame to the playout buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayoutPush {
+    /// Frame is queued for its target time.
+    Scheduled,
+    /// A later frame already played; the frame was dropped.
+    Late,
+    /// A frame with the same sequence is already queued, or an authenticated
+    /// copy arrived on another redundant path.
+    Duplicate,
+    /// A fragment was buffered; its frame is not complete yet.
+    Incomplete,
+    /// A parity datagram arrived but no frame of its group needed rebuilding.
+    ParityUnused,
+}
+
+#[derive(Debug)]
+struct ScheduledFrame {
+    target_us: u64,
+    frame: FrameEnvelope,
+}
+
+/// Sequence-ordered playout queue keyed by frame target times.
+#[derive(Debug)]
+pub struct PlayoutBuffer {
+    frames: BTreeMap<u64, ScheduledFrame>,
+    last_played: Option<u64>,
+    reorder_window: usize,
+}
+
+impl PlayoutBuffer {
+    /// Creates a buffer that reorders within `reorder_window` frames (minimum 1).
+    pub fn new(reorder_window: usize) -> Self {
+        Self {
+            frames: BTreeMap::new(),
+            last_played: None,
+            reorder_window: reorder_window.max(1),
+        }
+    }
+
+    /// Queues `frame` for playout at `target_us`.
+    pub fn push(&mut self, frame: FrameEnvelope, target_us: u64) -> PlayoutPush {
+        if self
+            .last_played
+            .is_some_and(|played| frame.sequence <= played)
+        {
+            return PlayoutPush::Late;
+        }
+        if self.frames.contains_key(&frame.sequence) {
+            return PlayoutPush::Duplicate;
+        }
+        self.frames
+            .insert(frame.sequence, ScheduledFrame { target_us, frame });
+        PlayoutPush::Scheduled
+    }
+
+    /// Releases the next frame in sequence order once its target time is reached.
+    ///
+    /// When more than `reorder_window` frames are queued the oldest frame is
+    /// released immediately so the buffer cannot grow without bound.
+    pub fn pop_due(&mut self, now_us: u64) -> Option<FrameEnvelope> {
+        let target_us = self.next_due_us()?;
+        self.pop_released(now_us, target_us)
+    }
+
+    /// Releases the next frame in sequence order once `release_us` is reached,
+    /// or immediately when the reorder window overflows.
+    pub(crate) fn pop_released(&mut self, now_us: u64, release_us: u64) -> Option<FrameEnvelope> {
+        let (&sequence, _) = self.frames.iter().next()?;
+        if release_us > now_us && self.frames.len() <= self.reorder_window {
+            return None;
+        }
+        let scheduled = self.frames.remove(&sequence)?;
+        self.last_played = Some(sequence);
+        Some(scheduled.frame)
+    }
+
+    /// Next frame in sequence order, with its target time.
+    pub(crate) fn peek(&self) -> Option<(&FrameEnvelope, u64)> {
+        self.frames
+            .values()
+            .next()
+            .map(|scheduled| (&scheduled.frame, scheduled.target_us))
+    }
+
+    /// Target time of the next frame in sequence order, for timer scheduling.
+    pub fn next_due_us(&self) -> Option<u64> {
+        self.frames
+            .values()
+            .next()
+            .map(|scheduled| scheduled.target_us)
+    }
+
+    /// Sequence of the most recently released frame.
+    pub fn last_played(&self) -> Option<u64> {
+        self.last_played
+    }
+
+    /// Number of frames waiting for playout.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns `true` when no frames are waiting.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+}
+
+impl Default for PlayoutBuffer {
+    fn default() -> Self {
+        Self::new(DEFAULT_REORDER_WINDOW)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::{ChannelBuffer, FrameKind, MessageType};
+    use uuid::Uuid;
+
+    fn frame(sequence: u64) -> FrameEnvelope {
+        FrameEnvelope {
+            message_type: MessageType::AlpineFrame,
+            session_id: Uuid::nil(),
+            stream_id: 0,
+            epoch: 0,
+            sequence,
+            timestamp_us: sequence * 1_000,
+            priority: 0,
+            channels: ChannelBuffer::U8(vec![sequence as u8]),
+            groups: None,
+            metadata: None,
+            adaptation: None,
+            kind: FrameKind::Keyframe,
+            base_sequence: None,
+            delta: None,
+            ciphertext: None,
+            mac: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn holds_frames_until_target_time() {
+        let mut buffer = PlayoutBuffer::default();
+        assert_eq!(buffer.push(frame(1), 5_000), PlayoutPush::Scheduled);
+        assert!(buffer.pop_due(4_999).is_none());
+        assert_eq!(buffer.next_due_us(), Some(5_000));
+        assert_eq!(buffer.pop_due(5_000).unwrap().sequence, 1);
+    }
+
+    #[test]
+    fn reorders_within_window() {
+        let mut buffer = PlayoutBuffer::default();
+        buffer.push(frame(3), 3_000);
+        buffer.push(frame(2), 2_000);
+        assert_eq!(buffer.pop_due(10_000).unwrap().sequence, 2);
+        assert_eq!(buffer.pop_due(10_000).unwrap().sequence, 3);
+    }
+
+    #[test]
+    fn frames_behind_playout_are_late() {
+        let mut buffer = PlayoutBuffer::default();
+        buffer.push(frame(5), 5_000);
+        buffer.pop_due(5_000).unwrap();
+        assert_eq!(buffer.push(frame(4), 4_000), PlayoutPush::Late);
+        assert_eq!(buffer.push(frame(6), 6_000), PlayoutPush::Scheduled);
+        assert_eq!(buffer.push(frame(6), 6_000), PlayoutPush::Duplicate);
+    }
+
+    #[test]
+    fn overflow_forces_oldest_frame_out() {
+        let mut buffer = PlayoutBuffer::new(2);
+        for seq in 1..=3 {
+            buffer.push(frame(seq), 100_000);
+        }
+        assert_eq!(buffer.pop_due(0).unwrap().sequence, 1);
+        assert!(buffer.pop_due(0).is_none());
+    }
+}
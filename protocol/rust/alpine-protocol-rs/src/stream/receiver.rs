@@ -0,0 +1,923 @@
+//! Node-side receive path for ALPINE streaming.
+//!
+//! `AlnpStreamReceiver` decodes inbound frame datagrams, rejects anything that
+//! does not belong to the bound session or fails authentication, and records
+//! every accepted arrival in
+//! a per-session `NetworkConditions` tracker. This gives the Phase 3 loss,
+//! jitter, and recovery machinery real input on the device side.
+//!
+//! Callers that want smooth output instead of arrival-driven output use
+//! [`AlnpStreamReceiver::enqueue`] together with
+//! [`AlnpStreamReceiver::poll_playout`]; frames are then held in a
+//! [`PlayoutBuffer`] until their target time and released in sequence order,
+//! with sequence gaps filled by the session's `JitterStrategy`.
+//!
+//! Nodes close the adaptation loop by sending authenticated `ReceiverReport`s
+//! back to the controller, either on demand via [`AlnpStreamReceiver::report`]
+//! or periodically through a feedback transport
+//! ([`AlnpStreamReceiver::with_feedback`]).
+//!
+//! Frames the sender split into `FrameFragment`s are authenticated per
+//! fragment, reassembled, and then accepted like any other frame datagram.
+//! When the profile enables FEC, a `FrameParity` datagram rebuilds a single
+//! lost frame of its group, which is then authenticated the same way.
+//!
+//! A receiver fed by several redundant network paths
+//! ([`AlnpStreamReceiver::with_paths`]) merges them by sequence: the first
+//! authenticated copy of a frame is used, later copies are reported as
+//! duplicates, and every path keeps its own loss metrics.
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::Duration;
+
+use tracing::{debug, warn};
+
+use crate::messages::{
+    CapabilitySet, FrameEnvelope, FrameFragment, FrameKind, FrameParity, KeyframeRequest,
+    MessageType, ReceiverReport,
+};
+use crate::profile::CompiledStreamProfile;
+use crate::session::AlnpSession;
+use crate::stream::auth::{encode_signed, open_frame, open_signed};
+use crate::stream::delta::DeltaDecoder;
+use crate::stream::epoch::{new_epoch, EpochKeys, PeerEpochs};
+use crate::stream::fec::ParityDecoder;
+use crate::stream::fragment::{Reassembler, DEFAULT_REASSEMBLY_TIMEOUT};
+use crate::stream::groups::ChannelGroups;
+use crate::stream::jitter::{JitterConcealer, PlayoutFrame};
+use crate::stream::network::{NetworkConditions, NetworkMetrics, DEFAULT_METRICS_HORIZON};
+use crate::stream::playout::{PlayoutBuffer, PlayoutPush, DEFAULT_REORDER_WINDOW};
+use crate::stream::replay::ReplayWindow;
+use crate::stream::{
+    check_capabilities, now_us, FrameTransport, StreamError, KEYFRAME_REQUEST_INTERVAL,
+};
+
+/// Default spacing between periodic receiver reports.
+pub const DEFAULT_REPORT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Feedback channel used for periodic receiver reports.
+struct Feedback {
+    transport: Box<dyn FrameTransport>,
+    interval_us: u64,
+    next_report_us: Option<u64>,
+    last_keyframe_request_us: Option<u64>,
+}
+
+impl fmt::Debug for Feedback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Feedback")
+            .field("interval_us", &self.interval_us)
+            .field("next_report_us", &self.next_report_us)
+            .field("last_keyframe_request_us", &self.last_keyframe_request_us)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Outcome of accepting one datagram; short-lived, so the frame is not boxed.
+#[allow(clippy::large_enum_variant)]
+enum Accepted {
+    Frame(FrameEnvelope),
+    /// A fragment was buffered; its frame is not complete yet.
+    Fragment,
+    /// A parity datagram had no lost frame to rebuild.
+    Parity,
+    /// An authenticated copy of a frame already received on another path.
+    Duplicate(u64),
+}
+
+/// Receive state kept separately for each redundant network path.
+#[derive(Debug)]
+struct PathState {
+    reassembly: Reassembler,
+    conditions: NetworkConditions,
+}
+
+fn build_paths(
+    count: usize,
+    horizon: u64,
+    timeout: Duration,
+) -> Vec<parking_lot::Mutex<PathState>> {
+    (0..count.max(1))
+        .map(|_| {
+            parking_lot::Mutex::new(PathState {
+                reassembly: Reassembler::new(timeout),
+                conditions: NetworkConditions::with_horizon(horizon),
+            })
+        })
+        .collect()
+}
+
+/// Receiver state machine used by nodes to consume streaming frames.
+#[derive(Debug)]
+pub struct AlnpStreamReceiver {
+    session: AlnpSession,
+    capabilities: Option<CapabilitySet>,
+    stream_id: u8,
+    epoch: u64,
+    keys: parking_lot::Mutex<EpochKeys>,
+    sender_epochs: parking_lot::Mutex<PeerEpochs>,
+    profile: CompiledStreamProfile,
+    conditions: parking_lot::Mutex<NetworkConditions>,
+    replay: parking_lot::Mutex<ReplayWindow>,
+    paths: Vec<parking_lot::Mutex<PathState>>,
+    reassembly_timeout: Duration,
+    parity: Option<parking_lot::Mutex<ParityDecoder>>,
+    decoder: parking_lot::Mutex<DeltaDecoder>,
+    groups: parking_lot::Mutex<ChannelGroups>,
+    playout: parking_lot::Mutex<PlayoutBuffer>,
+    reorder_window: usize,
+    concealer: parking_lot::Mutex<JitterConcealer>,
+    output: parking_lot::Mutex<VecDeque<(u64, PlayoutFrame)>>,
+    report_sequence: parking_lot::Mutex<u64>,
+    request_sequence: parking_lot::Mutex<u64>,
+    feedback: Option<parking_lot::Mutex<Feedback>>,
+}
+
+impl AlnpStreamReceiver {
+    /// Builds a receiver bound to an established session and compiled profile.
+    pub fn new(session: AlnpSession, profile: CompiledStreamProfile) -> Self {
+        Self::with_reorder_window(session, profile, DEFAULT_REORDER_WINDOW)
+    }
+
+    /// Builds a receiver whose playout buffer reorders within `reorder_window` frames.
+    pub fn with_reorder_window(
+        session: AlnpSession,
+        profile: CompiledStreamProfile,
+        reorder_window: usize,
+    ) -> Self {
+        let parity = (profile.fec_group_size() > 0)
+            .then(|| parking_lot::Mutex::new(ParityDecoder::default()));
+        Self {
+            capabilities: session.local_capabilities(),
+            epoch: new_epoch(&session),
+            session,
+            stream_id: 0,
+            keys: parking_lot::Mutex::new(EpochKeys::default()),
+            sender_epochs: parking_lot::Mutex::new(PeerEpochs::default()),
+            profile,
+            conditions: parking_lot::Mutex::new(NetworkConditions::new()),
+            replay: parking_lot::Mutex::new(ReplayWindow::new()),
+            paths: build_paths(1, DEFAULT_METRICS_HORIZON, DEFAULT_REASSEMBLY_TIMEOUT),
+            reassembly_timeout: DEFAULT_REASSEMBLY_TIMEOUT,
+            parity,
+            decoder: parking_lot::Mutex::new(DeltaDecoder::default()),
+            groups: parking_lot::Mutex::new(ChannelGroups::new()),
+            playout: parking_lot::Mutex::new(PlayoutBuffer::new(reorder_window)),
+            reorder_window,
+            concealer: parking_lot::Mutex::new(JitterConcealer::default()),
+            output: parking_lot::Mutex::new(VecDeque::new()),
+            report_sequence: parking_lot::Mutex::new(0),
+            request_sequence: parking_lot::Mutex::new(0),
+            feedback: None,
+        }
+    }
+
+    /// Accepts only logical stream `stream_id` of the session; defaults to 0.
+    ///
+    /// Frames, fragments and parity of other streams fail with
+    /// [`StreamError::StreamMismatch`]; route them with a
+    /// [`StreamDemux`](crate::stream::StreamDemux) instead. Reports and
+    /// keyframe requests carry the same id.
+    pub fn with_stream_id(mut self, stream_id: u8) -> Self {
+        self.stream_id = stream_id;
+        self
+    }
+
+    /// Logical stream this receiver accepts.
+    pub fn stream_id(&self) -> u8 {
+        self.stream_id
+    }
+
+    /// Identifier stamped on this receiver's reports and keyframe requests,
+    /// which are sealed with a key derived for it; greater than the epoch of
+    /// every receiver created on the session before it.
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Computes metrics over the last `horizon` sequences instead of the
+    /// [`DEFAULT_METRICS_HORIZON`](crate::stream::DEFAULT_METRICS_HORIZON).
+    pub fn with_metrics_horizon(mut self, horizon: u64) -> Self {
+        self.conditions = parking_lot::Mutex::new(NetworkConditions::with_horizon(horizon));
+        self.paths = build_paths(self.paths.len(), horizon, self.reassembly_timeout);
+        self
+    }
+
+    /// Waits up to `timeout` for the remaining fragments of a fragmented frame
+    /// instead of [`DEFAULT_REASSEMBLY_TIMEOUT`].
+    pub fn with_reassembly_timeout(mut self, timeout: Duration) -> Self {
+        let horizon = self.conditions.get_mut().horizon();
+        self.reassembly_timeout = timeout;
+        self.paths = build_paths(self.paths.len(), horizon, timeout);
+        self
+    }
+
+    /// Merges `count` redundant network paths carrying the same stream.
+    ///
+    /// Feed each path's datagrams through [`AlnpStreamReceiver::receive_on`] or
+    /// [`AlnpStreamReceiver::enqueue_on`]. The first authenticated copy of each
+    /// sequence is used; later copies are authenticated, counted for their
+    /// path, and reported as duplicates instead of replays. Fragments are
+    /// reassembled per path.
+    pub fn with_paths(mut self, count: usize) -> Self {
+        let horizon = self.conditions.get_mut().horizon();
+        self.paths = build_paths(count, horizon, self.reassembly_timeout);
+        self
+    }
+
+    /// Sends a receiver report over `transport` every `interval` of received traffic.
+    ///
+    /// Reports are emitted from the receive path once the interval has elapsed
+    /// since the previous report. The same transport carries keyframe requests
+    /// whenever a delta cannot be reconstructed (at most one per
+    /// [`KEYFRAME_REQUEST_INTERVAL`] until a keyframe arrives). Transport failures
+    /// are logged and never fail frame reception.
+    pub fn with_feedback(
+        mut self,
+        transport: impl FrameTransport + 'static,
+        interval: Duration,
+    ) -> Self {
+        self.feedback = Some(parking_lot::Mutex::new(Feedback {
+            transport: Box::new(transport),
+            interval_us: interval.as_micros() as u64,
+            next_report_us: None,
+            last_keyframe_request_us: None,
+        }));
+        self
+    }
+
+    /// Installs the channel groups received through `ControlOp::DefineGroups`,
+    /// replacing earlier definitions.
+    pub fn define_groups(&self, groups: ChannelGroups) {
+        *self.groups.lock() = groups;
+    }
+
+    /// Builds an authenticated receiver report from the current metrics.
+    ///
+    /// # Guarantees
+    /// * Carries loss ratio, late rate, jitter, max loss gap, and the last
+    ///   sequence seen.
+    /// * Echoes the newest frame's `timestamp_us` and how long it was held
+    ///   before the report, so the controller can measure round-trip time.
+    /// * Each report gets the next `report_sequence` and a tag keyed by this
+    ///   receiver's [`epoch`](AlnpStreamReceiver::epoch) key, so the controller
+    ///   can reject forged or replayed reports.
+    pub fn report(&self) -> Result<Vec<u8>, StreamError> {
+        self.report_at(now_us())
+    }
+
+    fn report_at(&self, now_us: u64) -> Result<Vec<u8>, StreamError> {
+        let established = self
+            .session
+            .ensure_streaming_ready()
+            .map_err(|_| StreamError::NotAuthenticated)?;
+        let keys = self
+            .keys
+            .lock()
+            .get(&self.session, self.stream_id, self.epoch)?;
+        let (metrics, last_sequence, echo) = {
+            let conditions = self.conditions.lock();
+            (
+                conditions.metrics(),
+                conditions.last_sequence(),
+                conditions.last_echo(),
+            )
+        };
+        let (echo_timestamp_us, echo_delay_us) = echo
+            .map(|(timestamp_us, arrival_us)| (timestamp_us, now_us.saturating_sub(arrival_us)))
+            .unwrap_or((0, 0));
+        let report_sequence = {
+            let mut sequence = self.report_sequence.lock();
+            *sequence = sequence.wrapping_add(1);
+            *sequence
+        };
+        let mut report = ReceiverReport {
+            message_type: MessageType::AlpineReceiverReport,
+            session_id: established.session_id,
+            stream_id: self.stream_id,
+            epoch: self.epoch,
+            report_sequence,
+            timestamp_us: now_us,
+            loss_ratio: metrics.loss_ratio,
+            late_frame_rate: metrics.late_frame_rate,
+            jitter_ms: metrics.jitter_ms,
+            max_loss_gap: metrics.max_loss_gap,
+            last_sequence: last_sequence.unwrap_or(0),
+            echo_timestamp_us,
+            echo_delay_us,
+            path_loss_ratios: self.path_loss_ratios(),
+            mac: Vec::new(),
+        };
+        encode_signed(&keys, &mut report)
+    }
+
+    /// Decodes a frame datagram that arrived now.
+    ///
+    /// See [`AlnpStreamReceiver::receive_at`] for the guarantees.
+    pub fn receive(&self, bytes: &[u8]) -> Result<FrameEnvelope, StreamError> {
+        self.receive_at(bytes, now_us())
+    }
+
+    /// Decodes a frame datagram using an explicit arrival timestamp.
+    ///
+    /// # Guarantees
+    /// * Only accepts frames while the session is authenticated and streaming-enabled.
+    /// * Rejects frames whose `session_id` does not match the established session,
+    ///   and frames of other streams with [`StreamError::StreamMismatch`].
+    /// * Rejects forged, replayed, or stale frames with
+    ///   [`StreamError::SessionMacMismatch`]; only authenticated frames advance
+    ///   the replay window.
+    /// * Follows a sender recreated on the session: once a frame, fragment or
+    ///   parity datagram from a newer `epoch` authenticates, replay, delta,
+    ///   playout and metrics state start over with it. Datagrams from older
+    ///   epochs, including ones this receiver never saw, fail with
+    ///   [`StreamError::SessionMacMismatch`] and never replace the current
+    ///   epoch.
+    /// * Decrypts encrypted payloads; when the profile asks for confidentiality and
+    ///   encryption was negotiated, cleartext frames are rejected with
+    ///   [`StreamError::EncryptionRequired`].
+    /// * Records accepted frames in `NetworkConditions` with a deadline of
+    ///   `timestamp_us` plus the profile's delivery budget and the sender's
+    ///   adaptive deadline offset.
+    /// * Returns the fully reconstructed channel vector for both keyframes and
+    ///   deltas; a delta whose base was lost fails with
+    ///   [`StreamError::DeltaBaseMissing`] after it has been counted as received.
+    /// * Rejects authenticated frames this node did not negotiate, using the
+    ///   capabilities it advertised: unlisted formats fail with
+    ///   [`StreamError::UnsupportedChannelFormat`], keyframes above
+    ///   `max_channels` with [`StreamError::TooManyChannels`], and `groups`
+    ///   without `grouping_supported` with [`StreamError::GroupingUnsupported`].
+    /// * Sets the member channels of the frame's `groups` after reconstruction,
+    ///   using the groups from [`AlnpStreamReceiver::define_groups`]; a frame
+    ///   naming an undefined group fails with [`StreamError::UnknownGroup`]
+    ///   and is not used as a delta base.
+    /// * Authenticates fragments individually and buffers them until the frame
+    ///   is complete, returning [`StreamError::FragmentPending`] meanwhile. A
+    ///   frame still missing fragments after the reassembly timeout is dropped
+    ///   and never partially applied.
+    /// * With FEC enabled, returns the frame rebuilt from a parity datagram when
+    ///   exactly one frame of its group was lost, and
+    ///   [`StreamError::ParityUnused`] otherwise. Rebuilt frames pass the same
+    ///   authentication and replay checks and count as received on arrival of
+    ///   the parity.
+    pub fn receive_at(&self, bytes: &[u8], arrival_us: u64) -> Result<FrameEnvelope, StreamError> {
+        self.receive_on_at(0, bytes, arrival_us)
+    }
+
+    /// Decodes a frame datagram that arrived now on redundant `path`.
+    pub fn receive_on(&self, path: usize, bytes: &[u8]) -> Result<FrameEnvelope, StreamError> {
+        self.receive_on_at(path, bytes, now_us())
+    }
+
+    /// Decodes a frame datagram that arrived on redundant `path`.
+    ///
+    /// # Guarantees
+    /// * Same as [`AlnpStreamReceiver::receive_at`] for the first copy of a frame.
+    /// * With several paths configured, an authenticated later copy fails with
+    ///   [`StreamError::DuplicateFrame`] and only updates that path's metrics.
+    /// * Fails with [`StreamError::UnknownPath`] for paths beyond
+    ///   [`AlnpStreamReceiver::with_paths`].
+    pub fn receive_on_at(
+        &self,
+        path: usize,
+        bytes: &[u8],
+        arrival_us: u64,
+    ) -> Result<FrameEnvelope, StreamError> {
+        match self.accept(path, bytes, arrival_us)? {
+            Accepted::Frame(frame) => self.decode(frame),
+            Accepted::Fragment => Err(StreamError::FragmentPending),
+            Accepted::Parity => Err(StreamError::ParityUnused),
+            Accepted::Duplicate(sequence) => Err(StreamError::DuplicateFrame(sequence)),
+        }
+    }
+
+    /// Authenticates a frame that arrived now and queues it for playout.
+    ///
+    /// See [`AlnpStreamReceiver::enqueue_at`] for the guarantees.
+    pub fn enqueue(&self, bytes: &[u8]) -> Result<PlayoutPush, StreamError> {
+        self.enqueue_at(bytes, now_us())
+    }
+
+    /// Authenticates a frame and queues it for playout at its target time.
+    ///
+    /// # Guarantees
+    /// * Applies the same session, authentication, and replay checks as
+    ///   [`AlnpStreamReceiver::receive_at`].
+    /// * The target time is `timestamp_us` plus the profile's delivery budget
+    ///   plus the sender's current adaptive deadline offset.
+    /// * Frames arriving after a later frame was already played are dropped and
+    ///   reported to `NetworkConditions` as late.
+    /// * Channel reconstruction is deferred to [`AlnpStreamReceiver::poll_playout`]
+    ///   so reordered deltas decode against the right base.
+    /// * Fragments are buffered until their frame is complete and reported as
+    ///   [`PlayoutPush::Incomplete`] meanwhile.
+    /// * A frame rebuilt from parity is queued like a received frame; parity
+    ///   with nothing to rebuild is reported as [`PlayoutPush::ParityUnused`].
+    pub fn enqueue_at(&self, bytes: &[u8], arrival_us: u64) -> Result<PlayoutPush, StreamError> {
+        self.enqueue_on_at(0, bytes, arrival_us)
+    }
+
+    /// Queues a frame datagram that arrived now on redundant `path`.
+    pub fn enqueue_on(&self, path: usize, bytes: &[u8]) -> Result<PlayoutPush, StreamError> {
+        self.enqueue_on_at(path, bytes, now_us())
+    }
+
+    /// Queues a frame datagram that arrived on redundant `path`.
+    ///
+    /// Same as [`AlnpStreamReceiver::enqueue_at`], except that an authenticated
+    /// later copy of a frame returns [`PlayoutPush::Duplicate`] when several
+    /// paths are configured.
+    pub fn enqueue_on_at(
+        &self,
+        path: usize,
+        bytes: &[u8],
+        arrival_us: u64,
+    ) -> Result<PlayoutPush, StreamError> {
+        let frame = match self.accept(path, bytes, arrival_us)? {
+            Accepted::Frame(frame) => frame,
+            Accepted::Fragment => return Ok(PlayoutPush::Incomplete),
+            Accepted::Parity => return Ok(PlayoutPush::ParityUnused),
+            Accepted::Duplicate(_) => return Ok(PlayoutPush::Duplicate),
+        };
+        let target_us = self.playout_target_us(&frame);
+        let outcome = self.playout.lock().push(frame, target_us);
+        if outcome == PlayoutPush::Late {
+            self.conditions.lock().record_late_frame();
+        }
+        Ok(outcome)
+    }
+
+    /// Releases the next output frame whose target time is at or before `now_us`.
+    ///
+    /// # Guarantees
+    /// * Returns `Ok(None)` when nothing is due.
+    /// * Output is in sequence order. When a sequence gap is detected, the
+    ///   session's [`JitterStrategy`](crate::session::JitterStrategy) decides
+    ///   what covers it: `HoldLast` repeats the last output, `Lerp` interpolates
+    ///   towards the frame after the gap, and `Drop` emits nothing. Concealed
+    ///   frames precede the received frame that revealed the gap.
+    /// * Each concealed frame is due at the target time of the slot it fills,
+    ///   estimated from the frames on either side of the gap, so concealment
+    ///   keeps the frame cadence instead of arriving in a burst. A frame of a
+    ///   concealed slot that arrives afterwards is late.
+    /// * Received frames are returned unchanged.
+    /// * A released delta whose base never arrived surfaces as
+    ///   [`StreamError::DeltaBaseMissing`]; callers keep polling.
+    pub fn poll_playout(&self, now_us: u64) -> Result<Option<PlayoutFrame>, StreamError> {
+        let mut output = self.output.lock();
+        if let Some(&(due_us, _)) = output.front() {
+            if due_us > now_us {
+                return Ok(None);
+            }
+            return Ok(output.pop_front().map(|(_, frame)| frame));
+        }
+        let strategy = self.session.jitter_strategy();
+        let mut concealer = self.concealer.lock();
+        let (frame, target_us, release_us) = {
+            let mut playout = self.playout.lock();
+            let Some((next, target_us)) = playout.peek() else {
+                return Ok(None);
+            };
+            let release_us = concealer.release_us(strategy, next, target_us);
+            let Some(frame) = playout.pop_released(now_us, release_us) else {
+                return Ok(None);
+            };
+            (frame, target_us, release_us)
+        };
+        let frame = self.decode(frame)?;
+        let concealed = concealer.conceal(strategy, &frame);
+        if concealed.is_empty() {
+            return Ok(Some(PlayoutFrame::Received(frame)));
+        }
+        // A frame forced out by the reorder window flushes its gap right away.
+        let forced = release_us > now_us;
+        let budget_us = target_us.saturating_sub(frame.timestamp_us);
+        let due = |slot_us: u64| if forced { now_us } else { slot_us };
+        output.extend(concealed.into_iter().map(|concealed| {
+            (
+                due(concealed.timestamp_us.saturating_add(budget_us)),
+                PlayoutFrame::Concealed(concealed),
+            )
+        }));
+        output.push_back((due(target_us), PlayoutFrame::Received(frame)));
+        Ok(output.pop_front().map(|(_, frame)| frame))
+    }
+
+    /// Time at which [`AlnpStreamReceiver::poll_playout`] next has output, so
+    /// callers can sleep until it is due.
+    pub fn next_playout_us(&self) -> Option<u64> {
+        let output = self.output.lock();
+        if let Some(&(due_us, _)) = output.front() {
+            return Some(due_us);
+        }
+        let strategy = self.session.jitter_strategy();
+        let concealer = self.concealer.lock();
+        let playout = self.playout.lock();
+        let (next, target_us) = playout.peek()?;
+        Some(concealer.release_us(strategy, next, target_us))
+    }
+
+    /// Authenticates a frame, fragment, or parity datagram.
+    fn accept(&self, path: usize, bytes: &[u8], arrival_us: u64) -> Result<Accepted, StreamError> {
+        if path >= self.paths.len() {
+            return Err(StreamError::UnknownPath(path));
+        }
+        self.session
+            .ensure_streaming_ready()
+            .map_err(|_| StreamError::NotAuthenticated)?;
+        if !self.session.streaming_enabled() {
+            return Err(StreamError::StreamingDisabled);
+        }
+
+        let err = match serde_cbor::from_slice::<FrameEnvelope>(bytes) {
+            Ok(frame) => return self.accept_frame(Some(path), frame, bytes, arrival_us),
+            Err(err) => err,
+        };
+        if let Ok(fragment) = serde_cbor::from_slice::<FrameFragment>(bytes) {
+            let origin = (fragment.epoch, fragment.sequence);
+            return match self.reassemble(path, fragment, arrival_us)? {
+                Some(datagram) => self.accept_rebuilt(Some(path), origin, &datagram, arrival_us),
+                None => Ok(Accepted::Fragment),
+            };
+        }
+        if let Ok(parity) = serde_cbor::from_slice::<FrameParity>(bytes) {
+            let epoch = parity.epoch;
+            // A rebuilt frame did not arrive on any path, so no path is credited.
+            return match self.recover(parity)? {
+                Some((sequence, datagram)) => {
+                    self.accept_rebuilt(None, (epoch, sequence), &datagram, arrival_us)
+                }
+                None => Ok(Accepted::Parity),
+            };
+        }
+        Err(StreamError::Decode(err.to_string()))
+    }
+
+    /// Accepts a frame datagram rebuilt from fragments or parity, which must
+    /// carry the epoch and sequence it was rebuilt for.
+    fn accept_rebuilt(
+        &self,
+        path: Option<usize>,
+        (epoch, sequence): (u64, u64),
+        datagram: &[u8],
+        arrival_us: u64,
+    ) -> Result<Accepted, StreamError> {
+        let frame: FrameEnvelope =
+            serde_cbor::from_slice(datagram).map_err(|e| StreamError::Decode(e.to_string()))?;
+        if frame.epoch != epoch || frame.sequence != sequence {
+            return Err(StreamError::SessionMacMismatch);
+        }
+        self.accept_frame(path, frame, datagram, arrival_us)
+    }
+
+    /// Authenticates a frame datagram and credits `path` with its arrival.
+    fn accept_frame(
+        &self,
+        path: Option<usize>,
+        mut frame: FrameEnvelope,
+        datagram: &[u8],
+        arrival_us: u64,
+    ) -> Result<Accepted, StreamError> {
+        let established = self
+            .session
+            .ensure_streaming_ready()
+            .map_err(|_| StreamError::NotAuthenticated)?;
+        if frame.message_type != MessageType::AlpineFrame {
+            return Err(StreamError::Decode(format!(
+                "expected alpine_frame, got {:?}",
+                frame.message_type
+            )));
+        }
+        if frame.session_id != established.session_id {
+            return Err(StreamError::SessionMismatch);
+        }
+        if frame.stream_id != self.stream_id {
+            return Err(StreamError::StreamMismatch(frame.stream_id));
+        }
+        let keys = self
+            .sender_epochs
+            .lock()
+            .keys(&self.session, self.stream_id, frame.epoch)?;
+        let encrypted = frame.ciphertext.is_some();
+        open_frame(&keys, &mut frame)?;
+        self.follow_sender(frame.epoch)?;
+        let mut replay = self.replay.lock();
+        let duplicate = !replay.check(frame.sequence);
+        if duplicate && !(self.redundant() && replay.seen(frame.sequence)) {
+            return Err(StreamError::SessionMacMismatch);
+        }
+        if !encrypted && self.profile.confidentiality() && self.session.encryption_negotiated() {
+            return Err(StreamError::EncryptionRequired);
+        }
+        if let Some(capabilities) = self.capabilities.as_ref() {
+            check_capabilities(
+                capabilities,
+                &frame.channels.format(),
+                frame.channels.channel_count(),
+                frame
+                    .groups
+                    .as_ref()
+                    .is_some_and(|groups| !groups.is_empty()),
+            )?;
+        }
+        let deadline_us = self.playout_target_us(&frame);
+        if let Some(path) = path {
+            self.paths[path].lock().conditions.record_frame(
+                frame.sequence,
+                frame.timestamp_us,
+                arrival_us,
+                deadline_us,
+            );
+        }
+        if duplicate {
+            return Ok(Accepted::Duplicate(frame.sequence));
+        }
+        replay.accept(frame.sequence);
+        drop(replay);
+        if let Some(parity) = self.parity.as_ref() {
+            parity.lock().record(frame.sequence, datagram);
+        }
+
+        self.conditions.lock().record_frame(
+            frame.sequence,
+            frame.timestamp_us,
+            arrival_us,
+            deadline_us,
+        );
+        self.maybe_report(arrival_us);
+        Ok(Accepted::Frame(frame))
+    }
+
+    fn redundant(&self) -> bool {
+        self.paths.len() > 1
+    }
+
+    /// Makes an authenticated sender `epoch` current. A newer epoch means the
+    /// sender was recreated and numbers its frames from 1 again, so every
+    /// piece of per-sequence state starts over; an older one is rejected.
+    fn follow_sender(&self, epoch: u64) -> Result<(), StreamError> {
+        if !self.sender_epochs.lock().follow(epoch)? {
+            return Ok(());
+        }
+        debug!(target: "alpine::stream", epoch, "sender restarted with a new epoch");
+        *self.replay.lock() = ReplayWindow::new();
+        *self.decoder.lock() = DeltaDecoder::default();
+        if let Some(parity) = self.parity.as_ref() {
+            *parity.lock() = ParityDecoder::default();
+        }
+        *self.playout.lock() = PlayoutBuffer::new(self.reorder_window);
+        *self.concealer.lock() = JitterConcealer::default();
+        let mut conditions = self.conditions.lock();
+        *conditions = NetworkConditions::with_horizon(conditions.horizon());
+        drop(conditions);
+        for path in &self.paths {
+            let mut state = path.lock();
+            state.reassembly = Reassembler::new(self.reassembly_timeout);
+            state.conditions = NetworkConditions::with_horizon(state.conditions.horizon());
+        }
+        Ok(())
+    }
+
+    fn recover(&self, mut parity: FrameParity) -> Result<Option<(u64, Vec<u8>)>, StreamError> {
+        let established = self
+            .session
+            .ensure_streaming_ready()
+            .map_err(|_| StreamError::NotAuthenticated)?;
+        if parity.message_type != MessageType::AlpineFrameParity {
+            return Err(StreamError::Decode(format!(
+                "expected alpine_frame_parity, got {:?}",
+                parity.message_type
+            )));
+        }
+        if parity.session_id != established.session_id {
+            return Err(StreamError::SessionMismatch);
+        }
+        if parity.stream_id != self.stream_id {
+            return Err(StreamError::StreamMismatch(parity.stream_id));
+        }
+        let Some(decoder) = self.parity.as_ref() else {
+            return Ok(None);
+        };
+        let keys = self
+            .sender_epochs
+            .lock()
+            .keys(&self.session, self.stream_id, parity.epoch)?;
+        open_signed(&keys, &mut parity)?;
+        self.follow_sender(parity.epoch)?;
+        Ok(decoder.lock().recover(&parity))
+    }
+
+    fn reassemble(
+        &self,
+        path: usize,
+        mut fragment: FrameFragment,
+        arrival_us: u64,
+    ) -> Result<Option<Vec<u8>>, StreamError> {
+        let established = self
+            .session
+            .ensure_streaming_ready()
+            .map_err(|_| StreamError::NotAuthenticated)?;
+        if fragment.message_type != MessageType::AlpineFrameFragment {
+            return Err(StreamError::Decode(format!(
+                "expected alpine_frame_fragment, got {:?}",
+                fragment.message_type
+            )));
+        }
+        if fragment.session_id != established.session_id {
+            return Err(StreamError::SessionMismatch);
+        }
+        if fragment.stream_id != self.stream_id {
+            return Err(StreamError::StreamMismatch(fragment.stream_id));
+        }
+        let keys = self
+            .sender_epochs
+            .lock()
+            .keys(&self.session, self.stream_id, fragment.epoch)?;
+        open_signed(&keys, &mut fragment)?;
+        self.follow_sender(fragment.epoch)?;
+        let replay = self.replay.lock();
+        // Fragments of a frame already rebuilt from another path are
+        // reassembled again so that path's metrics see the frame.
+        let usable =
+            replay.check(fragment.sequence) || (self.redundant() && replay.seen(fragment.sequence));
+        if !usable {
+            return Err(StreamError::SessionMacMismatch);
+        }
+        drop(replay);
+        let mut state = self.paths[path].lock();
+        let expired = state.reassembly.expire(arrival_us);
+        if expired > 0 {
+            debug!(
+                target: "alpine::stream",
+                path, expired, "dropped incomplete fragmented frames"
+            );
+        }
+        state.reassembly.push(fragment, arrival_us)
+    }
+
+    /// Builds an authenticated keyframe request for a frame that failed to decode.
+    ///
+    /// The controller answers by forcing its next frame to be a keyframe; see
+    /// [`AlnpStream::handle_keyframe_request`](crate::stream::AlnpStream::handle_keyframe_request).
+    pub fn keyframe_request(
+        &self,
+        failed_sequence: u64,
+        missing_base: Option<u64>,
+    ) -> Result<Vec<u8>, StreamError> {
+        let established = self
+            .session
+            .ensure_streaming_ready()
+            .map_err(|_| StreamError::NotAuthenticated)?;
+        let keys = self
+            .keys
+            .lock()
+            .get(&self.session, self.stream_id, self.epoch)?;
+        let request_sequence = {
+            let mut sequence = self.request_sequence.lock();
+            *sequence = sequence.wrapping_add(1);
+            *sequence
+        };
+        let mut request = KeyframeRequest {
+            message_type: MessageType::AlpineKeyframeRequest,
+            session_id: established.session_id,
+            stream_id: self.stream_id,
+            epoch: self.epoch,
+            request_sequence,
+            timestamp_us: now_us(),
+            failed_sequence,
+            missing_base,
+            mac: Vec::new(),
+        };
+        encode_signed(&keys, &mut request)
+    }
+
+    fn maybe_request_keyframe(&self, failed_sequence: u64, missing_base: u64) {
+        let Some(feedback) = self.feedback.as_ref() else {
+            return;
+        };
+        let mut feedback = feedback.lock();
+        let now = now_us();
+        let interval_us = KEYFRAME_REQUEST_INTERVAL.as_micros() as u64;
+        if feedback
+            .last_keyframe_request_us
+            .is_some_and(|last| now.saturating_sub(last) < interval_us)
+        {
+            return;
+        }
+        feedback.last_keyframe_request_us = Some(now);
+        let sent = self
+            .keyframe_request(failed_sequence, Some(missing_base))
+            .and_then(|bytes| {
+                feedback
+                    .transport
+                    .send_frame(&bytes)
+                    .map_err(StreamError::Transport)
+            });
+        if let Err(err) = sent {
+            warn!(target: "alpine::stream", "keyframe request not sent: {}", err);
+        }
+    }
+
+    fn maybe_report(&self, now_us: u64) {
+        let Some(feedback) = self.feedback.as_ref() else {
+            return;
+        };
+        let mut feedback = feedback.lock();
+        let interval_us = feedback.interval_us;
+        let due = *feedback
+            .next_report_us
+            .get_or_insert(now_us.saturating_add(interval_us));
+        if now_us < due {
+            return;
+        }
+        feedback.next_report_us = Some(now_us.saturating_add(interval_us));
+        let sent = self.report_at(now_us).and_then(|bytes| {
+            feedback
+                .transport
+                .send_frame(&bytes)
+                .map_err(StreamError::Transport)
+        });
+        if let Err(err) = sent {
+            warn!(target: "alpine::stream", "receiver report not sent: {}", err);
+        }
+    }
+
+    fn decode(&self, mut frame: FrameEnvelope) -> Result<FrameEnvelope, StreamError> {
+        let groups = self.groups.lock();
+        let decoded = self.decoder.lock().decode(
+            frame.sequence,
+            frame.kind,
+            std::mem::take(&mut frame.channels),
+            frame.base_sequence,
+            frame.delta.as_deref(),
+            |channels| match frame.groups.as_ref() {
+                Some(values) => groups.apply(channels, values),
+                None => Ok(()),
+            },
+        );
+        drop(groups);
+        match decoded {
+            Ok(channels) => {
+                if frame.kind == FrameKind::Keyframe {
+                    if let Some(feedback) = self.feedback.as_ref() {
+                        feedback.lock().last_keyframe_request_us = None;
+                    }
+                }
+                frame.channels = channels;
+                Ok(frame)
+            }
+            Err(StreamError::DeltaBaseMissing(base)) => {
+                self.maybe_request_keyframe(frame.sequence, base);
+                Err(StreamError::DeltaBaseMissing(base))
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Deadline for a frame: sender timestamp, profile budget, and adaptive offset.
+    fn playout_target_us(&self, frame: &FrameEnvelope) -> u64 {
+        let offset_us = deadline_offset_ms(frame) * 1_000;
+        let budget_us = (self.profile.delivery_deadline_us() as i64 + offset_us).max(0) as u64;
+        frame.timestamp_us.saturating_add(budget_us)
+    }
+
+    /// Returns the latest metrics snapshot for the received stream.
+    pub fn metrics(&self) -> NetworkMetrics {
+        self.conditions.lock().metrics()
+    }
+
+    /// Returns the metrics of each redundant path, indexed by path.
+    ///
+    /// Every path counts the frames that arrived on it, including copies
+    /// discarded as duplicates, so a degraded path shows its own loss while
+    /// [`AlnpStreamReceiver::metrics`] reflects the merged stream.
+    pub fn path_metrics(&self) -> Vec<NetworkMetrics> {
+        self.paths
+            .iter()
+            .map(|path| path.lock().conditions.metrics())
+            .collect()
+    }
+
+    fn path_loss_ratios(&self) -> Vec<f64> {
+        if !self.redundant() {
+            return Vec::new();
+        }
+        self.path_metrics()
+            .iter()
+            .map(|metrics| metrics.loss_ratio)
+            .collect()
+    }
+
+    /// Returns a copy of the tracker so callers can drive recovery or adaptation.
+    pub fn network_conditions(&self) -> NetworkConditions {
+        self.conditions.lock().clone()
+    }
+}
+
+/// Reads the sender's adaptive deadline offset from the frame header.
+fn deadline_offset_ms(frame: &FrameEnvelope) -> i64 {
+    frame
+        .adaptation
+        .map(|header| header.deadline_offset_ms as i64)
+        .unwrap_or(0)
+}
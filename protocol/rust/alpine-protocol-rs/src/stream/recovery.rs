@@ -4,31 +4,14 @@
 //! and exposes explicit `RecoveryStarted`/`RecoveryComplete` events. Recovery is
 //! triggered only by sustained loss ratios or large burst gaps and never rewinds
 //! the timeline.
-use crate::stream::network::NetworkConditions;
+use crate::messages::RecoveryReason;
+use crate::stream::network::{NetworkConditions, NetworkMetrics};
 
 const SUSTAINED_LOSS_THRESHOLD: f64 = 0.25;
 const RECOVERY_CLEAR_LOSS_THRESHOLD: f64 = 0.05;
 const BURST_LOSS_THRESHOLD: u64 = 3;
 const RECOVERY_CLEAR_BURST_THRESHOLD: u64 = 1;
 
-/// Represents why recovery was triggered.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum RecoveryReason {
-    /// Sustained loss ratio across many frames.
-    SustainedLoss,
-    /// Burst loss gap (skipped sequences) exceeded the safe window.
-    BurstLoss,
-}
-
-impl RecoveryReason {
-    pub(crate) fn as_str(&self) -> &'static str {
-        match self {
-            RecoveryReason::SustainedLoss => "sustained_loss",
-            RecoveryReason::BurstLoss => "burst_loss",
-        }
-    }
-}
-
 /// Events emitted while evaluation recovery state.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RecoveryEvent {
@@ -36,6 +19,11 @@ pub enum RecoveryEvent {
     RecoveryStarted(RecoveryReason),
     /// Recovery completed once metrics returned to safe bounds.
     RecoveryComplete(RecoveryReason),
+    /// A receiver asked for a keyframe; the next frame is forced to one.
+    KeyframeRequested {
+        /// Sequence the receiver failed to reconstruct.
+        failed_sequence: u64,
+    },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -60,8 +48,12 @@ impl RecoveryMonitor {
 
     /// Feeds fresh metrics and returns a matching recovery event, if any.
     pub fn feed(&mut self, conditions: &NetworkConditions) -> Option<RecoveryEvent> {
-        let metrics = conditions.metrics();
-        let gap = conditions.max_loss_gap();
+        self.feed_metrics(&conditions.metrics())
+    }
+
+    /// Feeds a metrics snapshot, e.g. one carried by a receiver report.
+    pub fn feed_metrics(&mut self, metrics: &NetworkMetrics) -> Option<RecoveryEvent> {
+        let gap = metrics.max_loss_gap;
         match self.state {
             RecoveryState::Idle => {
                 if gap >= BURST_LOSS_THRESHOLD {
@@ -101,6 +93,12 @@ impl RecoveryMonitor {
     }
 }
 
+impl Default for RecoveryMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -108,9 +106,9 @@ mod tests {
 
     fn low_loss_conditions() -> NetworkConditions {
         let mut cond = NetworkConditions::new();
-        cond.record_frame(10, 0, 1_000);
-        cond.record_frame(11, 1_000, 2_000);
-        cond.record_frame(12, 2_000, 3_000);
+        cond.record_frame(10, 0, 0, 1_000);
+        cond.record_frame(11, 1_000, 1_000, 2_000);
+        cond.record_frame(12, 2_000, 2_000, 3_000);
         cond
     }
 
@@ -118,9 +116,9 @@ mod tests {
     fn starts_and_completes_on_loss_ratio() {
         let mut monitor = RecoveryMonitor::new();
         let mut cond = NetworkConditions::new();
-        cond.record_frame(1, 0, 0);
-        cond.record_frame(2, 1_000, 0);
-        cond.record_frame(4, 2_000, 0);
+        cond.record_frame(1, 0, 0, 0);
+        cond.record_frame(2, 1_000, 1_000, 0);
+        cond.record_frame(4, 2_000, 2_000, 0);
         let event = monitor.feed(&cond);
         assert_eq!(
             event,
@@ -141,8 +139,8 @@ mod tests {
     fn burst_gap_triggers_recovery() {
         let mut monitor = RecoveryMonitor::new();
         let mut cond = NetworkConditions::new();
-        cond.record_frame(1, 0, 0);
-        cond.record_frame(5, 1_000, 0);
+        cond.record_frame(1, 0, 0, 0);
+        cond.record_frame(5, 1_000, 1_000, 0);
         let event = monitor.feed(&cond);
         assert_eq!(
             event,
@@ -159,12 +157,35 @@ mod tests {
     fn recovery_idempotent_until_cleared() {
         let mut monitor = RecoveryMonitor::new();
         let mut cond = NetworkConditions::new();
-        cond.record_frame(1, 0, 0);
-        cond.record_frame(4, 1_000, 0);
+        cond.record_frame(1, 0, 0, 0);
+        cond.record_frame(4, 1_000, 1_000, 0);
         assert!(matches!(
             monitor.feed(&cond),
             Some(RecoveryEvent::RecoveryStarted(_))
         ));
         assert_eq!(monitor.feed(&cond), None);
     }
+
+    #[test]
+    fn long_running_session_completes_recovery_after_burst() {
+        let mut monitor = RecoveryMonitor::new();
+        let mut cond = NetworkConditions::with_horizon(16);
+        cond.record_frame(1, 0, 0, 0);
+        cond.record_frame(10, 1_000, 1_000, 0);
+        assert!(matches!(
+            monitor.feed(&cond),
+            Some(RecoveryEvent::RecoveryStarted(RecoveryReason::BurstLoss))
+        ));
+        for seq in 11..=40 {
+            cond.record_frame(seq, seq * 1_000, seq * 1_000, 0);
+            if let Some(event) = monitor.feed(&cond) {
+                assert_eq!(
+                    event,
+                    RecoveryEvent::RecoveryComplete(RecoveryReason::BurstLoss)
+                );
+                return;
+            }
+        }
+        panic!("recovery never completed");
+    }
 }
@@ -0,0 +1,107 @@
+//! Sliding anti-replay window for streaming sequences.
+//!
+//! Frames are authenticated per sequence number, so a captured datagram would
+//! still verify if it were sent again. The window remembers the highest
+//! accepted sequence plus a bitmap of the preceding `REPLAY_WINDOW` sequences;
+//! duplicates and anything older than the window are rejected as stale.
+
+/// Number of sequences behind the highest accepted one that may still arrive.
+pub const REPLAY_WINDOW: u64 = 64;
+
+/// Receiver-side replay window keyed by frame `sequence`.
+#[derive(Debug, Clone, Default)]
+pub struct ReplayWindow {
+    highest: u64,
+    seen: u64,
+}
+
+impl ReplayWindow {
+    /// Creates an empty window; sequence `0` is never accepted.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns `true` when `sequence` has not been accepted and is inside the window.
+    ///
+    /// This does not record the sequence; call [`ReplayWindow::accept`] once the
+    /// frame has been authenticated so forged frames cannot advance the window.
+    pub fn check(&self, sequence: u64) -> bool {
+        if sequence == 0 {
+            return false;
+        }
+        if sequence > self.highest {
+            return true;
+        }
+        let offset = self.highest - sequence;
+        offset < REPLAY_WINDOW && self.seen & (1u64 << offset) == 0
+    }
+
+    /// Marks `sequence` as accepted, sliding the window forward when needed.
+    pub fn accept(&mut self, sequence: u64) {
+        if sequence > self.highest {
+            let shift = sequence - self.highest;
+            self.seen = if shift >= REPLAY_WINDOW {
+                0
+            } else {
+                self.seen << shift
+            };
+            self.seen |= 1;
+            self.highest = sequence;
+        } else {
+            let offset = self.highest - sequence;
+            if offset < REPLAY_WINDOW {
+                self.seen |= 1u64 << offset;
+            }
+        }
+    }
+
+    /// Returns `true` when `sequence` was already accepted and is still inside
+    /// the window, i.e. a repeat of a frame this receiver used.
+    pub fn seen(&self, sequence: u64) -> bool {
+        if sequence == 0 || sequence > self.highest {
+            return false;
+        }
+        let offset = self.highest - sequence;
+        offset < REPLAY_WINDOW && self.seen & (1u64 << offset) != 0
+    }
+
+    /// Highest sequence accepted so far (`0` before the first frame).
+    pub fn highest(&self) -> u64 {
+        self.highest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicates_are_rejected() {
+        let mut window = ReplayWindow::new();
+        assert!(window.check(1));
+        window.accept(1);
+        assert!(!window.check(1));
+        assert!(window.check(2));
+    }
+
+    #[test]
+    fn reordered_frames_inside_window_are_accepted_once() {
+        let mut window = ReplayWindow::new();
+        window.accept(10);
+        assert!(window.check(7));
+        window.accept(7);
+        assert!(!window.check(7));
+        assert!(window.check(8));
+        assert!(window.seen(7));
+        assert!(!window.seen(8));
+    }
+
+    #[test]
+    fn stale_frames_outside_window_are_rejected() {
+        let mut window = ReplayWindow::new();
+        window.accept(100);
+        assert!(!window.check(100 - REPLAY_WINDOW));
+        assert!(window.check(100 - REPLAY_WINDOW + 1));
+        assert!(!window.check(0));
+    }
+}
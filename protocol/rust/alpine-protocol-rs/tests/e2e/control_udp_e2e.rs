@@ -1,6 +1,5 @@
 use std::error::Error;
 
-use serde_cbor;
 use serde_json::json;
 use tokio::net::UdpSocket;
 
@@ -1,13 +1,13 @@
 use std::error::Error;
 use std::net::{SocketAddr, UdpSocket as StdUdpSocket};
 
-use serde_cbor;
 use tokio::net::UdpSocket;
 
-use alpine::messages::{ChannelFormat, FrameEnvelope, MessageType};
+use alpine::messages::{ChannelBuffer, MessageType};
 use alpine::profile::StreamProfile;
-use alpine::session::JitterStrategy;
-use alpine::stream::{AlnpStream, FrameTransport};
+use alpine::stream::{
+    AdaptationEvent, AlnpStream, AlnpStreamReceiver, FrameTransport, StreamEventKind,
+};
 
 use alpine::e2e_common::run_udp_handshake;
 
@@ -33,8 +33,7 @@ impl FrameTransport for UdpFrameTransport {
 
 #[tokio::test]
 async fn streaming_udp_e2e_phase3() -> Result<(), Box<dyn Error>> {
-    let (controller_session, _node_session) = run_udp_handshake().await?;
-    controller_session.set_jitter_strategy(JitterStrategy::HoldLast);
+    let (controller_session, node_session) = run_udp_handshake().await?;
 
     let stream_socket = StdUdpSocket::bind(("127.0.0.1", 0))?;
     let receiver_socket = UdpSocket::bind(("127.0.0.1", 0)).await?;
@@ -42,31 +41,82 @@ async fn streaming_udp_e2e_phase3() -> Result<(), Box<dyn Error>> {
 
     let transport = UdpFrameTransport::new(stream_socket, receiver_addr);
     let profile = StreamProfile::auto().compile().unwrap();
-    let stream = AlnpStream::new(controller_session.clone(), transport, profile);
+    let stream = AlnpStream::new(controller_session.clone(), transport, profile.clone());
+    let receiver = AlnpStreamReceiver::new(node_session, profile);
 
     let receiver_task = tokio::spawn(async move {
         let mut frames = Vec::with_capacity(2);
         for _ in 0..2 {
             let mut buf = vec![0u8; 4096];
             let (len, _) = receiver_socket.recv_from(&mut buf).await?;
-            let frame: FrameEnvelope = serde_cbor::from_slice(&buf[..len])?;
+            let frame = receiver.receive(&buf[..len])?;
             frames.push(frame);
         }
         Ok::<_, Box<dyn Error + Send + Sync>>(frames)
     });
 
     stream
-        .send(ChannelFormat::U8, vec![1, 2, 3], 5, None, None)
-        .map_err(|e| Box::<dyn Error>::from(e))?;
+        .send(ChannelBuffer::U8(vec![1, 2, 3]), 5, None, None)
+        .map_err(Box::<dyn Error>::from)?;
     stream
-        .send(ChannelFormat::U8, Vec::new(), 5, None, None)
-        .map_err(|e| Box::<dyn Error>::from(e))?;
+        .send(ChannelBuffer::U8(vec![4, 5, 6]), 5, None, None)
+        .map_err(Box::<dyn Error>::from)?;
 
     let frames = receiver_task.await?.map_err(|e| e as Box<dyn Error>)?;
     assert_eq!(frames.len(), 2);
     assert_eq!(frames[0].message_type, MessageType::AlpineFrame);
-    assert_eq!(frames[0].channels, vec![1, 2, 3]);
+    assert_eq!(frames[0].channels, ChannelBuffer::U8(vec![1, 2, 3]));
     assert_eq!(frames[1].message_type, MessageType::AlpineFrame);
-    assert_eq!(frames[1].channels, frames[0].channels);
+    assert_eq!(frames[1].channels, ChannelBuffer::U8(vec![4, 5, 6]));
+    Ok(())
+}
+
+#[tokio::test]
+async fn clean_network_keeps_stream_out_of_degraded_safe() -> Result<(), Box<dyn Error>> {
+    const REPORTS: usize = 64;
+    let (controller_session, node_session) = run_udp_handshake().await?;
+
+    let stream_socket = StdUdpSocket::bind(("127.0.0.1", 0))?;
+    let receiver_socket = UdpSocket::bind(("127.0.0.1", 0)).await?;
+    let feedback_socket = UdpSocket::bind(("127.0.0.1", 0)).await?;
+    let controller_socket = UdpSocket::bind(("127.0.0.1", 0)).await?;
+
+    // Realtime profiles allow no positive deadline offset, so every clean
+    // report asks to relax a deadline that is already at its bound.
+    let profile = StreamProfile::realtime().compile().unwrap();
+    let transport = UdpFrameTransport::new(stream_socket, receiver_socket.local_addr()?);
+    let stream = AlnpStream::new(controller_session, transport, profile.clone());
+    let receiver = AlnpStreamReceiver::new(node_session, profile);
+    let mut events = stream.subscribe_events();
+
+    let mut buf = vec![0u8; 4096];
+    for round in 0..REPORTS {
+        stream
+            .send(ChannelBuffer::U8(vec![round as u8; 8]), 5, None, None)
+            .map_err(Box::<dyn Error>::from)?;
+        let (len, _) = receiver_socket.recv_from(&mut buf).await?;
+        let frame = receiver.receive(&buf[..len])?;
+        let header = frame.adaptation.expect("adaptation header");
+        assert!(!header.degraded_safe, "degraded-safe at frame {}", round);
+        assert!(header.deadline_offset_ms <= 0);
+
+        let report = receiver.report()?;
+        feedback_socket
+            .send_to(&report, controller_socket.local_addr()?)
+            .await?;
+        let (len, _) = controller_socket.recv_from(&mut buf).await?;
+        stream.handle_receiver_report(&buf[..len])?;
+    }
+
+    while let Ok(event) = events.try_recv() {
+        assert!(
+            !matches!(
+                event.kind,
+                StreamEventKind::Adaptation(AdaptationEvent::EnteredDegradedSafe(_))
+            ),
+            "unexpected {:?}",
+            event.kind
+        );
+    }
     Ok(())
 }
@@ -1,5 +1,7 @@
 use std::convert::TryInto;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use async_trait::async_trait;
 use ed25519_dalek::{Signature, SigningKey, Verifier};
@@ -14,11 +16,18 @@ use alpine::crypto::X25519KeyExchange;
 use alpine::discovery::DiscoveryResponder;
 use alpine::handshake::{HandshakeContext, HandshakeError, HandshakeMessage, HandshakeTransport};
 use alpine::messages::{
-    CapabilitySet, ChannelFormat, ControlOp, DeviceIdentity, ErrorCode, FrameEnvelope, MessageType,
+    AdaptationHeader, CapabilitySet, ChannelBuffer, ChannelFormat, ChannelRange, ControlOp,
+    DeviceIdentity, ErrorCode, FrameEnvelope, FrameKind, MessageType, ReceiverReport,
 };
-use alpine::profile::StreamProfile;
+use alpine::profile::{CompiledStreamProfile, StreamProfile};
 use alpine::session::{AlnpSession, JitterStrategy, StaticKeyAuthenticator};
-use alpine::stream::{AlnpStream, FrameTransport};
+use alpine::stream::{
+    peek_stream_id, AdaptationDecision, AdaptationEvent, AdaptationPolicy, AdaptationState,
+    AlnpStream, AlnpStreamReceiver, AsyncAdapter, AsyncAlnpStream, AsyncFrameTransport,
+    BlockingAdapter, ChannelGroups, DegradedReason, FrameBuf, FramePacer, FrameTransport,
+    KeyframeRequestOutcome, NetworkMetrics, PacerStats, PlayoutFrame, PlayoutPush, RecoveryEvent,
+    RecoveryReason, StreamDemux, StreamError, StreamEventKind, DEFAULT_MAX_DATAGRAM_SIZE,
+};
 
 /// Simple transport bridge used to run two handshake participants in tests.
 struct PipeTransport {
@@ -72,11 +81,18 @@ fn make_identity(name: &str) -> DeviceIdentity {
 }
 
 async fn create_sessions() -> (AlnpSession, AlnpSession) {
+    create_sessions_with(CapabilitySet::default(), CapabilitySet::default()).await
+}
+
+async fn create_sessions_with(
+    controller_caps: CapabilitySet,
+    node_caps: CapabilitySet,
+) -> (AlnpSession, AlnpSession) {
     let (mut controller_transport, mut node_transport) = PipeTransport::pair();
     let controller_task = tokio::spawn(async move {
         AlnpSession::connect(
             make_identity("controller"),
-            CapabilitySet::default(),
+            controller_caps,
             StaticKeyAuthenticator::default(),
             X25519KeyExchange::new(),
             HandshakeContext::default(),
@@ -87,7 +103,7 @@ async fn create_sessions() -> (AlnpSession, AlnpSession) {
     let node_task = tokio::spawn(async move {
         AlnpSession::accept(
             make_identity("node"),
-            CapabilitySet::default(),
+            node_caps,
             StaticKeyAuthenticator::default(),
             X25519KeyExchange::new(),
             HandshakeContext::default(),
@@ -99,25 +115,52 @@ async fn create_sessions() -> (AlnpSession, AlnpSession) {
     (ctrl_res.unwrap().unwrap(), node_res.unwrap().unwrap())
 }
 
+/// Capabilities for tests that stream 16-bit or very large frames.
+fn wide_caps() -> CapabilitySet {
+    CapabilitySet {
+        channel_formats: vec![ChannelFormat::U8, ChannelFormat::U16],
+        max_channels: 65_536,
+        ..CapabilitySet::default()
+    }
+}
+
+/// Values of a `U8` frame, for assertions.
+fn u8_values(channels: &ChannelBuffer) -> &[u8] {
+    match channels {
+        ChannelBuffer::U8(values) => values,
+        other => panic!("expected u8 channels, got {:?}", other.format()),
+    }
+}
+
 #[derive(Clone)]
 struct RecordingTransport {
     frames: Arc<Mutex<Vec<Vec<u8>>>>,
+    down: Arc<AtomicBool>,
 }
 
 impl RecordingTransport {
     fn new() -> Self {
         Self {
             frames: Arc::new(Mutex::new(Vec::new())),
+            down: Arc::new(AtomicBool::new(false)),
         }
     }
 
     fn snapshots(&self) -> Vec<Vec<u8>> {
         self.frames.lock().unwrap().clone()
     }
+
+    /// Makes every send fail, without recording, while `down` is set.
+    fn set_down(&self, down: bool) {
+        self.down.store(down, Ordering::SeqCst);
+    }
 }
 
 impl FrameTransport for RecordingTransport {
     fn send_frame(&self, bytes: &[u8]) -> Result<(), String> {
+        if self.down.load(Ordering::SeqCst) {
+            return Err("link down".into());
+        }
         self.frames.lock().unwrap().push(bytes.to_vec());
         Ok(())
     }
@@ -177,26 +220,1538 @@ async fn control_mac_roundtrip() {
     assert_eq!(expected_mac, ack.mac);
 }
 
+async fn play_with_gap(strategy: JitterStrategy, values: [u8; 3]) -> Vec<PlayoutFrame> {
+    let (controller, node) = create_sessions().await;
+    node.set_jitter_strategy(strategy);
+    let transport = RecordingTransport::new();
+    let profile = StreamProfile::auto().compile().unwrap();
+    let stream = AlnpStream::new(controller, transport.clone(), profile.clone());
+    for value in values {
+        stream
+            .send(ChannelBuffer::U8(vec![value, 50]), 5, None, None)
+            .unwrap();
+    }
+    let snapshots = transport.snapshots();
+    let receiver = AlnpStreamReceiver::new(node, profile);
+    receiver.enqueue(&snapshots[0]).unwrap();
+    receiver.enqueue(&snapshots[2]).unwrap();
+    let mut played = Vec::new();
+    while let Some(frame) = receiver.poll_playout(u64::MAX).unwrap() {
+        played.push(frame);
+    }
+    played
+}
+
 #[tokio::test]
 async fn streaming_frames_hold_last_when_requested() {
-    let (controller, _) = create_sessions().await;
-    controller.set_jitter_strategy(JitterStrategy::HoldLast);
+    let played = play_with_gap(JitterStrategy::HoldLast, [10, 30, 40]).await;
+    let sequences: Vec<u64> = played.iter().map(PlayoutFrame::sequence).collect();
+    assert_eq!(sequences, vec![1, 2, 3]);
+    assert!(played[1].is_concealed());
+    assert_eq!(u8_values(played[1].channels()), &[10, 50]);
+    assert_eq!(u8_values(played[2].channels()), &[40, 50]);
+}
+
+#[tokio::test]
+async fn streaming_frames_drop_gaps_when_requested() {
+    let played = play_with_gap(JitterStrategy::Drop, [10, 30, 40]).await;
+    let sequences: Vec<u64> = played.iter().map(PlayoutFrame::sequence).collect();
+    assert_eq!(sequences, vec![1, 3]);
+}
+
+#[tokio::test]
+async fn streaming_frames_lerp_across_gaps_without_damping_changes() {
+    let played = play_with_gap(JitterStrategy::Lerp, [0, 0, 200]).await;
+    assert_eq!(played.len(), 3);
+    assert!(played[1].is_concealed());
+    let filled = u8_values(played[1].channels())[0];
+    assert!(filled > 0 && filled < 200);
+    assert_eq!(u8_values(played[2].channels()), &[200, 50]);
+    assert!(!played[2].is_concealed());
+}
+
+#[tokio::test]
+async fn concealed_frames_play_out_at_their_own_slots() {
+    let (controller, node) = create_sessions().await;
+    node.set_jitter_strategy(JitterStrategy::HoldLast);
     let transport = RecordingTransport::new();
     let profile = StreamProfile::auto().compile().unwrap();
-    let stream = AlnpStream::new(controller.clone(), transport.clone(), profile);
+    let stream = AlnpStream::new(controller, transport.clone(), profile.clone());
+    for value in [10u8, 20, 30, 40] {
+        stream
+            .send(ChannelBuffer::U8(vec![value]), 5, None, None)
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(2));
+    }
+    let snapshots = transport.snapshots();
+    let receiver = AlnpStreamReceiver::new(node, profile);
+    receiver.enqueue(&snapshots[0]).unwrap();
+    receiver.enqueue(&snapshots[3]).unwrap();
+
+    let first_due = receiver.next_playout_us().unwrap();
+    let first = receiver.poll_playout(first_due).unwrap().unwrap();
+    assert_eq!(first.sequence(), 1);
+    let last: FrameEnvelope = serde_cbor::from_slice(&snapshots[3]).unwrap();
+    let last_due = last.timestamp_us + (first_due - first.timestamp_us());
+
+    let mut previous_due = first_due;
+    for sequence in [2, 3] {
+        let slot_due = receiver.next_playout_us().unwrap();
+        assert!(slot_due > previous_due && slot_due < last_due);
+        assert!(receiver.poll_playout(slot_due - 1).unwrap().is_none());
+        let concealed = receiver.poll_playout(slot_due).unwrap().unwrap();
+        assert!(concealed.is_concealed());
+        assert_eq!(concealed.sequence(), sequence);
+        assert_eq!(u8_values(concealed.channels()), &[10]);
+        previous_due = slot_due;
+    }
+
+    assert_eq!(receiver.next_playout_us(), Some(last_due));
+    assert!(receiver.poll_playout(last_due - 1).unwrap().is_none());
+    let received = receiver.poll_playout(last_due).unwrap().unwrap();
+    assert!(!received.is_concealed());
+    assert_eq!(received.sequence(), 4);
+    assert_eq!(receiver.enqueue(&snapshots[1]).unwrap(), PlayoutPush::Late);
+}
+
+#[tokio::test]
+async fn delta_frames_carry_only_changes_and_reconstruct() {
+    let (controller, node) = create_sessions().await;
+    let transport = RecordingTransport::new();
+    let profile = StreamProfile::auto().compile().unwrap();
+    let stream = AlnpStream::new(controller, transport.clone(), profile.clone());
+    let mut channels = vec![0u8; 64];
     stream
-        .send(ChannelFormat::U8, vec![10, 20], 5, None, None)
+        .send(ChannelBuffer::U8(channels.clone()), 5, None, None)
         .unwrap();
+    channels[10] = 200;
     stream
-        .send(ChannelFormat::U8, Vec::new(), 5, None, None)
+        .send(ChannelBuffer::U8(channels.clone()), 5, None, None)
         .unwrap();
+
+    let snapshots = transport.snapshots();
+    let wire: FrameEnvelope = serde_cbor::from_slice(&snapshots[1]).unwrap();
+    assert_eq!(wire.kind, FrameKind::Delta);
+    assert_eq!(wire.base_sequence, Some(1));
+    assert!(wire.channels.is_empty());
+    assert!(snapshots[1].len() < snapshots[0].len());
+
+    let receiver = AlnpStreamReceiver::new(node, profile);
+    receiver.receive(&snapshots[0]).unwrap();
+    let rebuilt = receiver.receive(&snapshots[1]).unwrap();
+    assert_eq!(u8_values(&rebuilt.channels), channels);
+}
+
+#[tokio::test]
+async fn frames_after_a_failed_send_resync_with_a_keyframe() {
+    let (controller, node) = create_sessions().await;
+    let transport = RecordingTransport::new();
+    let profile = StreamProfile::auto().compile().unwrap();
+    let stream = AlnpStream::new(controller, transport.clone(), profile.clone());
+    let mut channels = vec![0u8; 64];
+    stream
+        .send(ChannelBuffer::U8(channels.clone()), 5, None, None)
+        .unwrap();
+    channels[3] = 90;
+    transport.set_down(true);
+    assert!(matches!(
+        stream.send(ChannelBuffer::U8(channels.clone()), 5, None, None),
+        Err(StreamError::Transport(_))
+    ));
+    transport.set_down(false);
+    channels[4] = 91;
+    stream
+        .send(ChannelBuffer::U8(channels.clone()), 5, None, None)
+        .unwrap();
+
     let snapshots = transport.snapshots();
     assert_eq!(snapshots.len(), 2);
-    let first: FrameEnvelope = serde_cbor::from_slice(&snapshots[0]).unwrap();
-    let second: FrameEnvelope = serde_cbor::from_slice(&snapshots[1]).unwrap();
-    assert_eq!(first.channels, vec![10, 20]);
-    assert_eq!(second.channels, first.channels);
-    assert_eq!(first.message_type, MessageType::AlpineFrame);
+    let wire: FrameEnvelope = serde_cbor::from_slice(&snapshots[1]).unwrap();
+    assert_eq!(wire.sequence, 3);
+    assert_eq!(wire.kind, FrameKind::Keyframe);
+
+    let receiver = AlnpStreamReceiver::new(node, profile);
+    receiver.receive(&snapshots[0]).unwrap();
+    let rebuilt = receiver.receive(&snapshots[1]).unwrap();
+    assert_eq!(u8_values(&rebuilt.channels), channels);
+}
+
+#[tokio::test]
+async fn stream_receiver_tracks_sequences_from_sender() {
+    let (controller, node) = create_sessions().await;
+    let transport = RecordingTransport::new();
+    let profile = StreamProfile::auto().compile().unwrap();
+    let stream = AlnpStream::new(controller.clone(), transport.clone(), profile.clone());
+    for value in 0..3u8 {
+        stream
+            .send(ChannelBuffer::U8(vec![value]), 5, None, None)
+            .unwrap();
+    }
+    let receiver = AlnpStreamReceiver::new(node, profile);
+    let snapshots = transport.snapshots();
+    let first = receiver.receive(&snapshots[0]).unwrap();
+    let third = receiver.receive(&snapshots[2]).unwrap();
+    assert_eq!(first.sequence, 1);
+    assert_eq!(third.sequence, 3);
+    let metrics = receiver.metrics();
+    assert!((metrics.loss_ratio - 1.0 / 3.0).abs() < f64::EPSILON);
+}
+
+#[tokio::test]
+async fn playout_buffer_reorders_and_holds_until_deadline() {
+    let (controller, node) = create_sessions().await;
+    let transport = RecordingTransport::new();
+    let profile = StreamProfile::auto().compile().unwrap();
+    let stream = AlnpStream::new(controller, transport.clone(), profile.clone());
+    for value in 1..=4u8 {
+        stream
+            .send(ChannelBuffer::U8(vec![value]), 5, None, None)
+            .unwrap();
+    }
+    let receiver = AlnpStreamReceiver::new(node, profile);
+    let snapshots = transport.snapshots();
+    for index in [1, 0, 3] {
+        assert_eq!(
+            receiver.enqueue(&snapshots[index]).unwrap(),
+            PlayoutPush::Scheduled
+        );
+    }
+
+    let first_due = receiver.next_playout_us().unwrap();
+    assert!(receiver.poll_playout(first_due - 1).unwrap().is_none());
+    let mut played = Vec::new();
+    while let Some(frame) = receiver.poll_playout(u64::MAX).unwrap() {
+        played.push((frame.sequence(), u8_values(frame.channels())[0]));
+    }
+    assert_eq!(played, vec![(1, 1), (2, 2), (3, 2), (4, 4)]);
+
+    assert_eq!(receiver.enqueue(&snapshots[2]).unwrap(), PlayoutPush::Late);
+    assert!(receiver.metrics().late_frame_rate > 0.0);
+}
+
+#[tokio::test]
+async fn stream_receiver_rejects_foreign_session() {
+    let (controller, _) = create_sessions().await;
+    let (_, other_node) = create_sessions().await;
+    let transport = RecordingTransport::new();
+    let profile = StreamProfile::auto().compile().unwrap();
+    let stream = AlnpStream::new(controller, transport.clone(), profile.clone());
+    stream
+        .send(ChannelBuffer::U8(vec![1, 2, 3]), 5, None, None)
+        .unwrap();
+    let receiver = AlnpStreamReceiver::new(other_node, profile);
+    let result = receiver.receive(&transport.snapshots()[0]);
+    assert!(matches!(result, Err(StreamError::SessionMismatch)));
+}
+
+#[tokio::test]
+async fn stream_receiver_rejects_forged_and_replayed_frames() {
+    let (controller, node) = create_sessions().await;
+    let transport = RecordingTransport::new();
+    let profile = StreamProfile::auto().compile().unwrap();
+    let stream = AlnpStream::new(controller, transport.clone(), profile.clone());
+    stream
+        .send(ChannelBuffer::U8(vec![1, 2, 3]), 5, None, None)
+        .unwrap();
+    let receiver = AlnpStreamReceiver::new(node, profile);
+    let bytes = transport.snapshots().remove(0);
+
+    let mut forged: FrameEnvelope = serde_cbor::from_slice(&bytes).unwrap();
+    forged.channels = ChannelBuffer::U8(vec![255, 255, 255]);
+    let forged = serde_cbor::to_vec(&forged).unwrap();
+    let err = receiver.receive(&forged).unwrap_err();
+    assert!(matches!(err, StreamError::SessionMacMismatch));
+    assert_eq!(err.error_code(), Some(ErrorCode::SessionMacMismatch));
+
+    receiver.receive(&bytes).unwrap();
+    assert!(matches!(
+        receiver.receive(&bytes),
+        Err(StreamError::SessionMacMismatch)
+    ));
+}
+
+#[tokio::test]
+async fn recreated_streams_never_reuse_a_key_and_nonce() {
+    let (controller, node) = create_sessions().await;
+    let transport = RecordingTransport::new();
+    let profile = StreamProfile::auto()
+        .with_confidentiality(true)
+        .compile()
+        .unwrap();
+    let first = AlnpStream::new(controller.clone(), transport.clone(), profile.clone());
+    for _ in 0..2 {
+        first
+            .send(ChannelBuffer::U8(vec![7, 8, 9]), 5, None, None)
+            .unwrap();
+    }
+    let restarted = AlnpStream::new(controller, transport.clone(), profile.clone());
+    restarted
+        .send(ChannelBuffer::U8(vec![7, 8, 9]), 5, None, None)
+        .unwrap();
+    assert_ne!(first.epoch(), restarted.epoch());
+
+    // Both senders seal sequence 1 of stream 0 with the same payload, but
+    // under keys derived for their own epochs.
+    let datagrams = transport.snapshots();
+    let original: FrameEnvelope = serde_cbor::from_slice(&datagrams[0]).unwrap();
+    let recreated: FrameEnvelope = serde_cbor::from_slice(&datagrams[2]).unwrap();
+    assert_eq!(
+        (original.stream_id, original.sequence),
+        (recreated.stream_id, recreated.sequence)
+    );
+    assert_ne!(original.ciphertext, recreated.ciphertext);
+    assert_ne!(original.mac, recreated.mac);
+
+    // The receiver follows the new sender from sequence 1 and retires the old one.
+    let receiver = AlnpStreamReceiver::new(node.clone(), profile.clone());
+    receiver.receive(&datagrams[0]).unwrap();
+    receiver.receive(&datagrams[1]).unwrap();
+    let frame = receiver.receive(&datagrams[2]).unwrap();
+    assert_eq!(frame.sequence, 1);
+    assert_eq!(u8_values(&frame.channels), [7, 8, 9]);
+    assert!(matches!(
+        receiver.receive(&datagrams[1]),
+        Err(StreamError::SessionMacMismatch)
+    ));
+
+    // Controllers follow a recreated receiver's reports the same way.
+    let stale = receiver.report().unwrap();
+    restarted.handle_receiver_report(&stale).unwrap();
+    let replacement = AlnpStreamReceiver::new(node, profile);
+    assert_ne!(replacement.epoch(), receiver.epoch());
+    restarted
+        .handle_receiver_report(&replacement.report().unwrap())
+        .unwrap();
+    assert!(matches!(
+        restarted.handle_receiver_report(&receiver.report().unwrap()),
+        Err(StreamError::SessionMacMismatch)
+    ));
+}
+
+#[tokio::test]
+async fn replayed_old_epochs_never_displace_the_live_one() {
+    let (controller, node) = create_sessions().await;
+    let captured = RecordingTransport::new();
+    let live = RecordingTransport::new();
+    let profile = StreamProfile::auto().compile().unwrap();
+    let old = AlnpStream::new(controller.clone(), captured.clone(), profile.clone());
+    for _ in 0..2 {
+        old.send(ChannelBuffer::U8(vec![1, 2, 3]), 5, None, None)
+            .unwrap();
+    }
+    let stream = AlnpStream::new(controller, live.clone(), profile.clone());
+    assert!(stream.epoch() > old.epoch());
+    for _ in 0..2 {
+        stream
+            .send(ChannelBuffer::U8(vec![4, 5, 6]), 5, None, None)
+            .unwrap();
+    }
+    let captured = captured.snapshots();
+    let live = live.snapshots();
+
+    // A receiver that never saw the old epoch rejects it while following the
+    // live one, and the live stream carries on.
+    let receiver = AlnpStreamReceiver::new(node.clone(), profile.clone());
+    receiver.receive(&live[0]).unwrap();
+    for datagram in &captured {
+        assert!(matches!(
+            receiver.receive(datagram),
+            Err(StreamError::SessionMacMismatch)
+        ));
+    }
+    assert_eq!(receiver.receive(&live[1]).unwrap().sequence, 2);
+
+    // Replayed ahead of any live frame, the old epoch is replaced as soon as
+    // the live sender is heard, instead of locking it out.
+    let fresh = AlnpStreamReceiver::new(node.clone(), profile.clone());
+    fresh.receive(&captured[0]).unwrap();
+    assert_eq!(fresh.receive(&live[0]).unwrap().sequence, 1);
+    assert!(matches!(
+        fresh.receive(&captured[1]),
+        Err(StreamError::SessionMacMismatch)
+    ));
+
+    // Controllers apply the same rule to receiver reports.
+    let stale = fresh.report().unwrap();
+    let replacement = AlnpStreamReceiver::new(node, profile);
+    stream
+        .handle_receiver_report(&replacement.report().unwrap())
+        .unwrap();
+    assert!(matches!(
+        stream.handle_receiver_report(&stale),
+        Err(StreamError::SessionMacMismatch)
+    ));
+    stream
+        .handle_receiver_report(&replacement.report().unwrap())
+        .unwrap();
+}
+
+#[tokio::test]
+async fn receiver_reports_drive_controller_recovery() {
+    let (controller, node) = create_sessions().await;
+    let transport = RecordingTransport::new();
+    let profile = StreamProfile::auto().compile().unwrap();
+    let stream = AlnpStream::new(controller, transport.clone(), profile.clone());
+    for value in 0..8u8 {
+        stream
+            .send(ChannelBuffer::U8(vec![value]), 5, None, None)
+            .unwrap();
+    }
+    let receiver = AlnpStreamReceiver::new(node, profile);
+    let snapshots = transport.snapshots();
+    for index in [0, 1, 7] {
+        receiver.receive(&snapshots[index]).unwrap();
+    }
+
+    let report = stream
+        .handle_receiver_report(&receiver.report().unwrap())
+        .unwrap();
+    assert_eq!(report.last_sequence, 8);
+    assert_eq!(report.max_loss_gap, 5);
+    assert_eq!(stream.last_receiver_report(), Some(report));
+    let metrics = stream.network_metrics().unwrap();
+    assert_eq!(metrics.max_loss_gap, 5);
+    assert!(metrics.rtt_ms.is_some());
+
+    stream
+        .send(ChannelBuffer::U8(vec![9]), 5, None, None)
+        .unwrap();
+    let frame: FrameEnvelope =
+        serde_cbor::from_slice(transport.snapshots().last().unwrap()).unwrap();
+    assert_eq!(frame.kind, FrameKind::Keyframe);
+    let header = frame.adaptation.unwrap();
+    assert!(header.force_keyframe);
+    assert!(header.recovery.is_some());
+    assert_eq!(frame.metadata, None);
+}
+
+#[test]
+fn adaptation_header_encodes_compactly() {
+    let header = AdaptationHeader {
+        keyframe_interval: 6,
+        delta_depth: 0,
+        deadline_offset_ms: -15,
+        frames_since_keyframe: 2,
+        degraded_safe: true,
+        force_keyframe: true,
+        event: Some(AdaptationEvent::EnteredDegradedSafe(
+            DegradedReason::UnrecoverableBurst,
+        )),
+        recovery: Some(RecoveryReason::BurstLoss),
+    };
+    let bytes = serde_cbor::to_vec(&header).unwrap();
+    assert!(bytes.len() <= 10, "{} bytes", bytes.len());
+    assert_eq!(
+        serde_cbor::from_slice::<AdaptationHeader>(&bytes).unwrap(),
+        header
+    );
+    assert!(serde_cbor::from_slice::<AdaptationHeader>(
+        &serde_cbor::to_vec(&(6u8, 0u8, 0i16, 0u8, 0u8, 99u8, 0u8)).unwrap()
+    )
+    .is_err());
+}
+
+#[tokio::test]
+async fn stream_events_explain_recovery_to_subscribers() {
+    let (controller, node) = create_sessions().await;
+    let transport = RecordingTransport::new();
+    let profile = StreamProfile::auto().compile().unwrap();
+    let stream = AlnpStream::new(controller, transport.clone(), profile.clone());
+    let mut events = stream.subscribe_events();
+    for value in 0..8u8 {
+        stream
+            .send(ChannelBuffer::U8(vec![value]), 5, None, None)
+            .unwrap();
+    }
+    let receiver = AlnpStreamReceiver::new(node, profile);
+    let snapshots = transport.snapshots();
+    for index in [0, 7] {
+        receiver.receive(&snapshots[index]).unwrap();
+    }
+    stream
+        .handle_receiver_report(&receiver.report().unwrap())
+        .unwrap();
+
+    let event = events.try_recv().unwrap();
+    assert_eq!(
+        event.kind,
+        StreamEventKind::Recovery(RecoveryEvent::RecoveryStarted(RecoveryReason::BurstLoss))
+    );
+    assert!(event.timestamp_us > 0);
+    assert_eq!(event.metrics.unwrap().max_loss_gap, 6);
+    assert_eq!(event.recovery, Some(RecoveryReason::BurstLoss));
+    while let Ok(event) = events.try_recv() {
+        assert!(matches!(event.kind, StreamEventKind::Adaptation(_)));
+    }
+}
+
+#[tokio::test]
+async fn receiver_reports_reject_forgery_and_replay() {
+    let (controller, node) = create_sessions().await;
+    let profile = StreamProfile::auto().compile().unwrap();
+    let stream = AlnpStream::new(controller, RecordingTransport::new(), profile.clone());
+    let receiver = AlnpStreamReceiver::new(node, profile);
+    let bytes = receiver.report().unwrap();
+
+    let mut forged: ReceiverReport = serde_cbor::from_slice(&bytes).unwrap();
+    forged.loss_ratio = 0.9;
+    let forged = serde_cbor::to_vec(&forged).unwrap();
+    assert!(matches!(
+        stream.handle_receiver_report(&forged),
+        Err(StreamError::SessionMacMismatch)
+    ));
+
+    stream.handle_receiver_report(&bytes).unwrap();
+    assert!(matches!(
+        stream.handle_receiver_report(&bytes),
+        Err(StreamError::SessionMacMismatch)
+    ));
+}
+
+#[tokio::test]
+async fn receiver_sends_periodic_reports_over_feedback_transport() {
+    let (controller, node) = create_sessions().await;
+    let transport = RecordingTransport::new();
+    let feedback = RecordingTransport::new();
+    let profile = StreamProfile::auto().compile().unwrap();
+    let stream = AlnpStream::new(controller, transport.clone(), profile.clone());
+    for value in 0..3u8 {
+        stream
+            .send(ChannelBuffer::U8(vec![value]), 5, None, None)
+            .unwrap();
+    }
+    let receiver = AlnpStreamReceiver::new(node, profile)
+        .with_feedback(feedback.clone(), Duration::from_millis(10));
+    let snapshots = transport.snapshots();
+    receiver.receive_at(&snapshots[0], 0).unwrap();
+    receiver.receive_at(&snapshots[1], 5_000).unwrap();
+    assert!(feedback.snapshots().is_empty());
+    receiver.receive_at(&snapshots[2], 10_000).unwrap();
+
+    let reports = feedback.snapshots();
+    assert_eq!(reports.len(), 1);
+    let report = stream.handle_receiver_report(&reports[0]).unwrap();
+    assert_eq!(report.report_sequence, 1);
+    assert_eq!(report.last_sequence, 3);
+}
+
+#[tokio::test]
+async fn keyframe_requests_force_rate_limited_keyframes() {
+    let (controller, node) = create_sessions().await;
+    let transport = RecordingTransport::new();
+    let feedback = RecordingTransport::new();
+    let profile = StreamProfile::auto().compile().unwrap();
+    let stream = AlnpStream::new(controller, transport.clone(), profile.clone());
+    let mut channels = vec![0u8; 64];
+    for value in 0..4u8 {
+        channels[0] = value;
+        stream
+            .send(ChannelBuffer::U8(channels.clone()), 5, None, None)
+            .unwrap();
+    }
+    let receiver = AlnpStreamReceiver::new(node, profile)
+        .with_feedback(feedback.clone(), Duration::from_secs(60));
+    let snapshots = transport.snapshots();
+    receiver.receive(&snapshots[0]).unwrap();
+    assert!(matches!(
+        receiver.receive(&snapshots[2]),
+        Err(StreamError::DeltaBaseMissing(2))
+    ));
+    assert!(receiver.receive(&snapshots[3]).is_err());
+    let requests = feedback.snapshots();
+    assert_eq!(requests.len(), 1);
+
+    assert_eq!(
+        stream.handle_keyframe_request(&requests[0]).unwrap(),
+        KeyframeRequestOutcome::Scheduled
+    );
+    assert!(matches!(
+        stream.handle_keyframe_request(&requests[0]),
+        Err(StreamError::SessionMacMismatch)
+    ));
+    let again = receiver.keyframe_request(4, Some(3)).unwrap();
+    assert_eq!(
+        stream.handle_keyframe_request(&again).unwrap(),
+        KeyframeRequestOutcome::RateLimited
+    );
+
+    stream
+        .send(ChannelBuffer::U8(channels.clone()), 5, None, None)
+        .unwrap();
+    let bytes = transport.snapshots().pop().unwrap();
+    let frame: FrameEnvelope = serde_cbor::from_slice(&bytes).unwrap();
+    assert_eq!(frame.kind, FrameKind::Keyframe);
+    assert_eq!(
+        receiver.receive(&bytes).unwrap().channels,
+        ChannelBuffer::U8(channels)
+    );
+}
+
+#[derive(Debug)]
+struct KeyframesOnlyPolicy;
+
+impl AdaptationPolicy for KeyframesOnlyPolicy {
+    fn decide(
+        &self,
+        current: &AdaptationState,
+        _metrics: &NetworkMetrics,
+        _recovery: Option<RecoveryReason>,
+        _profile: &CompiledStreamProfile,
+    ) -> AdaptationDecision {
+        let mut state = current.clone();
+        state.delta_depth = 0;
+        AdaptationDecision {
+            state,
+            event: Some(AdaptationEvent::DeltaDisabled),
+        }
+    }
+}
+
+#[tokio::test]
+async fn custom_adaptation_policy_drives_stream() {
+    let (controller, node) = create_sessions().await;
+    let transport = RecordingTransport::new();
+    let profile = StreamProfile::auto().compile().unwrap();
+    let stream = AlnpStream::new(controller, transport.clone(), profile.clone())
+        .with_policy(KeyframesOnlyPolicy);
+    let mut channels = vec![0u8; 32];
+    stream
+        .send(ChannelBuffer::U8(channels.clone()), 5, None, None)
+        .unwrap();
+    channels[3] = 9;
+    stream
+        .send(ChannelBuffer::U8(channels.clone()), 5, None, None)
+        .unwrap();
+
+    let receiver = AlnpStreamReceiver::new(node, profile);
+    stream
+        .handle_receiver_report(&receiver.report().unwrap())
+        .unwrap();
+    channels[4] = 9;
+    stream
+        .send(ChannelBuffer::U8(channels.clone()), 5, None, None)
+        .unwrap();
+
+    let kinds: Vec<FrameKind> = transport
+        .snapshots()
+        .iter()
+        .map(|bytes| serde_cbor::from_slice::<FrameEnvelope>(bytes).unwrap().kind)
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![FrameKind::Keyframe, FrameKind::Delta, FrameKind::Keyframe]
+    );
+}
+
+fn wall(pixels: usize, seed: u16) -> Vec<u16> {
+    (0..pixels * 3)
+        .map(|i| (i as u16).wrapping_mul(31).wrapping_add(seed) | 0x100)
+        .collect()
+}
+
+#[tokio::test]
+async fn large_frames_are_fragmented_and_reassembled() {
+    let (controller, node) = create_sessions_with(wide_caps(), wide_caps()).await;
+    let transport = RecordingTransport::new();
+    let profile = StreamProfile::auto().compile().unwrap();
+    let stream = AlnpStream::new(controller, transport.clone(), profile.clone());
+    let channels = wall(10_000, 0);
+    stream
+        .send(ChannelBuffer::U16(channels.clone()), 5, None, None)
+        .unwrap();
+
+    let mut datagrams = transport.snapshots();
+    assert!(datagrams.len() > 1);
+    assert!(datagrams
+        .iter()
+        .all(|bytes| bytes.len() <= DEFAULT_MAX_DATAGRAM_SIZE));
+    datagrams.reverse();
+
+    let receiver = AlnpStreamReceiver::new(node, profile);
+    let last = datagrams.pop().unwrap();
+    for bytes in &datagrams {
+        assert!(matches!(
+            receiver.receive(bytes),
+            Err(StreamError::FragmentPending)
+        ));
+    }
+    let frame = receiver.receive(&last).unwrap();
+    assert_eq!(frame.sequence, 1);
+    assert_eq!(frame.channels, ChannelBuffer::U16(channels));
+    assert!(matches!(
+        receiver.receive(&last),
+        Err(StreamError::SessionMacMismatch)
+    ));
+}
+
+#[tokio::test]
+async fn frames_missing_fragments_are_dropped_not_applied() {
+    let (controller, node) = create_sessions_with(wide_caps(), wide_caps()).await;
+    let transport = RecordingTransport::new();
+    let profile = StreamProfile::auto().compile().unwrap();
+    let stream =
+        AlnpStream::new(controller, transport.clone(), profile.clone()).with_max_datagram_size(512);
+    let receiver = AlnpStreamReceiver::new(node, profile);
+    for (pixels, drop_one) in [(400, false), (401, true), (402, false)] {
+        let before = transport.snapshots().len();
+        stream
+            .send(ChannelBuffer::U16(wall(pixels, 7)), 5, None, None)
+            .unwrap();
+        let datagrams = transport.snapshots().split_off(before);
+        assert!(datagrams.iter().all(|bytes| bytes.len() <= 512));
+        let skip = if drop_one { 1 } else { datagrams.len() };
+        for (index, bytes) in datagrams.iter().enumerate() {
+            if index == skip {
+                continue;
+            }
+            match receiver.enqueue_at(bytes, 0).unwrap() {
+                PlayoutPush::Incomplete => {}
+                push => assert_eq!(push, PlayoutPush::Scheduled),
+            }
+        }
+    }
+
+    let mut played = Vec::new();
+    while let Some(frame) = receiver.poll_playout(u64::MAX).unwrap() {
+        if !frame.is_concealed() {
+            played.push((frame.sequence(), frame.channels().len()));
+        }
+    }
+    assert_eq!(played, vec![(1, 1_200), (3, 1_206)]);
+    assert!(receiver.metrics().loss_ratio > 0.0);
+}
+
+#[tokio::test]
+async fn parity_rebuilds_a_single_lost_frame() {
+    let (controller, node) = create_sessions().await;
+    let transport = RecordingTransport::new();
+    let profile = StreamProfile::install().compile().unwrap();
+    assert_eq!(profile.fec_group_size(), 4);
+    let stream = AlnpStream::new(controller, transport.clone(), profile.clone());
+    for value in 1..=4u8 {
+        stream
+            .send(ChannelBuffer::U8(vec![value; 4]), 5, None, None)
+            .unwrap();
+    }
+    let datagrams = transport.snapshots();
+    assert_eq!(datagrams.len(), 5);
+
+    let receiver = AlnpStreamReceiver::new(node, profile);
+    for index in [0, 2, 3] {
+        receiver.receive(&datagrams[index]).unwrap();
+    }
+    let rebuilt = receiver.receive(&datagrams[4]).unwrap();
+    assert_eq!(rebuilt.sequence, 2);
+    assert_eq!(u8_values(&rebuilt.channels), [2; 4]);
+    assert!(matches!(
+        receiver.receive(&datagrams[4]),
+        Err(StreamError::ParityUnused)
+    ));
+    assert!(matches!(
+        receiver.receive(&datagrams[1]),
+        Err(StreamError::SessionMacMismatch)
+    ));
+}
+
+#[tokio::test]
+async fn realtime_profile_sends_no_parity() {
+    let (controller, _node) = create_sessions().await;
+    let transport = RecordingTransport::new();
+    let profile = StreamProfile::realtime().compile().unwrap();
+    let stream = AlnpStream::new(controller, transport.clone(), profile);
+    for value in 0..16u8 {
+        stream
+            .send(ChannelBuffer::U8(vec![value]), 5, None, None)
+            .unwrap();
+    }
+    assert_eq!(transport.snapshots().len(), 16);
+}
+
+#[tokio::test]
+async fn partial_updates_send_only_changed_ranges() {
+    let (controller, node) = create_sessions_with(wide_caps(), wide_caps()).await;
+    let transport = RecordingTransport::new();
+    let profile = StreamProfile::realtime().compile().unwrap();
+    let stream = AlnpStream::new(controller, transport.clone(), profile.clone());
+    let cue = |value| {
+        vec![ChannelRange {
+            offset: 4_000,
+            values: ChannelBuffer::U8(vec![value, value, value]),
+        }]
+    };
+    assert!(matches!(
+        stream.send_partial(ChannelFormat::U8, cue(1), 5, None, None),
+        Err(StreamError::PartialBaseMissing)
+    ));
+    let mut expected = vec![0u8; 8_000];
+    stream
+        .send(ChannelBuffer::U8(expected.clone()), 5, None, None)
+        .unwrap();
+    let full = transport.snapshots().len();
+    assert!(full > 1);
+    for value in 1..=20u8 {
+        stream
+            .send_partial(ChannelFormat::U8, cue(value), 5, None, None)
+            .unwrap();
+    }
+    assert!(matches!(
+        stream.send_partial(
+            ChannelFormat::U8,
+            vec![ChannelRange {
+                offset: 7_999,
+                values: ChannelBuffer::U8(vec![1, 1]),
+            }],
+            5,
+            None,
+            None
+        ),
+        Err(StreamError::PartialRangeOutOfBounds(7_999))
+    ));
+
+    let datagrams = transport.snapshots();
+    let receiver = AlnpStreamReceiver::new(node, profile);
+    let mut partial_frames = 0;
+    let mut refreshes = 0;
+    for bytes in datagrams.iter().rev() {
+        receiver.enqueue_at(bytes, 0).unwrap();
+        if let Ok(frame) = serde_cbor::from_slice::<FrameEnvelope>(bytes) {
+            if frame.kind == FrameKind::Delta {
+                partial_frames += 1;
+                assert!(bytes.len() < 400, "{}", bytes.len());
+            }
+        }
+    }
+    for bytes in &datagrams[full..] {
+        if serde_cbor::from_slice::<FrameEnvelope>(bytes).is_err() {
+            refreshes += 1;
+        }
+    }
+    assert!(partial_frames > 0);
+    assert!(refreshes > 0, "keyframe cadence refreshes the full state");
+
+    let mut played = Vec::new();
+    while let Some(frame) = receiver.poll_playout(u64::MAX).unwrap() {
+        played.push((frame.sequence(), u8_values(frame.channels()).to_vec()));
+    }
+    assert_eq!(played.len(), 21);
+    for (sequence, channels) in played {
+        if sequence > 1 {
+            expected[4_000..4_003].fill(sequence as u8 - 1);
+        }
+        assert_eq!(channels, expected);
+    }
+}
+
+#[tokio::test]
+async fn channel_groups_set_members_and_mask_partial_updates() {
+    let node_caps = CapabilitySet {
+        grouping_supported: true,
+        ..CapabilitySet::default()
+    };
+    let (controller, node) = create_sessions_with(CapabilitySet::default(), node_caps).await;
+    let session_id = controller.established().unwrap().session_id;
+    let keys = controller.keys().unwrap();
+    let client = ControlClient::new(Uuid::new_v4(), session_id, ControlCrypto::new(keys.clone()));
+    let responder = ControlResponder::new(session_id, ControlCrypto::new(keys));
+    let groups = ChannelGroups::new()
+        .with_group("wash", (100..200).collect())
+        .with_group("spot", vec![7, 8, 9]);
+    let envelope = client
+        .envelope(
+            1,
+            ControlOp::DefineGroups,
+            serde_json::to_value(&groups).unwrap(),
+        )
+        .unwrap();
+
+    // Node side: authenticate the declaration and install it.
+    responder.verify(&envelope).unwrap();
+    let profile = StreamProfile::realtime().compile().unwrap();
+    let receiver = AlnpStreamReceiver::new(node, profile.clone());
+    receiver.define_groups(serde_json::from_value(envelope.payload).unwrap());
+
+    let transport = RecordingTransport::new();
+    let stream = AlnpStream::new(controller, transport.clone(), profile);
+    stream.define_groups(groups);
+    let mut expected = vec![0u8; 256];
+    stream
+        .send(ChannelBuffer::U8(expected.clone()), 5, None, None)
+        .unwrap();
+    let assign = |entries: &[(&str, Vec<u8>)]| {
+        Some(
+            entries
+                .iter()
+                .map(|(id, values)| (id.to_string(), ChannelBuffer::U8(values.clone())))
+                .collect(),
+        )
+    };
+    stream
+        .send_partial(
+            ChannelFormat::U8,
+            vec![ChannelRange {
+                offset: 0,
+                values: ChannelBuffer::U8(vec![1, 2]),
+            }],
+            5,
+            assign(&[("wash", vec![255]), ("spot", vec![10, 20, 30])]),
+            None,
+        )
+        .unwrap();
+    expected[..2].copy_from_slice(&[1, 2]);
+    expected[100..200].fill(255);
+    expected[7..10].copy_from_slice(&[10, 20, 30]);
+
+    let datagrams = transport.snapshots();
+    receiver.receive(&datagrams[0]).unwrap();
+    let wire: FrameEnvelope = serde_cbor::from_slice(&datagrams[1]).unwrap();
+    assert_eq!(wire.kind, FrameKind::Delta);
+    // Only the two explicit channels travel as values; the groups carry the rest.
+    let sent: usize = wire.delta.iter().flatten().map(|r| r.values.len()).sum();
+    assert_eq!(sent, 2);
+    assert_eq!(
+        receiver.receive(&datagrams[1]).unwrap().channels,
+        ChannelBuffer::U8(expected.clone())
+    );
+
+    // A group update on its own leaves every other channel as it was.
+    stream
+        .send_partial(
+            ChannelFormat::U8,
+            Vec::new(),
+            5,
+            assign(&[("wash", vec![7])]),
+            None,
+        )
+        .unwrap();
+    expected[100..200].fill(7);
+    let datagrams = transport.snapshots();
+    assert_eq!(
+        receiver.receive(&datagrams[2]).unwrap().channels,
+        ChannelBuffer::U8(expected.clone())
+    );
+
+    assert!(matches!(
+        stream.send_partial(
+            ChannelFormat::U8,
+            Vec::new(),
+            5,
+            assign(&[("fog", vec![1])]),
+            None
+        ),
+        Err(StreamError::UnknownGroup(id)) if id == "fog"
+    ));
+}
+
+#[tokio::test]
+async fn channel_groups_require_peer_support() {
+    let (controller, _node) = create_sessions().await;
+    let transport = RecordingTransport::new();
+    let profile = StreamProfile::realtime().compile().unwrap();
+    let stream = AlnpStream::new(controller, transport.clone(), profile);
+    stream.define_groups(ChannelGroups::new().with_group("wash", vec![0, 1]));
+    let groups = Some(
+        [("wash".to_string(), ChannelBuffer::U8(vec![9]))]
+            .into_iter()
+            .collect(),
+    );
+    assert!(matches!(
+        stream.send(ChannelBuffer::U8(vec![0; 4]), 5, groups, None),
+        Err(StreamError::GroupingUnsupported)
+    ));
+    assert!(transport.snapshots().is_empty());
+}
+
+#[tokio::test]
+async fn frames_are_validated_against_negotiated_capabilities() {
+    let (controller, node) = create_sessions().await;
+    let transport = RecordingTransport::new();
+    let profile = StreamProfile::realtime().compile().unwrap();
+    let stream = AlnpStream::new(controller, transport.clone(), profile.clone());
+    stream.define_groups(ChannelGroups::new().with_group("wash", vec![0, 1]));
+
+    let err = stream
+        .send(ChannelBuffer::U16(vec![0; 4]), 5, None, None)
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        StreamError::UnsupportedChannelFormat(ChannelFormat::U16)
+    ));
+    assert_eq!(err.error_code(), Some(ErrorCode::StreamBadFormat));
+    let err = stream
+        .send(ChannelBuffer::U8(vec![0; 513]), 5, None, None)
+        .unwrap_err();
+    assert!(matches!(err, StreamError::TooManyChannels(513, 512)));
+    assert_eq!(err.error_code(), Some(ErrorCode::StreamTooLarge));
+    let groups = Some(
+        [("wash".to_string(), ChannelBuffer::U8(vec![9]))]
+            .into_iter()
+            .collect(),
+    );
+    let err = stream
+        .send(ChannelBuffer::U8(vec![0; 4]), 5, groups, None)
+        .unwrap_err();
+    assert!(matches!(err, StreamError::GroupingUnsupported));
+    assert_eq!(
+        err.error_code(),
+        Some(ErrorCode::StreamUnsupportedChannelMode)
+    );
+    assert!(transport.snapshots().is_empty());
+
+    // Rejected frames consume no sequence.
+    stream
+        .send(ChannelBuffer::U8(vec![0; 512]), 5, None, None)
+        .unwrap();
+    let receiver = AlnpStreamReceiver::new(node, profile.clone());
+    assert_eq!(
+        receiver
+            .receive(&transport.snapshots()[0])
+            .unwrap()
+            .sequence,
+        1
+    );
+
+    // A sender that ignores what the node advertised is rejected on receive.
+    let (_controller, node) = create_sessions_with(wide_caps(), CapabilitySet::default()).await;
+    let transport = RecordingTransport::new();
+    let looped = AlnpStream::new(node.clone(), transport.clone(), profile.clone());
+    looped
+        .send(ChannelBuffer::U16(vec![0; 4]), 5, None, None)
+        .unwrap();
+    looped
+        .send(ChannelBuffer::U8(vec![0; 600]), 5, None, None)
+        .unwrap();
+    let receiver = AlnpStreamReceiver::new(node, profile);
+    let datagrams = transport.snapshots();
+    assert!(matches!(
+        receiver.receive(&datagrams[0]),
+        Err(StreamError::UnsupportedChannelFormat(ChannelFormat::U16))
+    ));
+    assert!(matches!(
+        receiver.receive(&datagrams[1]),
+        Err(StreamError::TooManyChannels(600, 512))
+    ));
+}
+
+#[tokio::test]
+async fn extended_channel_formats_stream_as_typed_buffers() {
+    let caps = CapabilitySet {
+        channel_formats: vec![
+            ChannelFormat::U8,
+            ChannelFormat::U24,
+            ChannelFormat::F32,
+            ChannelFormat::Rgb8,
+        ],
+        ..CapabilitySet::default()
+    };
+    let (controller, node) = create_sessions_with(caps.clone(), caps).await;
+    let transport = RecordingTransport::new();
+    let profile = StreamProfile::realtime().compile().unwrap();
+    let stream = AlnpStream::new(controller, transport.clone(), profile.clone());
+
+    let mut fine = vec![0u32; 64];
+    stream
+        .send(ChannelBuffer::U24(fine.clone()), 5, None, None)
+        .unwrap();
+    fine[3] = 0x12_3456;
+    stream
+        .send(ChannelBuffer::U24(fine.clone()), 5, None, None)
+        .unwrap();
+    stream.send(vec![0.0f32, 0.5, 1.0], 5, None, None).unwrap();
+    let pixels = ChannelBuffer::U8(vec![255, 0, 0, 0, 255, 0])
+        .convert(ChannelFormat::Rgb8)
+        .unwrap();
+    stream.send(pixels, 5, None, None).unwrap();
+
+    let receiver = AlnpStreamReceiver::new(node, profile);
+    let frames: Vec<FrameEnvelope> = transport
+        .snapshots()
+        .iter()
+        .map(|bytes| receiver.receive(bytes).unwrap())
+        .collect();
+    assert_eq!(frames[1].kind, FrameKind::Delta);
+    assert_eq!(frames[1].channels, ChannelBuffer::U24(fine));
+    assert_eq!(frames[2].channels.format(), ChannelFormat::F32);
+    assert_eq!(frames[2].channels, ChannelBuffer::F32(vec![0.0, 0.5, 1.0]));
+    assert_eq!(
+        frames[3].channels,
+        ChannelBuffer::Rgb8(vec![[255, 0, 0], [0, 255, 0]])
+    );
+
+    // Every pixel component counts against max_channels.
+    assert!(matches!(
+        stream.send(ChannelBuffer::Rgb8(vec![[0; 3]; 171]), 5, None, None),
+        Err(StreamError::TooManyChannels(513, 512))
+    ));
+    assert!(matches!(
+        stream.send(ChannelBuffer::Rgbw8(vec![[0; 4]; 4]), 5, None, None),
+        Err(StreamError::UnsupportedChannelFormat(ChannelFormat::Rgbw8))
+    ));
+}
+
+struct DownTransport;
+
+impl FrameTransport for DownTransport {
+    fn send_frame(&self, _bytes: &[u8]) -> Result<(), String> {
+        Err("link down".into())
+    }
+}
+
+#[tokio::test]
+async fn send_with_reuses_buffers_and_matches_send() {
+    let (controller, node) = create_sessions().await;
+    let transport = RecordingTransport::new();
+    let profile = StreamProfile::realtime().compile().unwrap();
+    let stream = AlnpStream::new(controller, transport.clone(), profile.clone());
+    let mut buf = FrameBuf::with_channels(ChannelFormat::U8, 512);
+    stream.send_with(&mut buf, 5).unwrap();
+    let wire = buf.datagram().as_ptr();
+    let receiver = AlnpStreamReceiver::new(node, profile);
+    assert_eq!(
+        receiver.receive(buf.datagram()).unwrap().channels,
+        ChannelBuffer::U8(vec![0; 512])
+    );
+    for value in 1..=10u8 {
+        if let ChannelBuffer::U8(values) = buf.channels_mut() {
+            values[value as usize] = value;
+        }
+        stream.send_with(&mut buf, 5).unwrap();
+        assert_eq!(buf.datagram(), transport.snapshots().last().unwrap());
+        assert_eq!(buf.datagram().as_ptr(), wire, "datagram buffer reused");
+        let frame = receiver.receive(buf.datagram()).unwrap();
+        assert_eq!(&frame.channels, buf.channels());
+    }
+    let delta: FrameEnvelope = serde_cbor::from_slice(buf.datagram()).unwrap();
+    assert_eq!(delta.kind, FrameKind::Delta);
+
+    // `send` and `send_with` share one sequence and delta history.
+    let mut channels = u8_values(buf.channels()).to_vec();
+    channels[100] = 100;
+    stream
+        .send(ChannelBuffer::U8(channels.clone()), 5, None, None)
+        .unwrap();
+    let frame = receiver
+        .receive(transport.snapshots().last().unwrap())
+        .unwrap();
+    assert_eq!(frame.sequence, 12);
+    assert_eq!(u8_values(&frame.channels), channels);
+}
+
+#[tokio::test(start_paused = true)]
+async fn pacer_coalesces_updates_and_refreshes_when_idle() {
+    let node_caps = CapabilitySet {
+        max_frame_rate_hz: Some(60),
+        ..CapabilitySet::default()
+    };
+    let (controller, node) = create_sessions_with(CapabilitySet::default(), node_caps).await;
+    let transport = RecordingTransport::new();
+    let profile = StreamProfile::realtime().compile().unwrap();
+    let stream = AlnpStream::new(controller, transport.clone(), profile.clone());
+    let pacer = FramePacer::new(stream, 120)
+        .with_idle_refresh(Duration::from_millis(100))
+        .spawn()
+        .unwrap();
+    assert_eq!(pacer.rate_hz(), 60, "capped by the node's capabilities");
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(
+        transport.snapshots().is_empty(),
+        "nothing sent before an update"
+    );
+    for value in 1..=10u8 {
+        pacer.update(&ChannelBuffer::U8(vec![value; 16]));
+    }
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert_eq!(transport.snapshots().len(), 1);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(transport.snapshots().len(), 1, "idle ticks send nothing");
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(
+        pacer.stats(),
+        PacerStats {
+            frames_sent: 2,
+            updates_coalesced: 9,
+            idle_refreshes: 1,
+            send_errors: 0,
+        }
+    );
+
+    let receiver = AlnpStreamReceiver::new(node, profile);
+    for bytes in transport.snapshots() {
+        let wire: FrameEnvelope = serde_cbor::from_slice(&bytes).unwrap();
+        assert_eq!(wire.kind, FrameKind::Keyframe);
+        assert_eq!(
+            receiver.receive(&bytes).unwrap().channels,
+            ChannelBuffer::U8(vec![10; 16])
+        );
+    }
+    let stream = pacer.stop().await;
+    stream
+        .send(ChannelBuffer::U8(vec![0; 16]), 5, None, None)
+        .unwrap();
+}
+
+#[tokio::test]
+async fn pacer_rejects_a_zero_rate() {
+    let (controller, _) = create_sessions().await;
+    let profile = StreamProfile::realtime().compile().unwrap();
+    let stream = AlnpStream::new(controller, RecordingTransport::new(), profile);
+    assert!(matches!(
+        FramePacer::new(stream, 0).spawn(),
+        Err(StreamError::ZeroFrameRate)
+    ));
+}
+
+struct TokioUdpTransport {
+    socket: tokio::net::UdpSocket,
+    peer: std::net::SocketAddr,
+}
+
+#[async_trait]
+impl AsyncFrameTransport for TokioUdpTransport {
+    async fn send_frame(&self, bytes: &[u8]) -> Result<(), String> {
+        self.socket
+            .send_to(bytes, self.peer)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[tokio::test]
+async fn async_stream_sends_over_tokio_udp() {
+    let (controller, node) = create_sessions().await;
+    let inbound = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let transport = TokioUdpTransport {
+        socket: tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap(),
+        peer: inbound.local_addr().unwrap(),
+    };
+    let profile = StreamProfile::auto().compile().unwrap();
+    let stream = AsyncAlnpStream::new(controller, transport, profile.clone());
+    let receiver = AlnpStreamReceiver::new(node, profile);
+
+    let mut buf = [0u8; 2048];
+    for value in 0..10u8 {
+        stream
+            .send(ChannelBuffer::U8(vec![value; 32]), 5, None, None)
+            .await
+            .unwrap();
+        let frame = loop {
+            let len = inbound.recv(&mut buf).await.unwrap();
+            match receiver.receive(&buf[..len]) {
+                Err(StreamError::ParityUnused) => continue,
+                result => break result.unwrap(),
+            }
+        };
+        assert_eq!(frame.sequence, value as u64 + 1);
+        assert_eq!(u8_values(&frame.channels), [value; 32]);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn transport_adapters_work_in_both_directions() {
+    let (controller, node) = create_sessions().await;
+    let profile = StreamProfile::realtime().compile().unwrap();
+    let receiver = AlnpStreamReceiver::new(node.clone(), profile.clone());
+
+    let recorded = RecordingTransport::new();
+    let stream = AsyncAlnpStream::new(
+        controller.clone(),
+        AsyncAdapter::new(recorded.clone()),
+        profile.clone(),
+    )
+    .with_redundant_path(AsyncAdapter::new(DownTransport));
+    stream
+        .send(ChannelBuffer::U8(vec![1, 2, 3]), 5, None, None)
+        .await
+        .unwrap();
+    let mut buf = FrameBuf::with_channels(ChannelFormat::U8, 3);
+    *buf.channels_mut() = ChannelBuffer::U8(vec![9, 0, 0]);
+    stream.send_with(&mut buf, 5).await.unwrap();
+
+    let blocking = recorded.clone();
+    let runtime = tokio::runtime::Handle::current();
+    let blocking_profile = profile.clone();
+    tokio::task::spawn_blocking(move || {
+        let transport = BlockingAdapter::new(AsyncAdapter::new(blocking), runtime);
+        let stream = AlnpStream::new(controller, transport, blocking_profile);
+        stream
+            .send(ChannelBuffer::U8(vec![4, 5, 6]), 5, None, None)
+            .unwrap();
+    })
+    .await
+    .unwrap();
+
+    let snapshots = recorded.snapshots();
+    let frames: Vec<_> = snapshots[..2]
+        .iter()
+        .map(|bytes| receiver.receive(bytes).unwrap().channels)
+        .collect();
+    assert_eq!(
+        frames,
+        vec![
+            ChannelBuffer::U8(vec![1, 2, 3]),
+            ChannelBuffer::U8(vec![9, 0, 0])
+        ]
+    );
+    // The blocking stream restarts at sequence 1, so it needs its own receiver.
+    let receiver = AlnpStreamReceiver::new(node, profile);
+    assert_eq!(
+        receiver.receive(&snapshots[2]).unwrap().channels,
+        ChannelBuffer::U8(vec![4, 5, 6])
+    );
+}
+
+#[tokio::test]
+async fn async_streams_send_channel_groups() {
+    let node_caps = CapabilitySet {
+        grouping_supported: true,
+        ..CapabilitySet::default()
+    };
+    let (controller, node) = create_sessions_with(CapabilitySet::default(), node_caps).await;
+    let groups = ChannelGroups::new().with_group("wash", vec![1, 2]);
+    let profile = StreamProfile::realtime().compile().unwrap();
+    let receiver = AlnpStreamReceiver::new(node, profile.clone());
+    receiver.define_groups(groups.clone());
+
+    let recorded = RecordingTransport::new();
+    let stream = AsyncAlnpStream::new(controller, AsyncAdapter::new(recorded.clone()), profile);
+    stream.define_groups(groups);
+    let assign = Some(
+        [("wash".to_string(), ChannelBuffer::U8(vec![9]))]
+            .into_iter()
+            .collect(),
+    );
+    stream
+        .send(ChannelBuffer::U8(vec![0; 4]), 5, assign, None)
+        .await
+        .unwrap();
+    assert_eq!(
+        receiver.receive(&recorded.snapshots()[0]).unwrap().channels,
+        ChannelBuffer::U8(vec![0, 9, 9, 0])
+    );
+}
+
+#[tokio::test]
+async fn streams_share_a_session_with_independent_sequences() {
+    let (controller, node) = create_sessions_with(wide_caps(), wide_caps()).await;
+    let transport = RecordingTransport::new();
+    let realtime = StreamProfile::realtime().compile().unwrap();
+    let install = StreamProfile::install().compile().unwrap();
+    let fixtures = AlnpStream::new(controller.clone(), transport.clone(), realtime.clone());
+    let ambient = AlnpStream::new(controller, transport.clone(), install.clone()).with_stream_id(1);
+    for value in 1..=3u8 {
+        fixtures
+            .send(ChannelBuffer::U8(vec![value; 4]), 5, None, None)
+            .unwrap();
+        ambient
+            .send(
+                ChannelBuffer::U16(vec![u16::from(value) * 100; 2]),
+                1,
+                None,
+                None,
+            )
+            .unwrap();
+    }
+
+    let demux = StreamDemux::new()
+        .with_stream(AlnpStreamReceiver::new(node.clone(), realtime))
+        .with_stream(AlnpStreamReceiver::new(node.clone(), install.clone()).with_stream_id(1));
+    let mut received = Vec::new();
+    for bytes in transport.snapshots() {
+        match demux.receive(&bytes) {
+            Ok((stream_id, frame)) => {
+                let first = match &frame.channels {
+                    ChannelBuffer::U8(values) => u16::from(values[0]),
+                    ChannelBuffer::U16(values) => values[0],
+                    other => panic!("unexpected format {:?}", other.format()),
+                };
+                received.push((stream_id, frame.sequence, first))
+            }
+            Err(StreamError::ParityUnused) => {}
+            Err(err) => panic!("unexpected error: {err}"),
+        }
+    }
+    assert_eq!(
+        received,
+        vec![
+            (0, 1, 1),
+            (1, 1, 100),
+            (0, 2, 2),
+            (1, 2, 200),
+            (0, 3, 3),
+            (1, 3, 300)
+        ]
+    );
+
+    // Each receiver only accepts its own stream.
+    let first = transport.snapshots()[1].clone();
+    assert_eq!(peek_stream_id(&first).unwrap(), 1);
+    let default_only = AlnpStreamReceiver::new(node, install);
+    assert!(matches!(
+        default_only.receive(&first),
+        Err(StreamError::StreamMismatch(1))
+    ));
+    assert!(matches!(
+        StreamDemux::new().receive(&first),
+        Err(StreamError::UnknownStream(1))
+    ));
+
+    // Feedback carries the stream id back to the matching sender.
+    let report = demux.stream(1).unwrap().report().unwrap();
+    assert_eq!(peek_stream_id(&report).unwrap(), 1);
+    assert!(matches!(
+        fixtures.handle_receiver_report(&report),
+        Err(StreamError::StreamMismatch(1))
+    ));
+    assert_eq!(
+        ambient
+            .handle_receiver_report(&report)
+            .unwrap()
+            .last_sequence,
+        3
+    );
+}
+
+#[tokio::test]
+async fn duplicate_stream_ids_never_share_a_key() {
+    let (controller, node) = create_sessions().await;
+    let profile = StreamProfile::auto().compile().unwrap();
+    let first_transport = RecordingTransport::new();
+    let second_transport = RecordingTransport::new();
+    let first = AlnpStream::new(controller.clone(), first_transport.clone(), profile.clone())
+        .with_stream_id(3);
+    let second =
+        AlnpStream::new(controller, second_transport.clone(), profile.clone()).with_stream_id(3);
+    for stream in [&first, &second] {
+        for _ in 0..2 {
+            stream
+                .send(ChannelBuffer::U8(vec![1, 2, 3]), 5, None, None)
+                .unwrap();
+        }
+    }
+    let first_frames = first_transport.snapshots();
+    let second_frames = second_transport.snapshots();
+    let a: FrameEnvelope = serde_cbor::from_slice(&first_frames[0]).unwrap();
+    let b: FrameEnvelope = serde_cbor::from_slice(&second_frames[0]).unwrap();
+    assert_eq!((a.stream_id, a.sequence), (b.stream_id, b.sequence));
+    assert_ne!(a.epoch, b.epoch);
+    assert_ne!(a.mac, b.mac);
+
+    // A receiver follows the newest sender it authenticated and rejects the other.
+    let receiver = AlnpStreamReceiver::new(node, profile).with_stream_id(3);
+    receiver.receive(&first_frames[0]).unwrap();
+    receiver.receive(&second_frames[0]).unwrap();
+    assert!(matches!(
+        receiver.receive(&first_frames[1]),
+        Err(StreamError::SessionMacMismatch)
+    ));
+    assert_eq!(receiver.receive(&second_frames[1]).unwrap().sequence, 2);
+}
+
+#[tokio::test]
+async fn redundant_paths_merge_without_gaps_or_duplicates() {
+    let (controller, node) = create_sessions().await;
+    let primary = RecordingTransport::new();
+    let backup = RecordingTransport::new();
+    let profile = StreamProfile::realtime().compile().unwrap();
+    let stream = AlnpStream::new(controller, primary.clone(), profile.clone())
+        .with_redundant_path(backup.clone())
+        .with_redundant_path(DownTransport);
+    for value in 1..=8u8 {
+        stream
+            .send(ChannelBuffer::U8(vec![value; 4]), 5, None, None)
+            .unwrap();
+    }
+    let (primary, backup) = (primary.snapshots(), backup.snapshots());
+    assert_eq!(primary, backup);
+
+    let receiver = AlnpStreamReceiver::new(node, profile).with_paths(2);
+    let mut sequences = Vec::new();
+    for (index, (first, second)) in primary.iter().zip(&backup).enumerate() {
+        // Path 0 loses every other frame; path 1 delivers everything late.
+        if index % 2 == 1 {
+            sequences.push(receiver.receive_on_at(0, first, 0).unwrap().sequence);
+        }
+        match receiver.receive_on_at(1, second, 0) {
+            Ok(frame) => sequences.push(frame.sequence),
+            Err(StreamError::DuplicateFrame(sequence)) => assert_eq!(sequence, index as u64 + 1),
+            Err(err) => panic!("unexpected error: {err}"),
+        }
+    }
+    assert_eq!(sequences, (1..=8).collect::<Vec<u64>>());
+    assert_eq!(receiver.metrics().loss_ratio, 0.0);
+    let paths = receiver.path_metrics();
+    assert!(paths[0].loss_ratio > 0.0);
+    assert_eq!(paths[1].loss_ratio, 0.0);
+    assert!(matches!(
+        receiver.receive_on(2, &primary[0]),
+        Err(StreamError::UnknownPath(2))
+    ));
+
+    let report: ReceiverReport = serde_cbor::from_slice(&receiver.report().unwrap()).unwrap();
+    assert_eq!(report.path_loss_ratios.len(), 2);
+    assert_eq!(report.loss_ratio, 0.0);
+}
+
+#[tokio::test]
+async fn single_path_receivers_still_reject_repeats() {
+    let (controller, node) = create_sessions().await;
+    let transport = RecordingTransport::new();
+    let profile = StreamProfile::realtime().compile().unwrap();
+    let stream = AlnpStream::new(controller, transport.clone(), profile.clone())
+        .with_redundant_path(transport.clone());
+    stream
+        .send(ChannelBuffer::U8(vec![1; 4]), 5, None, None)
+        .unwrap();
+    let datagrams = transport.snapshots();
+    assert_eq!(datagrams.len(), 2);
+
+    let receiver = AlnpStreamReceiver::new(node, profile);
+    receiver.receive(&datagrams[0]).unwrap();
+    assert!(matches!(
+        receiver.receive(&datagrams[1]),
+        Err(StreamError::SessionMacMismatch)
+    ));
+}
+
+#[tokio::test]
+async fn confidential_profile_encrypts_frame_payloads() {
+    let (controller, node) = create_sessions().await;
+    let transport = RecordingTransport::new();
+    let profile = StreamProfile::auto()
+        .with_confidentiality(true)
+        .compile()
+        .unwrap();
+    let stream = AlnpStream::new(controller, transport.clone(), profile.clone());
+    stream
+        .send(ChannelBuffer::U8(vec![7, 8, 9]), 5, None, None)
+        .unwrap();
+    let bytes = transport.snapshots().remove(0);
+    let wire: FrameEnvelope = serde_cbor::from_slice(&bytes).unwrap();
+    assert!(wire.channels.is_empty());
+    assert!(wire.ciphertext.is_some());
+
+    let receiver = AlnpStreamReceiver::new(node, profile);
+    let frame = receiver.receive(&bytes).unwrap();
+    assert_eq!(u8_values(&frame.channels), [7, 8, 9]);
+    assert!(frame.ciphertext.is_none());
+}
+
+#[tokio::test]
+async fn confidential_profile_requires_negotiated_encryption() {
+    let node_caps = CapabilitySet {
+        encryption_supported: false,
+        ..CapabilitySet::default()
+    };
+    let (controller, _) = create_sessions_with(CapabilitySet::default(), node_caps).await;
+    assert!(!controller.encryption_negotiated());
+    let profile = StreamProfile::auto()
+        .with_confidentiality(true)
+        .compile()
+        .unwrap();
+    let stream = AlnpStream::new(controller, RecordingTransport::new(), profile);
+    let result = stream.send(ChannelBuffer::U8(vec![1]), 5, None, None);
+    assert!(matches!(result, Err(StreamError::EncryptionUnavailable)));
 }
 
 #[test]
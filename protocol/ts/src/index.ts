@@ -18,6 +18,10 @@ export enum MessageType {
 export enum ChannelFormat {
   U8 = "u8",
   U16 = "u16",
+  U24 = "u24",
+  F32 = "f32",
+  Rgb8 = "rgb8",
+  Rgbw8 = "rgbw8",
 }
 
 export enum ControlOp {
@@ -29,6 +33,7 @@ export enum ControlOp {
   SetConfig = "set_config",
   SetMode = "set_mode",
   TimeSync = "time_sync",
+  DefineGroups = "define_groups",
   Vendor = "vendor",
 }
 
@@ -57,6 +62,7 @@ export interface CapabilitySet {
   grouping_supported: boolean;
   streaming_supported: boolean;
   encryption_supported: boolean;
+  max_frame_rate_hz?: number;
   vendor_extensions?: Record<string, unknown>;
 }
 
@@ -164,35 +170,120 @@ export interface Acknowledge {
   mac: Uint8Array;
 }
 
+export enum FrameKind {
+  Keyframe = "keyframe",
+  Delta = "delta",
+}
+
+/**
+ * `[keyframe_interval, delta_depth, deadline_offset_ms, frames_since_keyframe,
+ * flags, event, recovery]`; `event`/`recovery` are 0 when none.
+ */
+export type AdaptationHeader = [number, number, number, number, number, number, number];
+
+/** Bytes per channel slot on the wire for each format. */
+export const CHANNEL_SLOT_WIDTH: Record<ChannelFormat, number> = {
+  [ChannelFormat.U8]: 1,
+  [ChannelFormat.U16]: 2,
+  [ChannelFormat.U24]: 3,
+  [ChannelFormat.F32]: 4,
+  [ChannelFormat.Rgb8]: 3,
+  [ChannelFormat.Rgbw8]: 4,
+};
+
+/**
+ * Packs channel values into the big-endian byte string carried on the wire.
+ * `rgb8`/`rgbw8` take a flat list of component bytes (R, G, B[, W] per slot).
+ */
+export function packChannels(format: ChannelFormat, values: number[]): Uint8Array {
+  const packedRgb = format === ChannelFormat.Rgb8 || format === ChannelFormat.Rgbw8;
+  const componentWidth = packedRgb ? 1 : CHANNEL_SLOT_WIDTH[format];
+  const out = new Uint8Array(values.length * componentWidth);
+  const view = new DataView(out.buffer);
+  values.forEach((value, index) => {
+    const at = index * componentWidth;
+    switch (format) {
+      case ChannelFormat.U16:
+        view.setUint16(at, value);
+        break;
+      case ChannelFormat.U24:
+        view.setUint16(at, value >>> 8);
+        view.setUint8(at + 2, value & 0xff);
+        break;
+      case ChannelFormat.F32:
+        view.setFloat32(at, value);
+        break;
+      default:
+        view.setUint8(at, value);
+    }
+  });
+  return out;
+}
+
+export interface ChannelRange {
+  offset: number;
+  /** Packed like `channels`. */
+  values: Uint8Array;
+}
+
 export interface FrameEnvelope {
   type: MessageType.AlpineFrame;
   session_id: Uuid;
+  /** Logical stream within the session; omitted when 0. */
+  stream_id?: number;
+  /** u64 id of the sending stream instance, increasing per session; selects its epoch key. */
+  epoch: bigint;
+  /** Per-stream counter, starts at 1 in every epoch. */
+  sequence: number;
   timestamp_us: number;
   priority: number;
   channel_format: ChannelFormat;
-  channels: number[];
-  groups?: Record<string, number[]>;
+  /** Packed per `channel_format`; see `packChannels`. */
+  channels: Uint8Array;
+  groups?: Record<string, Uint8Array>;
   metadata?: Record<string, unknown>;
+  adaptation?: AdaptationHeader;
+  kind: FrameKind;
+  /** Delta frames only: sequence of the frame the ranges apply to. */
+  base_sequence?: number;
+  delta?: ChannelRange[];
+  /** Encrypted payload when confidentiality is negotiated. */
+  ciphertext?: Uint8Array;
+  /** ChaCha20-Poly1305 tag under the sender's epoch key. */
+  mac: Uint8Array;
 }
 
 export function buildFrameEnvelope(
   sessionId: Uuid,
+  epoch: bigint,
+  sequence: number,
   timestampUs: number,
   priority: number,
   channelFormat: ChannelFormat,
-  channels: number[],
-  groups?: Record<string, number[]>,
+  channels: Uint8Array,
+  mac: Uint8Array,
+  streamId?: number,
+  groups?: Record<string, Uint8Array>,
   metadata?: Record<string, unknown>,
+  adaptation?: AdaptationHeader,
+  ciphertext?: Uint8Array,
 ): FrameEnvelope {
   return {
     type: MessageType.AlpineFrame,
     session_id: sessionId,
+    stream_id: streamId || undefined,
+    epoch,
+    sequence,
     timestamp_us: timestampUs,
     priority,
     channel_format: channelFormat,
     channels,
     groups,
     metadata,
+    adaptation,
+    kind: FrameKind.Keyframe,
+    ciphertext,
+    mac,
   };
 }
 
@@ -10,11 +10,12 @@ use alpine::handshake::keepalive;
 use alpine::handshake::transport::{CborUdpTransport, ReliableControlChannel, TimeoutTransport};
 use alpine::handshake::{HandshakeContext, HandshakeError, HandshakeMessage, HandshakeTransport};
 use alpine::messages::{
-    Acknowledge, CapabilitySet, ChannelFormat, ControlEnvelope, ControlOp, DeviceIdentity,
+    Acknowledge, CapabilitySet, ChannelBuffer, ControlEnvelope, ControlOp, DeviceIdentity,
+    ReceiverReport,
 };
 use alpine::profile::StreamProfile;
 use alpine::session::{AlnpSession, Ed25519Authenticator};
-use alpine::stream::AlnpStream;
+use alpine::stream::{AlnpStream, ChannelGroups, KeyframeRequestOutcome, StreamEvent};
 use async_trait::async_trait;
 use serde::de::DeserializeOwned;
 use serde::Deserialize;
@@ -34,6 +35,7 @@ pub struct AlpineClient {
     local_addr: SocketAddr,
     remote_addr: SocketAddr,
     stream: Option<AlnpStream<UdpFrameTransport>>,
+    groups: ChannelGroups,
     control: ControlClient,
     keepalive_handle: Option<JoinHandle<()>>,
 }
@@ -150,6 +152,7 @@ impl AlpineClient {
             local_addr,
             remote_addr,
             stream: None,
+            groups: ChannelGroups::new(),
             control,
             keepalive_handle: Some(keepalive_handle),
         })
@@ -167,6 +170,7 @@ impl AlpineClient {
 
         let stream_socket = UdpFrameTransport::new(self.local_addr, self.remote_addr)?;
         let stream = AlnpStream::new(self.session.clone(), stream_socket, compiled.clone());
+        stream.define_groups(self.groups.clone());
         self.stream = Some(stream);
         Ok(compiled.config_id().to_string())
     }
@@ -174,10 +178,9 @@ impl AlpineClient {
     /// Sends a streaming frame over the active session.
     pub fn send_frame(
         &self,
-        channel_format: ChannelFormat,
-        channels: Vec<u16>,
+        channels: impl Into<ChannelBuffer>,
         priority: u8,
-        groups: Option<HashMap<String, Vec<u16>>>,
+        groups: Option<HashMap<String, ChannelBuffer>>,
         metadata: Option<HashMap<String, Value>>,
     ) -> Result<(), AlpineSdkError> {
         let stream = self
@@ -185,10 +188,46 @@ impl AlpineClient {
             .as_ref()
             .ok_or_else(|| AlpineSdkError::Io("stream not started".into()))?;
         stream
-            .send(channel_format, channels, priority, groups, metadata)
+            .send(channels, priority, groups, metadata)
             .map_err(AlpineSdkError::from)
     }
 
+    /// Feeds a receiver report datagram from the device into stream adaptation.
+    pub fn handle_receiver_report(&self, bytes: &[u8]) -> Result<ReceiverReport, AlpineSdkError> {
+        let stream = self
+            .stream
+            .as_ref()
+            .ok_or_else(|| AlpineSdkError::Io("stream not started".into()))?;
+        stream
+            .handle_receiver_report(bytes)
+            .map_err(AlpineSdkError::from)
+    }
+
+    /// Answers a keyframe request datagram from the device.
+    pub fn handle_keyframe_request(
+        &self,
+        bytes: &[u8],
+    ) -> Result<KeyframeRequestOutcome, AlpineSdkError> {
+        let stream = self
+            .stream
+            .as_ref()
+            .ok_or_else(|| AlpineSdkError::Io("stream not started".into()))?;
+        stream
+            .handle_keyframe_request(bytes)
+            .map_err(AlpineSdkError::from)
+    }
+
+    /// Subscribes to the stream's recovery and adaptation events.
+    pub fn subscribe_stream_events(
+        &self,
+    ) -> Result<tokio::sync::broadcast::Receiver<StreamEvent>, AlpineSdkError> {
+        let stream = self
+            .stream
+            .as_ref()
+            .ok_or_else(|| AlpineSdkError::Io("stream not started".into()))?;
+        Ok(stream.subscribe_events())
+    }
+
     /// Stops keep-alive and shuts down the session.
     pub async fn close(mut self) {
         self.session.close();
@@ -207,6 +246,26 @@ impl AlpineClient {
         self.control.envelope(seq, op, payload)
     }
 
+    /// Declares channel groups on the device; once it acknowledges them,
+    /// frames sent with `send_frame` may reference the groups by ID.
+    pub async fn define_groups(
+        &mut self,
+        groups: ChannelGroups,
+    ) -> Result<ControlReply<Value>, AlpineSdkError> {
+        let payload =
+            serde_json::to_value(&groups).map_err(|err| AlpineSdkError::Io(err.to_string()))?;
+        let reply = self
+            .control_request(ControlOp::DefineGroups, payload)
+            .await?;
+        if reply.ok() {
+            if let Some(stream) = self.stream.as_ref() {
+                stream.define_groups(groups.clone());
+            }
+            self.groups = groups;
+        }
+        Ok(reply)
+    }
+
     /// Sends a ping command and returns the parsed reply (CBOR payload optional).
     pub async fn ping(&self) -> Result<ControlReply<PingReply>, AlpineSdkError> {
         self.control_command("ping").await
@@ -9,4 +9,7 @@ pub mod transport;
 pub use client::AlpineClient;
 pub use discovery::{DiscoveryClient, DiscoveryClientOptions, DiscoveryError, DiscoveryOutcome};
 pub use error::AlpineSdkError;
-pub use transport::{quic::QuicFrameTransport, udp::UdpFrameTransport};
+pub use transport::{
+    quic::QuicFrameTransport,
+    udp::{AsyncUdpFrameTransport, UdpFrameTransport},
+};
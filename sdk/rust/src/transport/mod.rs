@@ -1,5 +1,5 @@
 pub mod udp;
 pub mod quic;
 
-pub use udp::UdpFrameTransport;
+pub use udp::{AsyncUdpFrameTransport, UdpFrameTransport};
 pub use quic::QuicFrameTransport;
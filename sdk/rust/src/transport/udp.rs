@@ -1,6 +1,8 @@
 use std::net::{SocketAddr, UdpSocket as StdUdpSocket};
 
-use alpine::stream::FrameTransport;
+use alpine::stream::{AsyncFrameTransport, FrameTransport};
+use async_trait::async_trait;
+use tokio::net::UdpSocket;
 
 /// UDP-based transport used by the SDK streaming client.
 #[derive(Debug)]
@@ -28,3 +30,29 @@ impl FrameTransport for UdpFrameTransport {
         Ok(())
     }
 }
+
+/// Tokio UDP transport for `AsyncAlnpStream`, for controllers that run on tokio.
+#[derive(Debug)]
+pub struct AsyncUdpFrameTransport {
+    socket: UdpSocket,
+}
+
+impl AsyncUdpFrameTransport {
+    /// Binds a tokio UDP socket to `local` and connects it to `peer`.
+    pub async fn new(local: SocketAddr, peer: SocketAddr) -> Result<Self, std::io::Error> {
+        let socket = UdpSocket::bind(local).await?;
+        socket.connect(peer).await?;
+        Ok(Self { socket })
+    }
+}
+
+#[async_trait]
+impl AsyncFrameTransport for AsyncUdpFrameTransport {
+    async fn send_frame(&self, bytes: &[u8]) -> Result<(), String> {
+        self.socket
+            .send(bytes)
+            .await
+            .map_err(|e| format!("udp stream send: {}", e))?;
+        Ok(())
+    }
+}